@@ -0,0 +1,266 @@
+use crate::record::amount::hex_to_decimal;
+use serde_json::{json, Map, Value};
+
+/// A Solidity ABI type, parsed from the `type`/`components` fields of an ABI JSON entry.
+#[derive(Clone, Debug)]
+pub enum ParamType {
+    Uint,
+    Int,
+    Address,
+    Bool,
+    FixedBytes(usize),
+    Bytes,
+    String,
+    Array(Box<ParamType>),
+    FixedArray(Box<ParamType>, usize),
+    Tuple(Vec<Param>),
+}
+
+#[derive(Clone, Debug)]
+pub struct Param {
+    pub name: String,
+    pub kind: ParamType,
+    /// Canonical type as used in signatures, e.g. `uint256` or `(address,uint24)[]`.
+    pub canonical: String,
+    pub indexed: bool,
+}
+
+impl Param {
+    /// Parses one entry of an ABI `inputs` array.
+    pub fn from_json(value: &Value) -> Result<Param, String> {
+        let type_name = value["type"].as_str().ok_or("ABI parameter is missing its type")?;
+        let components = value["components"].as_array()
+            .map(|components| components.iter().map(Param::from_json).collect::<Result<Vec<_>, _>>())
+            .transpose()?
+            .unwrap_or_default();
+        let (kind, canonical) = parse_type(type_name, &components)?;
+
+        Ok(Param {
+            name: value["name"].as_str().unwrap_or_default().to_string(),
+            kind,
+            canonical,
+            indexed: value["indexed"].as_bool().unwrap_or(false),
+        })
+    }
+}
+
+fn parse_type(type_name: &str, components: &[Param]) -> Result<(ParamType, String), String> {
+    // Array suffixes bind last, so `uint256[2][]` is a dynamic array of `uint256[2]`
+    if let Some(inner) = type_name.strip_suffix(']') {
+        let open = inner.rfind('[').ok_or_else(|| format!("Invalid ABI type '{}'", type_name))?;
+        let (element, size) = (&inner[..open], &inner[open + 1..]);
+        let (element_kind, element_canonical) = parse_type(element, components)?;
+        return if size.is_empty() {
+            Ok((ParamType::Array(Box::new(element_kind)), format!("{}[]", element_canonical)))
+        } else {
+            let size = size.parse().map_err(|_| format!("Invalid array size in '{}'", type_name))?;
+            Ok((ParamType::FixedArray(Box::new(element_kind), size), format!("{}[{}]", element_canonical, size)))
+        };
+    }
+
+    let kind = match type_name {
+        "address" => ParamType::Address,
+        "bool" => ParamType::Bool,
+        "string" => ParamType::String,
+        "bytes" => ParamType::Bytes,
+        "tuple" => {
+            let canonical = components.iter().map(|c| c.canonical.as_str()).collect::<Vec<_>>().join(",");
+            return Ok((ParamType::Tuple(components.to_vec()), format!("({})", canonical)));
+        }
+        "uint" => return Ok((ParamType::Uint, "uint256".to_string())),
+        "int" => return Ok((ParamType::Int, "int256".to_string())),
+        _ if type_name.starts_with("uint") => ParamType::Uint,
+        _ if type_name.starts_with("int") => ParamType::Int,
+        _ if type_name.starts_with("bytes") => {
+            let size = type_name[5..].parse().map_err(|_| format!("Invalid ABI type '{}'", type_name))?;
+            ParamType::FixedBytes(size)
+        }
+        _ => return Err(format!("Unsupported ABI type '{}'", type_name)),
+    };
+
+    Ok((kind, type_name.to_string()))
+}
+
+impl ParamType {
+    fn is_dynamic(&self) -> bool {
+        match self {
+            ParamType::Bytes | ParamType::String | ParamType::Array(_) => true,
+            ParamType::FixedArray(element, _) => element.is_dynamic(),
+            ParamType::Tuple(params) => params.iter().any(|p| p.kind.is_dynamic()),
+            _ => false,
+        }
+    }
+
+    /// Bytes this type takes up in the head of an encoding.
+    fn head_size(&self) -> usize {
+        match self {
+            _ if self.is_dynamic() => 32,
+            ParamType::FixedArray(element, size) => element.head_size() * size,
+            ParamType::Tuple(params) => params.iter().map(|p| p.kind.head_size()).sum(),
+            _ => 32,
+        }
+    }
+}
+
+fn word(data: &[u8], position: usize) -> Option<&[u8]> {
+    data.get(position..position.checked_add(32)?)
+}
+
+fn word_usize(data: &[u8], position: usize) -> Option<usize> {
+    let word = word(data, position)?;
+    if word[..24].iter().any(|&b| b != 0) {
+        return None;
+    }
+    Some(u64::from_be_bytes(word[24..].try_into().unwrap()) as usize)
+}
+
+fn int_to_decimal(word: &[u8]) -> Option<String> {
+    if word[0] & 0x80 == 0 {
+        return hex_to_decimal(&hex::encode(word));
+    }
+
+    // Two's complement: invert and add one to get the magnitude
+    let mut magnitude: Vec<u8> = word.iter().map(|b| !b).collect();
+    for byte in magnitude.iter_mut().rev() {
+        let (sum, overflow) = byte.overflowing_add(1);
+        *byte = sum;
+        if !overflow {
+            break;
+        }
+    }
+    hex_to_decimal(&hex::encode(magnitude)).map(|value| format!("-{}", value))
+}
+
+/// Decodes one value starting at `position`. Returns `None` when the data is too short or does
+/// not fit the type, which is how a wrong candidate signature is told apart from the right one.
+fn decode_value(kind: &ParamType, data: &[u8], position: usize) -> Option<Value> {
+    match kind {
+        ParamType::Uint => hex_to_decimal(&hex::encode(word(data, position)?)).map(Value::from),
+        ParamType::Int => int_to_decimal(word(data, position)?).map(Value::from),
+        ParamType::Address => {
+            let word = word(data, position)?;
+            word[..12].iter().all(|&b| b == 0).then(|| json!(format!("0x{}", hex::encode(&word[12..]))))
+        }
+        ParamType::Bool => match word_usize(data, position)? {
+            0 => Some(json!(false)),
+            1 => Some(json!(true)),
+            _ => None,
+        },
+        ParamType::FixedBytes(size) => Some(json!(format!("0x{}", hex::encode(word(data, position)?.get(..*size)?)))),
+        ParamType::Bytes | ParamType::String => {
+            let length = word_usize(data, position)?;
+            let bytes = data.get(position + 32..(position + 32).checked_add(length)?)?;
+            if let ParamType::String = kind {
+                Some(json!(String::from_utf8_lossy(bytes)))
+            } else {
+                Some(json!(format!("0x{}", hex::encode(bytes))))
+            }
+        }
+        ParamType::Array(element) => {
+            let length = word_usize(data, position)?;
+            // Every element takes at least one word, which bounds lengths read from garbage data
+            if length > data.len() / 32 {
+                return None;
+            }
+            let kinds = vec![element.as_ref().clone(); length];
+            decode_sequence(&kinds, data, position + 32).map(Value::Array)
+        }
+        ParamType::FixedArray(element, size) => {
+            let kinds = vec![element.as_ref().clone(); *size];
+            decode_sequence(&kinds, data, position).map(Value::Array)
+        }
+        ParamType::Tuple(params) => {
+            let kinds: Vec<ParamType> = params.iter().map(|p| p.kind.clone()).collect();
+            let values = decode_sequence(&kinds, data, position)?;
+            Some(Value::Object(named(params, values)))
+        }
+    }
+}
+
+/// Decodes consecutive values encoded with the ABI head/tail layout starting at `base`.
+fn decode_sequence(kinds: &[ParamType], data: &[u8], base: usize) -> Option<Vec<Value>> {
+    let mut values = Vec::with_capacity(kinds.len());
+    let mut head = base;
+
+    for kind in kinds {
+        let value = if kind.is_dynamic() {
+            let offset = word_usize(data, head)?;
+            decode_value(kind, data, base.checked_add(offset)?)?
+        } else {
+            decode_value(kind, data, head)?
+        };
+        values.push(value);
+        head += kind.head_size();
+    }
+
+    Some(values)
+}
+
+/// Pairs decoded values with parameter names, using `arg{i}` for unnamed parameters.
+pub fn named(params: &[Param], values: Vec<Value>) -> Map<String, Value> {
+    params.iter().zip(values).enumerate()
+        .map(|(i, (param, value))| {
+            let name = if param.name.is_empty() { format!("arg{}", i) } else { param.name.clone() };
+            (name, value)
+        })
+        .collect()
+}
+
+/// Decodes ABI-encoded `params` from `data`, requiring the encoding to be well formed.
+pub fn decode_params(params: &[Param], data: &[u8]) -> Option<Vec<Value>> {
+    let kinds: Vec<ParamType> = params.iter().map(|p| p.kind.clone()).collect();
+    decode_sequence(&kinds, data, 0)
+}
+
+/// Decodes a single static value stored in an indexed event topic. Indexed dynamic values are
+/// only stored as their keccak hash, so they are returned as the raw topic.
+pub fn decode_topic(param: &Param, topic: &[u8]) -> Option<Value> {
+    if param.kind.is_dynamic() || matches!(param.kind, ParamType::Tuple(_) | ParamType::FixedArray(..)) {
+        return Some(json!(format!("0x{}", hex::encode(topic))));
+    }
+    decode_value(&param.kind, topic, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(types: &[(&str, &str)]) -> Vec<Param> {
+        types.iter().map(|(name, kind)| Param::from_json(&json!({ "name": name, "type": kind })).unwrap()).collect()
+    }
+
+    #[test]
+    fn decodes_dynamic_strings_and_byte_arrays() {
+        // ("hello", [0x1234, 0xabcdef]): both heads are offsets, and so are the array's elements
+        let data = hex::decode(concat!(
+            "0000000000000000000000000000000000000000000000000000000000000040",
+            "0000000000000000000000000000000000000000000000000000000000000080",
+            "0000000000000000000000000000000000000000000000000000000000000005",
+            "68656c6c6f000000000000000000000000000000000000000000000000000000",
+            "0000000000000000000000000000000000000000000000000000000000000002",
+            "0000000000000000000000000000000000000000000000000000000000000040",
+            "0000000000000000000000000000000000000000000000000000000000000080",
+            "0000000000000000000000000000000000000000000000000000000000000002",
+            "1234000000000000000000000000000000000000000000000000000000000000",
+            "0000000000000000000000000000000000000000000000000000000000000003",
+            "abcdef0000000000000000000000000000000000000000000000000000000000",
+        )).unwrap();
+
+        let values = decode_params(&params(&[("name", "string"), ("blobs", "bytes[]")]), &data).unwrap();
+        assert_eq!(values, vec![json!("hello"), json!(["0x1234", "0xabcdef"])]);
+        // Cut off inside the second blob
+        assert!(decode_params(&params(&[("name", "string"), ("blobs", "bytes[]")]), &data[..data.len() - 32]).is_none());
+    }
+
+    #[test]
+    fn decodes_negative_int256_from_twos_complement() {
+        let data = hex::decode(concat!(
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffff8a432eb",
+            "000000000000000000000000000000000000000000000000000000000000002a",
+        )).unwrap();
+
+        let values = decode_params(&params(&[("a", "int256"), ("b", "int256"), ("c", "int256")]), &data).unwrap();
+        assert_eq!(values, vec![json!("-1"), json!("-123456789"), json!("42")]);
+    }
+}
@@ -0,0 +1,247 @@
+pub mod decode;
+
+use decode::{decode_params, decode_topic, named, Param};
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// ABIs compiled into the binary so common token and DEX activity decodes without setup.
+const BUNDLED_ABIS: &[(&str, &str)] = &[
+    ("erc20", include_str!("bundled/erc20.json")),
+    ("erc721", include_str!("bundled/erc721.json")),
+    ("erc1155", include_str!("bundled/erc1155.json")),
+    ("weth", include_str!("bundled/weth.json")),
+    ("uniswap_v2", include_str!("bundled/uniswap_v2.json")),
+    ("uniswap_v3", include_str!("bundled/uniswap_v3.json")),
+];
+
+pub struct Function {
+    pub name: String,
+    pub signature: String,
+    pub inputs: Vec<Param>,
+}
+
+pub struct Event {
+    pub name: String,
+    pub signature: String,
+    pub inputs: Vec<Param>,
+}
+
+/// A call or event matched against a known ABI entry.
+pub struct Decoded<'a> {
+    pub name: &'a str,
+    pub signature: &'a str,
+    pub arguments: Value,
+}
+
+/// Function selectors and event topics from every loaded ABI.
+///
+/// Different contracts reuse the same selector or topic with different parameter names or
+/// indexing (ERC-20 and ERC-721 `Transfer`), so each key can hold several candidates; the first
+/// one whose layout fits the data wins.
+#[derive(Default)]
+pub struct AbiRegistry {
+    functions: HashMap<[u8; 4], Vec<Function>>,
+    events: HashMap<[u8; 32], Vec<Event>>,
+    seen: HashSet<String>,
+}
+
+fn keccak(input: &str) -> [u8; 32] {
+    Keccak256::digest(input.as_bytes()).into()
+}
+
+fn signature(name: &str, inputs: &[Param]) -> String {
+    format!("{}({})", name, inputs.iter().map(|p| p.canonical.as_str()).collect::<Vec<_>>().join(","))
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    hex::decode(value.trim_start_matches("0x")).ok()
+}
+
+impl AbiRegistry {
+    /// Loads the bundled ABIs plus every `*.json` file in `dir`. Files may hold a bare ABI array
+    /// or a compiler artifact with an `abi` field; unreadable files are reported and skipped.
+    pub fn load(dir: &Path) -> Self {
+        let mut registry = AbiRegistry::default();
+        for (name, abi) in BUNDLED_ABIS {
+            let abi: Value = serde_json::from_str(abi).unwrap_or_else(|e| panic!("Bundled ABI {} is invalid: {}", name, e));
+            if let Err(e) = registry.add_abi(&abi) {
+                panic!("Bundled ABI {} is invalid: {}", name, e);
+            }
+        }
+
+        let Ok(entries) = fs::read_dir(dir) else {
+            return registry;
+        };
+        let mut paths: Vec<_> = entries.filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let result = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|contents| serde_json::from_str::<Value>(&contents).map_err(|e| e.to_string()))
+                .and_then(|abi| registry.add_abi(&abi));
+            if let Err(e) = result {
+                eprintln!("Skipping ABI file {}: {}", path.display(), e);
+            }
+        }
+
+        registry
+    }
+
+    fn add_abi(&mut self, abi: &Value) -> Result<(), String> {
+        let entries = abi.as_array().or_else(|| abi["abi"].as_array()).ok_or("expected an ABI array or an object with an `abi` field")?;
+
+        for entry in entries {
+            let entry_type = entry["type"].as_str().unwrap_or("function");
+            if entry_type != "function" && entry_type != "event" {
+                continue;
+            }
+            if entry["anonymous"].as_bool().unwrap_or(false) {
+                continue;
+            }
+
+            let name = entry["name"].as_str().ok_or("ABI entry is missing its name")?.to_string();
+            let inputs = entry["inputs"].as_array().into_iter().flatten()
+                .map(Param::from_json)
+                .collect::<Result<Vec<_>, _>>()?;
+            let signature = signature(&name, &inputs);
+
+            // The same signature appears in many ABIs; keep one per distinct indexing layout
+            let indexed: String = inputs.iter().map(|p| if p.indexed { '1' } else { '0' }).collect();
+            if !self.seen.insert(format!("{} {} {}", entry_type, signature, indexed)) {
+                continue;
+            }
+
+            let hash = keccak(&signature);
+            if entry_type == "function" {
+                let selector = hash[..4].try_into().unwrap();
+                self.functions.entry(selector).or_default().push(Function { name, signature, inputs });
+            } else {
+                self.events.entry(hash).or_default().push(Event { name, signature, inputs });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decodes transaction or trace calldata by its 4-byte selector.
+    pub fn decode_call(&self, input: &str) -> Option<Decoded<'_>> {
+        let input = decode_hex(input)?;
+        let selector: [u8; 4] = input.get(..4)?.try_into().ok()?;
+
+        self.functions.get(&selector)?.iter().find_map(|function| {
+            let values = decode_params(&function.inputs, &input[4..])?;
+            Some(Decoded {
+                name: &function.name,
+                signature: &function.signature,
+                arguments: Value::Object(named(&function.inputs, values)),
+            })
+        })
+    }
+
+    /// Decodes a log from its topics and data, matching `topics[0]` to an event signature.
+    pub fn decode_event(&self, topics: &[&str], data: &str) -> Option<Decoded<'_>> {
+        let topics: Vec<Vec<u8>> = topics.iter().map(|topic| decode_hex(topic)).collect::<Option<_>>()?;
+        let topic_0: [u8; 32] = topics.first()?.as_slice().try_into().ok()?;
+        let data = decode_hex(data).unwrap_or_default();
+
+        self.events.get(&topic_0)?.iter().find_map(|event| {
+            let indexed_count = event.inputs.iter().filter(|p| p.indexed).count();
+            if topics.len() != indexed_count + 1 {
+                return None;
+            }
+
+            let data_params: Vec<Param> = event.inputs.iter().filter(|p| !p.indexed).cloned().collect();
+            let mut data_values = decode_params(&data_params, &data)?.into_iter();
+            let mut indexed_topics = topics[1..].iter();
+
+            let mut values = Vec::with_capacity(event.inputs.len());
+            for param in &event.inputs {
+                if param.indexed {
+                    values.push(decode_topic(param, indexed_topics.next()?)?);
+                } else {
+                    values.push(data_values.next()?);
+                }
+            }
+
+            Some(Decoded {
+                name: &event.name,
+                signature: &event.signature,
+                arguments: Value::Object(named(&event.inputs, values)),
+            })
+        })
+    }
+
+    /// Builds `{chain}_decoded_calls` rows from transaction or trace rows. Transactions have no
+    /// `trace_address` and are stored under the top-level call's empty one, so a transaction and
+    /// its top-level trace share a row.
+    pub fn decoded_call_rows(&self, rows: &[Value]) -> Vec<Value> {
+        rows.iter().filter_map(|row| {
+            // Creation input is init code, not calldata
+            if row["trace_type"] == "create" {
+                return None;
+            }
+            let contract = row["to_address"].as_str()?;
+            let input = row["input"].as_str()?;
+            let decoded = self.decode_call(input)?;
+
+            Some(json!({
+                "transaction_hash": row["transaction_hash"],
+                "trace_address": row["trace_address"].as_str().unwrap_or(""),
+                "block_number": row["block_number"],
+                "timestamp": row["timestamp"],
+                "from_address": row["from_address"],
+                "contract_address": contract,
+                "selector": format!("0x{}", input.trim_start_matches("0x").get(..8)?.to_lowercase()),
+                "function_name": decoded.name,
+                "signature": decoded.signature,
+                "arguments": decoded.arguments.to_string(),
+            }))
+        }).collect()
+    }
+
+    /// Builds `{chain}_decoded_events` rows from `{chain}_logs` rows.
+    pub fn decoded_event_rows(&self, logs: &[Value]) -> Vec<Value> {
+        logs.iter().filter_map(|log| {
+            let topics: Vec<&str> = ["topic_0", "topic_1", "topic_2", "topic_3"].iter()
+                .map_while(|key| log[*key].as_str())
+                .collect();
+            let decoded = self.decode_event(&topics, log["data"].as_str().unwrap_or("0x"))?;
+
+            Some(json!({
+                "transaction_hash": log["transaction_hash"],
+                "log_index": log["log_index"],
+                "block_number": log["block_number"],
+                "timestamp": log["timestamp"],
+                "contract_address": log["address"],
+                "topic_0": topics[0],
+                "event_name": decoded.name,
+                "signature": decoded.signature,
+                "arguments": decoded.arguments.to_string(),
+            }))
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_erc20_transfer_calldata_by_selector() {
+        let registry = AbiRegistry::load(Path::new("no-such-abi-dir"));
+        // transfer(0x28c6…1d60, 1000000)
+        let input = "0xa9059cbb00000000000000000000000028c6c06298d514db089934071355e5743bf21d6000000000000000000000000000000000000000000000000000000000000f4240";
+
+        let decoded = registry.decode_call(input).unwrap();
+        assert_eq!(decoded.signature, "transfer(address,uint256)");
+        assert_eq!(decoded.arguments.as_object().unwrap().values().collect::<Vec<_>>(), vec![&json!("0x28c6c06298d514db089934071355e5743bf21d60"), &json!("1000000")]);
+        // Too short for the arguments, so no candidate fits
+        assert!(registry.decode_call(&input[..input.len() - 64]).is_none());
+    }
+}
@@ -0,0 +1,111 @@
+use super::AddressError;
+use bech32::{hrp, Hrp};
+use std::fmt;
+use std::str::FromStr;
+
+/// Base58 version bytes for P2PKH and P2SH addresses on mainnet and the test networks.
+const BASE58_VERSIONS: &[u8] = &[0x00, 0x05, 0x6f, 0xc4];
+const SEGWIT_HRPS: &[Hrp] = &[hrp::BC, hrp::TB, hrp::BCRT];
+
+/// A Bitcoin address: legacy base58check (P2PKH, P2SH), bech32 segwit v0 or bech32m segwit v1+
+/// (taproot).
+///
+/// Bech32 addresses may be written in upper case (as QR codes often are) but are stored in
+/// lower case, which is what Esplora and `blk*.dat` ingestion produce. Base58 is case sensitive
+/// and kept as given.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BitcoinAddress(String);
+
+impl FromStr for BitcoinAddress {
+    type Err = AddressError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let trimmed = input.trim();
+
+        // Legacy addresses with the accepted version bytes start with 1, 3, m, n or 2, so the
+        // prefix decides which encoding to check
+        let lower = trimmed.to_lowercase();
+        if lower.starts_with("bc1") || lower.starts_with("tb1") || lower.starts_with("bcrt1") {
+            let (hrp, _, _) = bech32::segwit::decode(trimmed).map_err(|e| AddressError::new(input, "Bitcoin", e.to_string()))?;
+            if !SEGWIT_HRPS.contains(&hrp) {
+                return Err(AddressError::new(input, "Bitcoin", format!("unknown network prefix '{}'", hrp)));
+            }
+            return Ok(BitcoinAddress(lower));
+        }
+
+        let payload = bs58::decode(trimmed).with_check(None).into_vec()
+            .map_err(|e| AddressError::new(input, "Bitcoin", e.to_string()))?;
+        match payload.split_first() {
+            Some((version, hash)) if BASE58_VERSIONS.contains(version) && hash.len() == 20 => Ok(BitcoinAddress(trimmed.to_string())),
+            Some((version, _)) if !BASE58_VERSIONS.contains(version) => {
+                Err(AddressError::new(input, "Bitcoin", format!("unknown version byte 0x{:02x}", version)))
+            }
+            _ => Err(AddressError::new(input, "Bitcoin", "expected a 20-byte hash")),
+        }
+    }
+}
+
+impl fmt::Display for BitcoinAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_base58_addresses() {
+        for input in [
+            "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa",
+            "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy",
+            "mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn",
+        ] {
+            assert_eq!(input.parse::<BitcoinAddress>().unwrap().to_string(), input);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_base58() {
+        for input in [
+            // Last character changed, so the checksum fails
+            "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb",
+            // 0, O, I and l are not in the base58 alphabet
+            "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfN0",
+            // A Solana key, which has no base58check checksum
+            "11111111111111111111111111111111",
+            "",
+        ] {
+            assert!(input.parse::<BitcoinAddress>().is_err(), "{} was accepted", input);
+        }
+    }
+
+    /// Vectors from BIP 173 and BIP 350.
+    #[test]
+    fn accepts_bech32_and_bech32m_addresses() {
+        for (input, stored) in [
+            ("BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4", "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"),
+            ("tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7", "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7"),
+            ("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0", "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0"),
+        ] {
+            assert_eq!(input.parse::<BitcoinAddress>().unwrap().to_string(), stored);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_bech32() {
+        for input in [
+            // Checksum of the first valid vector with its last character changed
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5",
+            // Witness v1 with a bech32 rather than bech32m checksum
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd",
+            // Mixed case
+            "bc1qW508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+            // Witness v0 program of the wrong length
+            "bc1qr508d6qejxtdg4y5r3zarvarya3ef5h",
+        ] {
+            assert!(input.parse::<BitcoinAddress>().is_err(), "{} was accepted", input);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use super::AddressError;
+use sha3::{Digest, Keccak256};
+use std::fmt;
+use std::str::FromStr;
+
+/// A 20-byte EVM address.
+///
+/// Parsing accepts all-lowercase and all-uppercase hex as is, and mixed-case hex only if it is a
+/// valid EIP-55 checksum, which catches most typos. `Display` writes the lowercase form that
+/// providers return and tables are keyed on; use `checksummed` for anything shown to a user.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EvmAddress([u8; 20]);
+
+impl EvmAddress {
+    /// The EIP-55 mixed-case form: each hex letter is uppercased when the matching nibble of the
+    /// keccak hash of the lowercase address is 8 or more.
+    pub fn checksummed(&self) -> String {
+        let lower = hex::encode(self.0);
+        let hash = Keccak256::digest(lower.as_bytes());
+
+        let mut checksummed = String::with_capacity(42);
+        checksummed.push_str("0x");
+        for (i, c) in lower.chars().enumerate() {
+            let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+            checksummed.push(if nibble >= 8 { c.to_ascii_uppercase() } else { c });
+        }
+        checksummed
+    }
+}
+
+impl FromStr for EvmAddress {
+    type Err = AddressError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let trimmed = input.trim();
+        let digits = trimmed.strip_prefix("0x")
+            .ok_or_else(|| AddressError::new(input, "EVM", "expected a 0x prefix"))?;
+        if digits.len() != 40 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(AddressError::new(input, "EVM", "expected 40 hex characters after 0x"));
+        }
+
+        let mut bytes = [0u8; 20];
+        hex::decode_to_slice(digits, &mut bytes).map_err(|e| AddressError::new(input, "EVM", e.to_string()))?;
+        let address = EvmAddress(bytes);
+
+        let mixed_case = digits.chars().any(|c| c.is_ascii_lowercase()) && digits.chars().any(|c| c.is_ascii_uppercase());
+        if mixed_case && address.checksummed() != trimmed {
+            return Err(AddressError::new(input, "EVM", format!("bad EIP-55 checksum, expected {}", address.checksummed())));
+        }
+
+        Ok(address)
+    }
+}
+
+impl fmt::Display for EvmAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", hex::encode(self.0))
+    }
+}
+
+/// An EVM address as typed by a user: hex, or an ENS name that is resolved before querying.
+#[derive(Clone, Debug)]
+pub enum NameOrAddress {
+    Name(String),
+    Address(EvmAddress),
+}
+
+impl FromStr for NameOrAddress {
+    type Err = AddressError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let trimmed = input.trim();
+        if !trimmed.to_lowercase().ends_with(".eth") {
+            return trimmed.parse().map(NameOrAddress::Address);
+        }
+
+        // Full ENSIP-15 normalisation is not applied; ASCII names only need lowercasing
+        let name = trimmed.to_lowercase();
+        if name.split('.').any(|label| label.is_empty() || label.chars().any(|c| c.is_whitespace())) {
+            return Err(AddressError::new(input, "ENS", "labels must be non-empty and contain no whitespace"));
+        }
+        Ok(NameOrAddress::Name(name))
+    }
+}
+
+impl fmt::Display for NameOrAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NameOrAddress::Name(name) => f.write_str(name),
+            NameOrAddress::Address(address) => address.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The test vectors from EIP-55.
+    const EIP55: &[&str] = &[
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ];
+
+    #[test]
+    fn checksums_the_eip55_vectors() {
+        for vector in EIP55 {
+            let address: EvmAddress = vector.parse().unwrap();
+            assert_eq!(address.checksummed(), *vector);
+            assert_eq!(address.to_string(), vector.to_lowercase());
+        }
+    }
+
+    #[test]
+    fn accepts_single_case_hex() {
+        for vector in EIP55 {
+            let upper = format!("0x{}", vector[2..].to_uppercase());
+            assert_eq!(vector.to_lowercase().parse::<EvmAddress>().unwrap(), upper.parse::<EvmAddress>().unwrap());
+        }
+    }
+
+    #[test]
+    fn rejects_a_bad_checksum() {
+        // One letter of the first vector in the wrong case
+        let error = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD".parse::<EvmAddress>().unwrap_err();
+        assert!(error.reason.contains("EIP-55"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_malformed_hex() {
+        for input in [
+            "5aaeb6053f3e94c9b9a09f33669435e7ef1beaed",
+            "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beae",
+            "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed00",
+            "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaeg",
+        ] {
+            assert!(input.parse::<EvmAddress>().is_err(), "{} was accepted", input);
+        }
+    }
+}
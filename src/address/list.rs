@@ -0,0 +1,206 @@
+use super::AddressError;
+use crate::error::{Error, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Where a listed address was read from.
+#[derive(Clone, Debug)]
+pub struct Origin {
+    /// The file's canonical path, or the path as given when it cannot be canonicalized.
+    pub file: String,
+    /// 1-based line of a text or CSV file, or 1-based entry of a JSON array.
+    pub line: usize,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+/// One address to investigate, with the label and notes it was listed with.
+#[derive(Clone, Debug)]
+pub struct ListedAddress<T> {
+    pub address: T,
+    /// The address as it was written, before parsing.
+    pub input: String,
+    pub label: Option<String>,
+    pub notes: Option<String>,
+    /// Every file entry that listed the address, in the order they were read. Empty for an
+    /// address only given on the command line.
+    pub origins: Vec<Origin>,
+}
+
+/// What loading one file added to a list.
+pub struct LoadSummary {
+    pub added: usize,
+    pub duplicates: usize,
+    /// Why each rejected entry was skipped, prefixed with where it was.
+    pub invalid: Vec<String>,
+}
+
+/// Addresses gathered from the command line and from address list files, deduplicated in the
+/// order they were first seen.
+///
+/// Files are read by extension: `.csv` files take an `address` column, plus optional `label`
+/// and `notes` columns, and without a header row the first three columns are read in that
+/// order. `.json` files hold an array of address strings or of objects with the same fields.
+/// Anything else is read as text with one address per line, optionally followed by a label;
+/// blank lines and lines starting with `#` are skipped.
+pub struct AddressList<T> {
+    entries: Vec<ListedAddress<T>>,
+    /// Index into `entries` by the address's `Display` form.
+    seen: HashMap<String, usize>,
+}
+
+impl<T> Default for AddressList<T> {
+    fn default() -> Self {
+        AddressList { entries: Vec::new(), seen: HashMap::new() }
+    }
+}
+
+impl<T: FromStr<Err = AddressError> + fmt::Display + Clone> AddressList<T> {
+    /// A list holding the addresses given on the command line.
+    pub fn new(addresses: &[T]) -> Self {
+        let mut list = AddressList::default();
+        for address in addresses {
+            list.insert(ListedAddress { address: address.clone(), input: address.to_string(), label: None, notes: None, origins: Vec::new() });
+        }
+        list
+    }
+
+    /// Adds the addresses listed in `path`. Entries that do not parse are skipped and reported in
+    /// the summary; a file that cannot be read or decoded at all is an error.
+    pub fn load(&mut self, path: &Path) -> Result<LoadSummary> {
+        let contents = fs::read_to_string(path).map_err(|e| Error::Validation(format!("Cannot read address list {}: {}", path.display(), e)))?;
+        let file = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()).display().to_string();
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("").to_lowercase();
+
+        let rows = match extension.as_str() {
+            "csv" => csv_rows(&contents),
+            "json" => json_rows(&contents),
+            _ => Ok(text_rows(&contents)),
+        }.map_err(|e| Error::Validation(format!("Cannot parse address list {}: {}", path.display(), e)))?;
+
+        let mut summary = LoadSummary { added: 0, duplicates: 0, invalid: Vec::new() };
+        for row in rows {
+            let origin = Origin { file: file.clone(), line: row.line };
+            match row.address.parse::<T>() {
+                Ok(address) => {
+                    let listed = ListedAddress { address, input: row.address, label: row.label, notes: row.notes, origins: vec![origin] };
+                    match self.insert(listed) {
+                        true => summary.added += 1,
+                        false => summary.duplicates += 1,
+                    }
+                }
+                Err(e) => summary.invalid.push(format!("{}: {}", origin, e)),
+            }
+        }
+        Ok(summary)
+    }
+
+    /// Adds `listed` unless its address is already in the list, in which case its origins are
+    /// added to the first entry's, along with any label or notes that one lacks. Returns whether
+    /// it was new.
+    fn insert(&mut self, listed: ListedAddress<T>) -> bool {
+        let key = listed.address.to_string();
+        if let Some(&index) = self.seen.get(&key) {
+            let existing = &mut self.entries[index];
+            existing.label = existing.label.take().or(listed.label);
+            existing.notes = existing.notes.take().or(listed.notes);
+            for origin in listed.origins {
+                if !existing.origins.iter().any(|seen| seen.file == origin.file) {
+                    existing.origins.push(origin);
+                }
+            }
+            return false;
+        }
+        self.seen.insert(key, self.entries.len());
+        self.entries.push(listed);
+        true
+    }
+}
+
+impl<T: Clone> AddressList<T> {
+    pub fn entries(&self) -> &[ListedAddress<T>] {
+        &self.entries
+    }
+
+    pub fn addresses(&self) -> Vec<T> {
+        self.entries.iter().map(|listed| listed.address.clone()).collect()
+    }
+}
+
+/// An entry as read from a file, before its address is parsed.
+struct RawRow {
+    line: usize,
+    address: String,
+    label: Option<String>,
+    notes: Option<String>,
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value.map(str::trim).filter(|value| !value.is_empty()).map(str::to_string)
+}
+
+fn text_rows(contents: &str) -> Vec<RawRow> {
+    contents.lines().enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line, text)| {
+            let (address, label) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+            RawRow { line, address: address.to_string(), label: non_empty(Some(label)), notes: None }
+        })
+        .collect()
+}
+
+fn csv_rows(contents: &str) -> Result<Vec<RawRow>> {
+    let mut reader = csv::ReaderBuilder::new().has_headers(false).flexible(true).trim(csv::Trim::All).from_reader(contents.as_bytes());
+    let mut records = reader.records().peekable();
+
+    // (address, label, notes) column indices
+    let mut columns = (Some(0), Some(1), Some(2));
+    if let Some(Ok(first)) = records.peek() {
+        let column = |name: &str| first.iter().position(|header| header.eq_ignore_ascii_case(name));
+        if let Some(address) = column("address") {
+            columns = (Some(address), column("label"), column("notes"));
+            records.next();
+        }
+    }
+
+    let mut rows = Vec::new();
+    for record in records {
+        let record = record?;
+        let line = record.position().map_or(0, |position| position.line() as usize);
+        let field = |index: Option<usize>| index.and_then(|index| record.get(index));
+        let address = field(columns.0).unwrap_or("");
+        if address.is_empty() && record.iter().all(str::is_empty) {
+            continue;
+        }
+        rows.push(RawRow { line, address: address.to_string(), label: non_empty(field(columns.1)), notes: non_empty(field(columns.2)) });
+    }
+    Ok(rows)
+}
+
+fn json_rows(contents: &str) -> Result<Vec<RawRow>> {
+    let entries: Vec<Value> = serde_json::from_str(contents).map_err(|e| Error::Validation(e.to_string()))?;
+    let mut rows = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let row = match entry {
+            Value::String(address) => RawRow { line: i + 1, address: address.clone(), label: None, notes: None },
+            Value::Object(fields) => RawRow {
+                line: i + 1,
+                address: fields.get("address").and_then(Value::as_str).unwrap_or("").to_string(),
+                label: non_empty(fields.get("label").and_then(Value::as_str)),
+                notes: non_empty(fields.get("notes").and_then(Value::as_str)),
+            },
+            _ => return Err(Error::Validation(format!("entry {} is neither an address nor an object with an address", i + 1))),
+        };
+        rows.push(row);
+    }
+    Ok(rows)
+}
@@ -0,0 +1,33 @@
+pub mod bitcoin;
+pub mod evm;
+pub mod list;
+pub mod solana;
+
+pub use self::bitcoin::BitcoinAddress;
+pub use evm::{EvmAddress, NameOrAddress};
+pub use list::AddressList;
+pub use solana::SolanaAddress;
+
+use std::fmt;
+
+/// Why a string was rejected as an address.
+#[derive(Debug)]
+pub struct AddressError {
+    pub input: String,
+    pub kind: &'static str,
+    pub reason: String,
+}
+
+impl AddressError {
+    fn new(input: &str, kind: &'static str, reason: impl Into<String>) -> Self {
+        AddressError { input: input.to_string(), kind, reason: reason.into() }
+    }
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' is not a valid {} address: {}", self.input, self.kind, self.reason)
+    }
+}
+
+impl std::error::Error for AddressError {}
@@ -0,0 +1,56 @@
+use super::AddressError;
+use std::fmt;
+use std::str::FromStr;
+
+/// A Solana account address: a 32-byte public key (or program-derived address) in base58.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SolanaAddress([u8; 32]);
+
+impl FromStr for SolanaAddress {
+    type Err = AddressError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut bytes = [0u8; 32];
+        let length = bs58::decode(input.trim()).onto(&mut bytes)
+            .map_err(|e| AddressError::new(input, "Solana", e.to_string()))?;
+        if length != 32 {
+            return Err(AddressError::new(input, "Solana", format!("expected 32 bytes, got {}", length)));
+        }
+        Ok(SolanaAddress(bytes))
+    }
+}
+
+impl fmt::Display for SolanaAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&bs58::encode(self.0).into_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_base58_keys() {
+        for input in ["11111111111111111111111111111111", "So11111111111111111111111111111111111111112"] {
+            assert_eq!(input.parse::<SolanaAddress>().unwrap().to_string(), input);
+        }
+        assert_eq!(" 11111111111111111111111111111111 ".parse::<SolanaAddress>().unwrap(), SolanaAddress([0; 32]));
+    }
+
+    #[test]
+    fn rejects_invalid_base58() {
+        for input in [
+            // 0, O, I and l are not in the base58 alphabet
+            "So1111111111111111111111111111111111111111O",
+            // Too short and too long for a 32-byte key
+            "1111111111111111111111111111111",
+            "So111111111111111111111111111111111111111121",
+            // A Bitcoin address decodes to 25 bytes
+            "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa",
+            "",
+        ] {
+            assert!(input.parse::<SolanaAddress>().is_err(), "{} was accepted", input);
+        }
+    }
+}
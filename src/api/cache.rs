@@ -0,0 +1,242 @@
+use crate::config::Config;
+use crate::error::{Error, Result};
+use chrono::Utc;
+use reqwest::Request;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CACHE_DIR: &str = "data/cache/http";
+/// How long a response is served from the cache when `cache.ttl_seconds` has no entry for its
+/// provider. Transpose queries cost credits, so they are kept for a week; node and explorer
+/// responses for an hour, so incremental syncs see new blocks soon after they land.
+const DEFAULT_TTLS: &[(&str, i64)] = &[
+    ("transpose", 7 * 24 * 60 * 60),
+    ("rpc", 60 * 60),
+    ("esplora", 60 * 60),
+    ("solana", 60 * 60),
+];
+
+/// Whether a response may answer the same request again in a later online run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reuse {
+    /// The answer cannot change, such as a mined transaction or a full page of history.
+    Stable,
+    /// The answer depends on the chain head or on current state, such as `eth_blockNumber`, an
+    /// account balance or the short last page of a history. It is still stored, so `--offline`
+    /// can replay the run, but online it is always fetched again.
+    Volatile,
+}
+
+/// A successful response as stored on disk, with the request that produced it so a cache
+/// directory can be audited or shared along with an investigation.
+#[derive(Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub provider: String,
+    pub method: String,
+    pub url: String,
+    pub request: String,
+    pub status: u16,
+    pub fetched_at: i64,
+    /// Entries written before responses were told apart are not reused online.
+    #[serde(default)]
+    pub reusable: bool,
+    pub body: String,
+}
+
+/// Provider responses kept on disk, addressed by a hash of the provider, method, URL and
+/// request body. Request headers are left out of the key, so changing an API key does not
+/// throw the cache away.
+pub struct ResponseCache {
+    dir: PathBuf,
+    provider: &'static str,
+    ttl_seconds: i64,
+    /// Whether fresh responses are stored and served; `--offline` reads the cache regardless.
+    enabled: bool,
+    offline: bool,
+}
+
+impl ResponseCache {
+    pub fn new(config: &Config, provider: &'static str) -> Self {
+        let ttl_seconds = config.cache.ttl_seconds.get(provider).map(|&ttl| ttl as i64)
+            .or_else(|| DEFAULT_TTLS.iter().find(|(name, _)| *name == provider).map(|(_, ttl)| *ttl))
+            .unwrap_or(0);
+
+        ResponseCache {
+            dir: PathBuf::from(CACHE_DIR),
+            provider,
+            ttl_seconds,
+            enabled: config.cache.enabled,
+            offline: config.offline,
+        }
+    }
+
+    /// Whether requests must be answered from the cache alone.
+    pub fn offline(&self) -> bool {
+        self.offline
+    }
+
+    /// The content address of `request`. JSON-RPC ids are left out, since they number the calls
+    /// of one run rather than say anything about the call itself.
+    pub fn key(&self, request: &Request) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.provider.as_bytes());
+        hasher.update(b"\n");
+        hasher.update(request.method().as_str().as_bytes());
+        hasher.update(b"\n");
+        hasher.update(request.url().as_str().as_bytes());
+        hasher.update(b"\n");
+        hasher.update(canonical_body(request).as_bytes());
+        hex::encode(hasher.finalize())
+    }
+
+    /// The cached response for `key`. Online, only stable responses younger than the provider's
+    /// TTL are served; offline, any cached response is, however old, so a past run can be replayed.
+    pub fn get(&self, key: &str) -> Option<CachedResponse> {
+        if !self.enabled && !self.offline {
+            return None;
+        }
+        let cached: CachedResponse = serde_json::from_str(&fs::read_to_string(self.path(key)).ok()?).ok()?;
+        match self.offline || (cached.reusable && Utc::now().timestamp() - cached.fetched_at < self.ttl_seconds) {
+            true => Some(cached),
+            false => None,
+        }
+    }
+
+    /// Stores a successful response to `request`. JSON-RPC errors arrive with a 200 status, so
+    /// JSON bodies carrying an `error` member, or batches with one in any answer, are not stored,
+    /// lest a transient node error be replayed until it expires.
+    pub fn put(&self, key: &str, request: &Request, status: u16, body: &[u8], reuse: Reuse) -> Result<()> {
+        if !self.enabled || self.ttl_seconds <= 0 {
+            return Ok(());
+        }
+        let Ok(body) = std::str::from_utf8(body) else {
+            return Ok(());
+        };
+        let has_error = |value: &Value| value.get("error").is_some();
+        if serde_json::from_str::<Value>(body).is_ok_and(|value| has_error(&value) || value.as_array().is_some_and(|batch| batch.iter().any(has_error))) {
+            return Ok(());
+        }
+
+        let cached = CachedResponse {
+            provider: self.provider.to_string(),
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            request: canonical_body(request),
+            status,
+            fetched_at: Utc::now().timestamp(),
+            reusable: reuse == Reuse::Stable,
+            body: body.to_string(),
+        };
+        let path = self.path(key);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Written aside and renamed, so a concurrent reader never sees half an entry
+        let partial = path.with_extension("partial");
+        fs::write(&partial, serde_json::to_string(&cached).map_err(|e| Error::Storage(e.to_string()))?)?;
+        fs::rename(&partial, &path)?;
+        Ok(())
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(&key[..2]).join(format!("{}.json", key))
+    }
+}
+
+/// The request body as text, with the `id` of a JSON-RPC request, or of each request in a
+/// batch, removed.
+fn canonical_body(request: &Request) -> String {
+    fn is_rpc(value: &Value) -> bool {
+        value.get("jsonrpc").is_some()
+    }
+
+    let body = request.body().and_then(|body| body.as_bytes()).unwrap_or_default();
+    match serde_json::from_slice::<Value>(body) {
+        Ok(Value::Array(mut batch)) if !batch.is_empty() && batch.iter().all(is_rpc) => {
+            for fields in batch.iter_mut().filter_map(Value::as_object_mut) {
+                fields.remove("id");
+            }
+            Value::Array(batch).to_string()
+        }
+        Ok(Value::Object(mut fields)) if fields.contains_key("jsonrpc") => {
+            fields.remove("id");
+            Value::Object(fields).to_string()
+        }
+        _ => String::from_utf8_lossy(body).into_owned(),
+    }
+}
+
+/// Deletes every cached response, returning how many there were.
+pub fn clear() -> Result<usize> {
+    let dir = Path::new(CACHE_DIR);
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let mut count = 0;
+    for shard in fs::read_dir(dir)? {
+        let shard = shard?.path();
+        if shard.is_dir() {
+            count += fs::read_dir(&shard)?.count();
+        }
+    }
+    fs::remove_dir_all(dir)?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::Client;
+    use serde_json::json;
+
+    const URL: &str = "http://127.0.0.1:8545/";
+
+    fn cache(provider: &'static str) -> ResponseCache {
+        ResponseCache { dir: PathBuf::from(CACHE_DIR), provider, ttl_seconds: 60, enabled: true, offline: false }
+    }
+
+    fn post(body: Value) -> Request {
+        Client::new().post(URL).json(&body).build().unwrap()
+    }
+
+    fn call(id: u64, block: &str) -> Value {
+        json!({ "jsonrpc": "2.0", "id": id, "method": "eth_getBlockByNumber", "params": [block, false] })
+    }
+
+    #[test]
+    fn keys_json_rpc_calls_without_their_id() {
+        let rpc = cache("rpc");
+        assert_eq!(rpc.key(&post(call(1, "0x10"))), rpc.key(&post(call(7, "0x10"))));
+        assert_ne!(rpc.key(&post(call(1, "0x10"))), rpc.key(&post(call(1, "0x11"))));
+        assert_ne!(rpc.key(&post(call(1, "0x10"))), cache("ens").key(&post(call(1, "0x10"))));
+
+        let with_key = Client::new().post(URL).header("X-API-KEY", "secret").json(&call(3, "0x10")).build().unwrap();
+        assert_eq!(rpc.key(&post(call(1, "0x10"))), rpc.key(&with_key));
+    }
+
+    #[test]
+    fn strips_the_id_of_every_call_in_a_batch() {
+        let rpc = cache("rpc");
+        let first = post(json!([call(1, "0x10"), call(2, "0x11")]));
+        let renumbered = post(json!([call(41, "0x10"), call(42, "0x11")]));
+        assert_eq!(rpc.key(&first), rpc.key(&renumbered));
+        assert_eq!(serde_json::from_str::<Value>(&canonical_body(&first)).unwrap(), json!([
+            { "jsonrpc": "2.0", "method": "eth_getBlockByNumber", "params": ["0x10", false] },
+            { "jsonrpc": "2.0", "method": "eth_getBlockByNumber", "params": ["0x11", false] },
+        ]));
+
+        let reordered = post(json!([call(1, "0x11"), call(2, "0x10")]));
+        assert_ne!(rpc.key(&first), rpc.key(&reordered));
+    }
+
+    #[test]
+    fn keeps_the_id_of_bodies_that_are_not_json_rpc() {
+        let transpose = cache("transpose");
+        let first = post(json!({ "id": 1, "sql": "SELECT 1" }));
+        let second = post(json!({ "id": 2, "sql": "SELECT 1" }));
+        assert_ne!(transpose.key(&first), transpose.key(&second));
+    }
+}
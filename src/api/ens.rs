@@ -0,0 +1,147 @@
+use crate::abi::decode::{decode_params, Param};
+use crate::address::EvmAddress;
+use crate::api::format_timestamp;
+use crate::error::{Error, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// The ENS registry on Ethereum mainnet.
+pub const ENS_REGISTRY: &str = "0x00000000000c2e074ec69a0dfb2997ba6c7d2e1e";
+
+const CACHE_PATH: &str = "data/cache/ens.json";
+/// How long a cached lookup is trusted before asking the chain again.
+const CACHE_TTL_SECONDS: i64 = 24 * 60 * 60;
+
+/// Row columns that hold the addresses a query's results were exchanged with.
+const COUNTERPARTY_FIELDS: &[&str] = &["address", "from_address", "to_address", "creator_address", "contract_address"];
+
+fn keccak(bytes: &[u8]) -> [u8; 32] {
+    Keccak256::digest(bytes).into()
+}
+
+/// The ENS namehash of `name`: the empty name hashes to zero, and each label is folded in from
+/// the top-level domain down as `keccak(node ++ keccak(label))`.
+pub fn namehash(name: &str) -> [u8; 32] {
+    let mut node = [0u8; 32];
+    for label in name.rsplit('.').filter(|label| !label.is_empty()) {
+        let mut preimage = node.to_vec();
+        preimage.extend_from_slice(&keccak(label.as_bytes()));
+        node = keccak(&preimage);
+    }
+    node
+}
+
+/// Node of the reverse record for `address`, `<lowercase hex>.addr.reverse`.
+pub fn reverse_node(address: &EvmAddress) -> [u8; 32] {
+    namehash(&format!("{}.addr.reverse", address.to_string().trim_start_matches("0x")))
+}
+
+/// Calldata for a registry or resolver function that takes a single `bytes32` node.
+pub fn node_call(signature: &str, node: &[u8; 32]) -> String {
+    format!("0x{}{}", hex::encode(&keccak(signature.as_bytes())[..4]), hex::encode(node))
+}
+
+/// Reads an `address` return value. The zero address means "not set" everywhere in ENS.
+pub fn decode_address(result: &Value) -> Option<EvmAddress> {
+    let bytes = hex::decode(result.as_str()?.trim_start_matches("0x")).ok()?;
+    let word = bytes.get(..32)?;
+    if word.iter().all(|&b| b == 0) {
+        return None;
+    }
+    format!("0x{}", hex::encode(&word[12..])).parse().ok()
+}
+
+/// Reads a `string` return value, treating the empty string as "not set".
+pub fn decode_name(result: &Value) -> Option<String> {
+    let bytes = hex::decode(result.as_str()?.trim_start_matches("0x")).ok()?;
+    let param = Param::from_json(&json!({ "type": "string" })).ok()?;
+    let name = decode_params(&[param], &bytes)?.pop()?.as_str()?.to_lowercase();
+    (!name.is_empty()).then_some(name)
+}
+
+/// An `ens_names` row.
+pub fn name_row(address: &EvmAddress, name: &str) -> Value {
+    json!({
+        "address": address.to_string(),
+        "name": name,
+        "resolved_at": format_timestamp(Utc::now().timestamp() as u64),
+    })
+}
+
+/// Adds every address in the counterparty columns of `rows` to `found`.
+pub fn counterparties(rows: &[Value], found: &mut BTreeSet<EvmAddress>) {
+    for row in rows {
+        for field in COUNTERPARTY_FIELDS {
+            if let Some(address) = row[*field].as_str().and_then(|value| value.parse().ok()) {
+                found.insert(address);
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedLookup {
+    value: Option<String>,
+    resolved_at: i64,
+}
+
+impl CachedLookup {
+    fn is_fresh(&self) -> bool {
+        Utc::now().timestamp() - self.resolved_at < CACHE_TTL_SECONDS
+    }
+}
+
+/// Forward and reverse ENS lookups kept on disk between runs. Reverse misses are cached too,
+/// since most counterparties have no name and would otherwise be looked up on every query.
+#[derive(Serialize, Deserialize, Default)]
+pub struct EnsCache {
+    #[serde(default)]
+    forward: HashMap<String, CachedLookup>,
+    #[serde(default)]
+    reverse: HashMap<String, CachedLookup>,
+}
+
+impl EnsCache {
+    /// Loads the cache, starting empty if it is missing or unreadable.
+    pub fn load() -> Self {
+        fs::read_to_string(CACHE_PATH).ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = Path::new(CACHE_PATH).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(CACHE_PATH, serde_json::to_string(self).map_err(|e| Error::Storage(e.to_string()))?)?;
+        Ok(())
+    }
+
+    /// The cached address for `name`, if it was resolved recently.
+    pub fn address(&self, name: &str) -> Option<EvmAddress> {
+        self.forward.get(name)
+            .filter(|lookup| lookup.is_fresh())
+            .and_then(|lookup| lookup.value.as_deref()?.parse().ok())
+    }
+
+    pub fn set_address(&mut self, name: &str, address: EvmAddress) {
+        self.forward.insert(name.to_string(), CachedLookup { value: Some(address.to_string()), resolved_at: Utc::now().timestamp() });
+    }
+
+    /// The cached reverse name for `address`: `Some(None)` when it is known to have none, `None`
+    /// when it has to be looked up.
+    pub fn name(&self, address: &EvmAddress) -> Option<Option<&str>> {
+        self.reverse.get(&address.to_string())
+            .filter(|lookup| lookup.is_fresh())
+            .map(|lookup| lookup.value.as_deref())
+    }
+
+    pub fn set_name(&mut self, address: &EvmAddress, name: Option<&str>) {
+        self.reverse.insert(address.to_string(), CachedLookup { value: name.map(str::to_string), resolved_at: Utc::now().timestamp() });
+    }
+}
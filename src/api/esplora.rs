@@ -0,0 +1,156 @@
+use crate::address::BitcoinAddress;
+use crate::api::format_timestamp;
+use crate::api::cache::Reuse;
+use crate::api::http::HttpClient;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+/// Rows for the `bitcoin_transactions`, `bitcoin_inputs` and `bitcoin_outputs` tables.
+#[derive(Default)]
+pub struct BitcoinRecords {
+    pub transactions: Vec<Value>,
+    pub inputs: Vec<Value>,
+    pub outputs: Vec<Value>,
+}
+
+impl BitcoinRecords {
+    /// Splits an Esplora transaction object into one transaction row plus its input and output rows.
+    pub fn push_esplora_transaction(&mut self, tx: &Value) {
+        let txid = tx["txid"].as_str().unwrap_or_default();
+        let vin = tx["vin"].as_array().cloned().unwrap_or_default();
+        let vout = tx["vout"].as_array().cloned().unwrap_or_default();
+        let is_coinbase = vin.iter().any(|input| input["is_coinbase"].as_bool().unwrap_or(false));
+
+        let input_value: u64 = vin.iter().filter_map(|input| input["prevout"]["value"].as_u64()).sum();
+        let output_value: u64 = vout.iter().filter_map(|output| output["value"].as_u64()).sum();
+
+        self.transactions.push(json!({
+            "txid": txid,
+            "block_height": tx["status"]["block_height"],
+            "block_hash": tx["status"]["block_hash"],
+            "block_time": tx["status"]["block_time"].as_u64().map(format_timestamp),
+            "version": tx["version"],
+            "locktime": tx["locktime"],
+            "size": tx["size"],
+            "weight": tx["weight"],
+            "fee": tx["fee"],
+            "input_count": vin.len(),
+            "output_count": vout.len(),
+            "input_value": input_value,
+            "output_value": output_value,
+            "is_coinbase": is_coinbase,
+        }));
+
+        for (index, input) in vin.iter().enumerate() {
+            let witness = input["witness"].as_array()
+                .map(|items| items.iter().filter_map(Value::as_str).collect::<Vec<_>>().join(","));
+
+            self.inputs.push(json!({
+                "txid": txid,
+                "input_index": index,
+                "prev_txid": if is_coinbase { Value::Null } else { input["txid"].clone() },
+                "prev_vout": if is_coinbase { Value::Null } else { input["vout"].clone() },
+                "address": input["prevout"]["scriptpubkey_address"],
+                "value": input["prevout"]["value"],
+                "script_sig": input["scriptsig"],
+                "witness": witness,
+                "sequence": input["sequence"],
+            }));
+        }
+
+        for (index, output) in vout.iter().enumerate() {
+            self.outputs.push(json!({
+                "txid": txid,
+                "output_index": index,
+                "address": output["scriptpubkey_address"],
+                "value": output["value"],
+                "script_pubkey": output["scriptpubkey"],
+                "script_type": output["scriptpubkey_type"],
+            }));
+        }
+    }
+}
+
+/// Client for an Esplora-compatible REST API (Blockstream's esplora, a self-hosted electrs, mempool.space).
+pub struct Esplora {
+    http: HttpClient,
+    base_url: String,
+    /// Txids already returned, so a transaction between two queried addresses is saved once.
+    seen: Mutex<HashSet<String>>,
+}
+
+impl Esplora {
+    pub fn new(config: &Config) -> Result<Self> {
+        let base_url = config.esplora_url().trim_end_matches('/').to_string();
+        Ok(Esplora {
+            http: HttpClient::new(config, "esplora", &base_url)?,
+            base_url,
+            seen: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// `GET`s `path`. Address stats, the mempool and the newest page of history change as blocks
+    /// arrive, so they are `Reuse::Volatile`; older pages of history are not.
+    async fn get(&self, path: &str, reuse: Reuse) -> Result<Value> {
+        let url = format!("{}{}", self.base_url, path);
+        let response = self.http.send(|client| client.get(&url), |_| reuse).await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            return Err(Error::ProviderResponse(format!("Esplora request to {} failed with {}: {}", url, status, response.text())));
+        }
+
+        response.json().map_err(|e| Error::ProviderResponse(format!("Unexpected Esplora response from {}: {}", url, e)))
+    }
+
+    /// Funding and spending totals for `address`, shaped like the `bitcoin_addresses` table.
+    pub async fn address(&self, address: &BitcoinAddress) -> Result<Vec<Value>> {
+        let stats = self.get(&format!("/address/{}", address), Reuse::Volatile).await?;
+        let chain = &stats["chain_stats"];
+        let funded = chain["funded_txo_sum"].as_u64().unwrap_or(0);
+        let spent = chain["spent_txo_sum"].as_u64().unwrap_or(0);
+
+        Ok(vec![json!({
+            "address": address.to_string(),
+            "tx_count": chain["tx_count"],
+            "funded_txo_count": chain["funded_txo_count"],
+            "funded_txo_sum": funded,
+            "spent_txo_count": chain["spent_txo_count"],
+            "spent_txo_sum": spent,
+            "balance": funded.saturating_sub(spent),
+            "mempool_tx_count": stats["mempool_stats"]["tx_count"],
+        })])
+    }
+
+    /// One page of `address`'s history, newest first, and the txid the next page starts after.
+    /// The first page (`after_txid` of `None`) also holds the mempool transactions. Confirmed
+    /// history comes 25 transactions at a time, keyed by the last txid seen. Transactions already
+    /// returned for another address are left out.
+    pub async fn transaction_page(&self, address: &BitcoinAddress, after_txid: Option<&str>) -> Result<(BitcoinRecords, Option<String>)> {
+        let (mut history, page) = match after_txid {
+            None => {
+                let mempool = self.get(&format!("/address/{}/txs/mempool", address), Reuse::Volatile).await?;
+                let page = self.get(&format!("/address/{}/txs/chain", address), Reuse::Volatile).await?;
+                (mempool.as_array().cloned().unwrap_or_default(), page)
+            }
+            Some(txid) => (Vec::new(), self.get(&format!("/address/{}/txs/chain/{}", address, txid), Reuse::Stable).await?),
+        };
+        let page = page.as_array().cloned().unwrap_or_default();
+        let next = page.last().and_then(|tx| tx["txid"].as_str()).map(str::to_string);
+        history.extend(page);
+
+        let mut records = BitcoinRecords::default();
+        for tx in &history {
+            if let Some(txid) = tx["txid"].as_str() {
+                if self.seen.lock().unwrap().insert(txid.to_string()) {
+                    records.push_esplora_transaction(tx);
+                }
+            }
+        }
+
+        Ok((records, next))
+    }
+}
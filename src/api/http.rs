@@ -0,0 +1,284 @@
+use super::cache::{ResponseCache, Reuse};
+use crate::config::{Config, RateLimit};
+use crate::error::{Error, Result};
+use chrono::{DateTime, Utc};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+/// Rate limits used when `http.rate_limits` has no entry for a provider. Transpose allows one
+/// request per second; the others are kept under the public endpoints' published limits.
+const DEFAULT_RATE_LIMITS: &[(&str, RateLimit)] = &[
+    ("transpose", RateLimit { requests_per_second: 1.0, burst: 1 }),
+    ("rpc", RateLimit { requests_per_second: 10.0, burst: 10 }),
+    ("esplora", RateLimit { requests_per_second: 4.0, burst: 4 }),
+    ("solana", RateLimit { requests_per_second: 4.0, burst: 4 }),
+];
+/// First retry delay; each further retry doubles it, up to `MAX_BACKOFF`.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Longest `Retry-After` that is waited out rather than failing the request.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(600);
+/// How far repeated 429s can slow a bucket below its configured rate.
+const MIN_RATE_FRACTION: f64 = 1.0 / 16.0;
+
+/// The process-wide client, so every provider shares one connection pool.
+static CLIENT: OnceLock<Client> = OnceLock::new();
+/// Token buckets by provider and endpoint, shared by every client for the same endpoint.
+static LIMITERS: OnceLock<Mutex<HashMap<String, Arc<TokenBucket>>>> = OnceLock::new();
+
+/// A token bucket that slows down when the server answers 429 and speeds back up as requests
+/// succeed. A `Retry-After` pauses every request through the bucket, not just the one that got it.
+struct TokenBucket {
+    limit: RateLimit,
+    state: Mutex<BucketState>,
+}
+
+struct BucketState {
+    tokens: f64,
+    rate: f64,
+    refilled_at: Instant,
+    paused_until: Option<Instant>,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit) -> Self {
+        TokenBucket {
+            limit,
+            state: Mutex::new(BucketState {
+                tokens: limit.burst.max(1) as f64,
+                rate: limit.requests_per_second,
+                refilled_at: Instant::now(),
+                paused_until: None,
+            }),
+        }
+    }
+
+    /// Waits until a request may be sent.
+    async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+                let elapsed = now.duration_since(state.refilled_at).as_secs_f64();
+                state.tokens = (state.tokens + elapsed * state.rate).min(self.limit.burst.max(1) as f64);
+                state.refilled_at = now;
+
+                match state.paused_until {
+                    Some(until) if until > now => until - now,
+                    _ if state.tokens >= 1.0 => {
+                        state.tokens -= 1.0;
+                        return;
+                    }
+                    _ => Duration::from_secs_f64((1.0 - state.tokens) / state.rate),
+                }
+            };
+            sleep(wait).await;
+        }
+    }
+
+    /// Halves the rate after a 429 and holds every request back for `pause`.
+    fn throttle(&self, pause: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.rate = (state.rate / 2.0).max(self.limit.requests_per_second * MIN_RATE_FRACTION);
+        let until = Instant::now() + pause;
+        state.paused_until = Some(state.paused_until.map_or(until, |paused| paused.max(until)));
+    }
+
+    /// Creeps back towards the configured rate after a success.
+    fn recover(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.rate = (state.rate + self.limit.requests_per_second * 0.05).min(self.limit.requests_per_second);
+    }
+}
+
+/// A response read in full, from the provider or from the response cache.
+pub struct HttpResponse {
+    status: StatusCode,
+    body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_slice(&self.body)
+    }
+}
+
+/// HTTP client for one provider endpoint: requests wait for the provider's rate limiter, time
+/// out after `http.timeout_seconds`, and timeouts, connection failures, 429s and 5xx responses
+/// are retried with exponential backoff and jitter, honoring `Retry-After`. Successful responses
+/// go through the response cache, and in offline mode only the cache is asked.
+pub struct HttpClient {
+    client: Client,
+    provider: &'static str,
+    limiter: Arc<TokenBucket>,
+    max_retries: u32,
+    cache: ResponseCache,
+}
+
+impl HttpClient {
+    /// A client for `provider` requests to `endpoint`. Clients for the same provider and
+    /// endpoint share a rate limiter.
+    pub fn new(config: &Config, provider: &'static str, endpoint: &str) -> Result<Self> {
+        let client = match CLIENT.get() {
+            Some(client) => client.clone(),
+            None => {
+                let client = Client::builder()
+                    .timeout(Duration::from_secs(config.http.timeout_seconds))
+                    .connect_timeout(Duration::from_secs(config.http.timeout_seconds.min(10)))
+                    .build()?;
+                CLIENT.get_or_init(|| client).clone()
+            }
+        };
+
+        let limit = config.http.rate_limits.get(provider).copied()
+            .or_else(|| DEFAULT_RATE_LIMITS.iter().find(|(name, _)| *name == provider).map(|(_, limit)| *limit))
+            .ok_or_else(|| Error::Config(format!("No rate limit is configured for provider '{}'", provider)))?;
+        if limit.requests_per_second <= 0.0 {
+            return Err(Error::Config(format!("The rate limit for provider '{}' must be above 0 requests per second", provider)));
+        }
+
+        let limiter = LIMITERS.get_or_init(Default::default).lock().unwrap()
+            .entry(format!("{} {}", provider, endpoint))
+            .or_insert_with(|| Arc::new(TokenBucket::new(limit)))
+            .clone();
+
+        let cache = ResponseCache::new(config, provider);
+        Ok(HttpClient { client, provider, limiter, max_retries: config.http.max_retries, cache })
+    }
+
+    /// Sends the request `build` makes, rebuilding it for each retry, unless the response cache
+    /// can answer it. `reuse` judges whether a successful response may be served from the cache
+    /// to later runs. Responses with other error statuses are returned for the caller to handle.
+    pub async fn send(&self, build: impl Fn(&Client) -> RequestBuilder, reuse: impl Fn(&HttpResponse) -> Reuse) -> Result<HttpResponse> {
+        let request = build(&self.client).build()?;
+        let key = self.cache.key(&request);
+        if let Some(cached) = self.cache.get(&key) {
+            let status = StatusCode::from_u16(cached.status).map_err(|e| Error::Storage(format!("Corrupt cache entry {}: {}", key, e)))?;
+            return Ok(HttpResponse { status, body: cached.body.into_bytes() });
+        }
+        if self.cache.offline() {
+            return Err(Error::Network(format!("Offline and the {} response to {} {} is not cached; run without --offline to fetch it", self.provider, request.method(), request.url())));
+        }
+
+        let mut attempt = 0;
+        loop {
+            self.limiter.acquire().await;
+            let failure = match build(&self.client).send().await {
+                Ok(response) if is_retryable(response.status()) => {
+                    let retry_after = retry_after(&response);
+                    let limited = response.status() == StatusCode::TOO_MANY_REQUESTS;
+                    if limited {
+                        self.limiter.throttle(retry_after.unwrap_or_default());
+                    }
+                    (format!("{} {}", response.url(), response.status()), retry_after, limited)
+                }
+                Ok(response) => {
+                    self.limiter.recover();
+                    let status = response.status();
+                    let response = HttpResponse { status, body: response.bytes().await?.to_vec() };
+                    if status.is_success() {
+                        self.cache.put(&key, &request, status.as_u16(), &response.body, reuse(&response))?;
+                    }
+                    return Ok(response);
+                }
+                Err(e) if e.is_timeout() || e.is_connect() => (e.to_string(), None, false),
+                Err(e) => return Err(e.into()),
+            };
+
+            attempt += 1;
+            let (reason, retry_after, limited) = failure;
+            if attempt > self.max_retries {
+                let message = format!("{} request failed after {} attempts: {}", self.provider, attempt, reason);
+                return Err(match limited {
+                    true => Error::RateLimited { provider: self.provider, message },
+                    false => Error::Network(message),
+                });
+            }
+            let delay = match retry_after {
+                Some(delay) if delay > MAX_RETRY_AFTER => {
+                    let message = format!("asked to wait {}s before retrying: {}", delay.as_secs(), reason);
+                    return Err(Error::RateLimited { provider: self.provider, message });
+                }
+                Some(delay) => delay,
+                None => backoff(attempt),
+            };
+            println!("{} request failed ({}); retrying in {:.1}s ({} of {})", self.provider, reason, delay.as_secs_f64(), attempt, self.max_retries);
+            sleep(delay).await;
+        }
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// The delay a response's `Retry-After` header asks for.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    retry_after_delay(value, Utc::now())
+}
+
+/// The delay from `now` a `Retry-After` value asks for, given either in seconds or as an HTTP
+/// date. A date already past gives none, so the usual backoff applies.
+fn retry_after_delay(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc);
+    (at - now).to_std().ok()
+}
+
+/// Exponential backoff with jitter: a random delay between half and all of
+/// `INITIAL_BACKOFF * 2^(attempt - 1)`, capped at `MAX_BACKOFF`, so clients that failed together
+/// do not retry together.
+fn backoff(attempt: u32) -> Duration {
+    let ceiling = INITIAL_BACKOFF.saturating_mul(1 << (attempt - 1).min(16)).min(MAX_BACKOFF);
+    let random = RandomState::new().build_hasher().finish();
+    let fraction = 0.5 + (random % 1000) as f64 / 2000.0;
+    ceiling.mul_f64(fraction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_retry_after_in_seconds_or_as_an_http_date() {
+        let now = DateTime::parse_from_rfc3339("2015-10-21T07:27:00Z").unwrap().with_timezone(&Utc);
+
+        assert_eq!(retry_after_delay("120", now), Some(Duration::from_secs(120)));
+        assert_eq!(retry_after_delay(" 0 ", now), Some(Duration::ZERO));
+        assert_eq!(retry_after_delay("Wed, 21 Oct 2015 07:28:00 GMT", now), Some(Duration::from_secs(60)));
+        assert_eq!(retry_after_delay("Wed, 21 Oct 2015 07:20:00 GMT", now), None);
+        assert_eq!(retry_after_delay("soon", now), None);
+        assert_eq!(retry_after_delay("-5", now), None);
+    }
+
+    #[test]
+    fn backs_off_exponentially_with_jitter_up_to_the_cap() {
+        for _ in 0..50 {
+            let first = backoff(1);
+            assert!(first >= INITIAL_BACKOFF / 2 && first <= INITIAL_BACKOFF, "{:?}", first);
+            let third = backoff(3);
+            assert!(third >= INITIAL_BACKOFF * 2 && third <= INITIAL_BACKOFF * 4, "{:?}", third);
+            let late = backoff(40);
+            assert!(late >= MAX_BACKOFF / 2 && late <= MAX_BACKOFF, "{:?}", late);
+        }
+    }
+}
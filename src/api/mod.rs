@@ -1 +1,37 @@
+pub mod cache;
+pub mod ens;
+pub mod esplora;
+pub mod http;
+pub mod provider;
+pub mod rpc;
+pub mod solana;
+pub mod template;
+pub mod traces;
+pub mod transfers;
 pub mod transpose;
+
+use crate::config::chains::Chain;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::record::Timestamp;
+use provider::{DataProvider, QueryKind};
+use rpc::JsonRpc;
+use transpose::Transpose;
+
+/// Names of the providers `provider_for` can build.
+pub const PROVIDERS: &[&str] = &["transpose", "rpc"];
+
+/// Builds the provider configured for `kind` queries on `chain`.
+pub fn provider_for(config: &Config, chain: &Chain, kind: QueryKind) -> Result<Box<dyn DataProvider>> {
+    let name = config.provider_name(chain.name, kind);
+    match name {
+        "transpose" => Ok(Box::new(Transpose::new(config)?)),
+        "rpc" => Ok(Box::new(JsonRpc::new(config, chain)?)),
+        _ => Err(Error::Config(format!("Unknown data provider '{}' configured for {} {}", name, chain.name, kind.as_str()))),
+    }
+}
+
+/// Formats a unix timestamp the way Transpose returns timestamps.
+pub fn format_timestamp(seconds: u64) -> String {
+    Timestamp::from_unix(seconds).map(|timestamp| timestamp.to_string()).unwrap_or_default()
+}
@@ -0,0 +1,140 @@
+use crate::address::EvmAddress;
+use crate::config::chains::Chain;
+use crate::error::{Error, Result};
+use crate::record::{columns, Column};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::fmt;
+
+/// The kinds of data a provider can be asked for, used to pick a provider per query in `Config`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueryKind {
+    Accounts,
+    Transactions,
+    Logs,
+    Blocks,
+    TokenTransfers,
+    Traces,
+    Ens,
+}
+
+impl QueryKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueryKind::Accounts => "accounts",
+            QueryKind::Transactions => "transactions",
+            QueryKind::Logs => "logs",
+            QueryKind::Blocks => "blocks",
+            QueryKind::TokenTransfers => "token_transfers",
+            QueryKind::Traces => "traces",
+            QueryKind::Ens => "ens",
+        }
+    }
+
+    /// The columns of a kind's rows, in the order they are stored.
+    pub fn columns(&self) -> &'static [Column] {
+        match self {
+            QueryKind::Accounts => columns::ACCOUNTS,
+            QueryKind::Transactions => columns::TRANSACTIONS,
+            QueryKind::Logs => columns::LOGS,
+            QueryKind::Blocks => columns::BLOCKS,
+            QueryKind::TokenTransfers => columns::TOKEN_TRANSFERS,
+            QueryKind::Traces => columns::TRACES,
+            QueryKind::Ens => columns::ENS_NAMES,
+        }
+    }
+
+    /// The columns a kind's rows are paginated by, most significant first. Empty for kinds that
+    /// are not paginated.
+    pub fn cursor_key(&self) -> &'static [&'static str] {
+        match self {
+            QueryKind::Transactions => &["block_number", "position"],
+            QueryKind::Logs => &["block_number", "log_index"],
+            QueryKind::Blocks => &["block_number"],
+            QueryKind::TokenTransfers => &["block_number", "log_index", "batch_index"],
+            QueryKind::Traces => &["block_number", "transaction_position"],
+            QueryKind::Accounts | QueryKind::Ens => &[],
+        }
+    }
+}
+
+/// Where a page of a keyset-paginated query starts, inclusive. Rows are walked in ascending
+/// `(block_number, position, sub_position)` order, where `position` is the transaction position
+/// or log index and `sub_position` the index within an ERC-1155 batch transfer. Parts a query
+/// does not key on stay 0.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Cursor {
+    pub block_number: u64,
+    pub position: u64,
+    pub sub_position: u64,
+}
+
+impl Cursor {
+    /// The cursor just past `row`, read from its key columns, most significant first.
+    pub fn after(row: &Value, key: &[&str]) -> Result<Cursor> {
+        let mut parts = [0u64; 3];
+        for (part, column) in parts.iter_mut().zip(key) {
+            *part = match &row[*column] {
+                Value::Number(n) => n.as_u64(),
+                Value::String(s) => s.parse().ok(),
+                _ => None,
+            }.ok_or_else(|| Error::ProviderResponse(format!("Cannot paginate past a row without a numeric {}: {}", column, row)))?;
+        }
+        parts[key.len() - 1] += 1;
+        Ok(Cursor { block_number: parts[0], position: parts[1], sub_position: parts[2] })
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "block {}, position {}", self.block_number, self.position)?;
+        if self.sub_position > 0 {
+            write!(f, ".{}", self.sub_position)?;
+        }
+        Ok(())
+    }
+}
+
+/// One page of rows and where the next one starts, or `None` once the history is exhausted.
+pub struct Page {
+    pub rows: Vec<Value>,
+    pub next: Option<Cursor>,
+}
+
+/// A source of EVM chain data. Every method returns rows shaped like the matching
+/// `{chain}_*` table in `helpers::migrations`, so callers can store results without knowing
+/// which backend produced them.
+#[async_trait]
+pub trait DataProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn accounts(&self, chain: &Chain, address: &EvmAddress) -> Result<Vec<Value>>;
+
+    /// One page of the transactions sent or received by `address`, starting at `from`.
+    async fn transactions(&self, chain: &Chain, address: &EvmAddress, from: Cursor) -> Result<Page>;
+
+    /// One page of the logs emitted by `address`, starting at `from`.
+    async fn logs(&self, chain: &Chain, address: &EvmAddress, from: Cursor) -> Result<Page>;
+
+    async fn blocks(&self, chain: &Chain, from_block: u64, to_block: u64) -> Result<Vec<Value>>;
+
+    /// One page of the ERC-20, ERC-721 and ERC-1155 transfers sent or received by `address`,
+    /// starting at `from`.
+    async fn token_transfers(&self, chain: &Chain, address: &EvmAddress, from: Cursor) -> Result<Page>;
+
+    /// One page of the traces (internal calls) of the transactions `address` took part in,
+    /// starting at `from`. Pages hold whole transactions, keyed by block and position, so a
+    /// transaction's call tree is never split.
+    async fn traces(&self, chain: &Chain, address: &EvmAddress, from: Cursor) -> Result<Page>;
+
+    /// Resolves an ENS name to the address its resolver points at, if it has one.
+    async fn resolve_name(&self, _chain: &Chain, _name: &str) -> Result<Option<EvmAddress>> {
+        Err(Error::Config(format!("The {} provider does not support ENS resolution", self.name())))
+    }
+
+    /// `ens_names` rows for those of `addresses` whose reverse record names a name that resolves
+    /// back to the same address.
+    async fn reverse_names(&self, _chain: &Chain, _addresses: &[EvmAddress]) -> Result<Vec<Value>> {
+        Err(Error::Config(format!("The {} provider does not support ENS resolution", self.name())))
+    }
+}
@@ -0,0 +1,765 @@
+use crate::address::EvmAddress;
+use crate::api::ens::{decode_address, decode_name, name_row, namehash, node_call, reverse_node, ENS_REGISTRY};
+use crate::api::cache::Reuse;
+use crate::api::http::{HttpClient, HttpResponse};
+use crate::api::provider::{Cursor, DataProvider, Page, QueryKind};
+use crate::api::traces::{call_frame_rows, parity_trace_row};
+use crate::api::transfers::{address_topic, decode_transfer_log, TRANSFER_BATCH_TOPIC, TRANSFER_SINGLE_TOPIC, TRANSFER_TOPIC};
+use crate::config::chains::Chain;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::record::evm::{Account, Block, Log, Transaction};
+use crate::record::{Amount, Timestamp};
+use async_trait::async_trait;
+use serde_json::{json, Map, Value};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Number of traces requested per `trace_filter` page.
+const TRACE_PAGE_SIZE: u64 = 1000;
+/// Width of each `eth_getLogs` block window, and so of each page of logs and transfers.
+const LOG_BLOCK_RANGE: u64 = 10_000;
+/// Number of transactions fetched per page of transactions or traces.
+const TRANSACTION_PAGE_SIZE: usize = 100;
+/// Calls sent in one JSON-RPC batch. Most public nodes accept at least this many.
+const BATCH_SIZE: usize = 100;
+
+/// Every transaction found for an address, as `(key, hash)` in key order.
+type TransactionKeys = Vec<(Cursor, String)>;
+
+/// An error object returned by a JSON-RPC node.
+#[derive(Debug)]
+pub struct RpcError {
+    pub method: String,
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    /// Whether the node rejected the call because it does not implement `method`.
+    pub fn is_unsupported(&self) -> bool {
+        let message = self.message.to_lowercase();
+        self.code == -32601
+            || message.contains("method not found")
+            || message.contains("not supported")
+            || message.contains("does not exist")
+            || message.contains("not available")
+    }
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RPC error calling {} ({}): {}", self.method, self.code, self.message)
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+/// Data provider that talks to an Ethereum node over standard JSON-RPC.
+///
+/// Works against any node, but the best results need an archive node with the `trace_*`
+/// namespace (Erigon, Reth, Nethermind, anvil). Without `trace_filter`, transaction discovery
+/// falls back to scanning every block from `{CHAIN}_RPC_SCAN_FROM` to the chain head, and
+/// refuses to scan from genesis unless that variable is set.
+/// The node's `eth_chainId` is checked against the registry before the first query.
+pub struct JsonRpc {
+    http: HttpClient,
+    url: String,
+    chain_name: &'static str,
+    chain_id: u64,
+    chain_verified: AtomicBool,
+    scan_from_block: Option<u64>,
+    next_id: AtomicU64,
+    unsupported_methods: Mutex<HashSet<String>>,
+    /// Transactions found per `(address, include_internal)`, discovered on the first page and
+    /// paged through after that.
+    discovered: Mutex<HashMap<(String, bool), TransactionKeys>>,
+}
+
+impl JsonRpc {
+    pub fn new(config: &Config, chain: &Chain) -> Result<Self> {
+        let url = config.rpc_url(chain);
+        Ok(JsonRpc {
+            http: HttpClient::new(config, "rpc", &url)?,
+            url,
+            chain_name: chain.name,
+            chain_id: chain.chain_id,
+            chain_verified: AtomicBool::new(false),
+            scan_from_block: config.rpc_scan_from_block(chain)?,
+            next_id: AtomicU64::new(1),
+            unsupported_methods: Mutex::new(HashSet::new()),
+            discovered: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub async fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let reuse = |response: &HttpResponse| match follows_head(method, &request["params"]) || null_result(response) {
+            true => Reuse::Volatile,
+            false => Reuse::Stable,
+        };
+        let response = self.http.send(|client| client.post(&self.url).json(&request), reuse).await?;
+        let response: Value = response.json()
+            .map_err(|e| Error::ProviderResponse(format!("Unexpected RPC response for {}: {}", method, e)))?;
+        rpc_result(method, &response)
+    }
+
+    /// Sends `calls` as JSON-RPC batches of up to `BATCH_SIZE` and returns each call's result in
+    /// order. A call the node answers with an error fails alone, not the whole batch.
+    async fn call_batch(&self, calls: &[(&str, Value)]) -> Result<Vec<Result<Value>>> {
+        let mut results = Vec::with_capacity(calls.len());
+        for chunk in calls.chunks(BATCH_SIZE) {
+            let first_id = self.next_id.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+            let request: Vec<Value> = chunk.iter().zip(first_id..)
+                .map(|((method, params), id)| json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+                .collect();
+            let reuse = |_: &HttpResponse| match chunk.iter().any(|(method, params)| follows_head(method, params)) {
+                true => Reuse::Volatile,
+                false => Reuse::Stable,
+            };
+            let response = self.http.send(|client| client.post(&self.url).json(&request), reuse).await?;
+            let responses: Vec<Value> = response.json()
+                .map_err(|e| Error::ProviderResponse(format!("Unexpected RPC batch response: {}", e)))?;
+
+            // Nodes may answer a batch in any order
+            let by_id: HashMap<u64, &Value> = responses.iter().filter_map(|response| Some((response["id"].as_u64()?, response))).collect();
+            for ((method, _), id) in chunk.iter().zip(first_id..) {
+                results.push(match by_id.get(&id) {
+                    Some(response) => rpc_result(method, response),
+                    None => Err(Error::ProviderResponse(format!("RPC batch response has no answer for {} (id {})", method, id))),
+                });
+            }
+        }
+        Ok(results)
+    }
+
+    /// Calls an optional method such as `trace_filter`, returning `None` once the node has shown
+    /// it does not support it.
+    async fn call_optional(&self, method: &str, params: Value) -> Result<Option<Value>> {
+        if self.unsupported_methods.lock().unwrap().contains(method) {
+            return Ok(None);
+        }
+
+        match self.call(method, params).await {
+            Ok(result) => Ok(Some(result)),
+            Err(Error::Rpc(e)) if e.is_unsupported() => {
+                println!("Node does not support {}; falling back to standard methods.", method);
+                self.unsupported_methods.lock().unwrap().insert(method.to_string());
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Guards against querying one chain's node for another chain's tables.
+    async fn verify_chain(&self) -> Result<()> {
+        if self.chain_verified.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let result = self.call("eth_chainId", json!([])).await?;
+        let chain_id = hex_u64(&result).ok_or_else(|| Error::ProviderResponse(format!("Invalid chain id: {}", result)))?;
+        if chain_id != self.chain_id {
+            return Err(Error::Config(format!("RPC endpoint {} serves chain id {}, but {} is chain id {}", self.url, chain_id, self.chain_name, self.chain_id)));
+        }
+
+        self.chain_verified.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn block_number(&self) -> Result<u64> {
+        let result = self.call("eth_blockNumber", json!([])).await?;
+        hex_u64(&result).ok_or_else(|| Error::ProviderResponse(format!("Invalid block number: {}", result)))
+    }
+
+    async fn block(&self, number: u64, full: bool) -> Result<Value> {
+        let block = self.call("eth_getBlockByNumber", json!([to_hex(number), full])).await?;
+        if block.is_null() {
+            return Err(Error::ProviderResponse(format!("Block {} not found", number)));
+        }
+        Ok(block)
+    }
+
+    async fn code_at(&self, address: &str, block: &str) -> Result<bool> {
+        let code = self.call("eth_getCode", json!([address, block])).await?;
+        Ok(code.as_str().is_some_and(|code| code.len() > 2))
+    }
+
+    /// Block timestamp, fetched once per block and remembered in `timestamps`.
+    async fn block_timestamp(&self, number: u64, timestamps: &mut HashMap<u64, Option<Timestamp>>) -> Result<Option<Timestamp>> {
+        if let Entry::Vacant(entry) = timestamps.entry(number) {
+            let block = self.block(number, false).await?;
+            entry.insert(hex_u64(&block["timestamp"]).and_then(Timestamp::from_unix));
+        }
+        Ok(timestamps[&number])
+    }
+
+    /// The block window of the page of logs starting at `from`: never before
+    /// `{CHAIN}_RPC_SCAN_FROM`, at most `LOG_BLOCK_RANGE` blocks wide since most nodes cap the
+    /// range of a single call, and `None` once `from` is past the chain head. Also returns where
+    /// the next page starts.
+    async fn log_window(&self, from: Cursor) -> Result<Option<(u64, u64, Option<Cursor>)>> {
+        let latest = self.block_number().await?;
+        let start = from.block_number.max(self.scan_from_block.unwrap_or(0));
+        if start > latest {
+            return Ok(None);
+        }
+        let end = (start + LOG_BLOCK_RANGE - 1).min(latest);
+        let next = (end < latest).then(|| Cursor { block_number: end + 1, ..Cursor::default() });
+        Ok(Some((start, end, next)))
+    }
+
+    /// Runs an `eth_getLogs` filter over one block window, dropping logs before `from`.
+    async fn get_logs(&self, filter: &Value, from: Cursor, (start, end): (u64, u64)) -> Result<Vec<Value>> {
+        let mut window = filter.clone();
+        window["fromBlock"] = json!(to_hex(start));
+        window["toBlock"] = json!(to_hex(end));
+
+        let logs = self.call("eth_getLogs", json!([window])).await?;
+        Ok(logs.as_array().into_iter().flatten()
+            .filter(|log| log_key(log) >= (from.block_number, from.position))
+            .cloned()
+            .collect())
+    }
+
+    /// Every transaction sent from or to `address`, or that deployed it, from `from_block` on,
+    /// in block order.
+    async fn transaction_keys(&self, address: &str, from_block: u64) -> Result<TransactionKeys> {
+        if let Some(keys) = self.transaction_keys_from_traces(address, true, from_block).await? {
+            return Ok(keys);
+        }
+        if from_block == 0 && self.scan_from_block.is_none() {
+            return Err(Error::Config(format!(
+                "{} does not support trace_filter, so transactions can only be found by fetching every block. Set {}_RPC_SCAN_FROM to the first block to scan (e.g. the block {} was created in).",
+                self.url, self.chain_name.to_uppercase(), address
+            )));
+        }
+
+        let latest = self.block_number().await?;
+        println!("Scanning blocks {} to {} for transactions of {}", from_block, latest, address);
+
+        let mut keys = Vec::new();
+        for number in from_block..=latest {
+            let block = self.block(number, true).await?;
+            for (position, tx) in block["transactions"].as_array().into_iter().flatten().enumerate() {
+                let from = tx["from"].as_str().unwrap_or_default();
+                let to = tx["to"].as_str().unwrap_or_default();
+                if from.eq_ignore_ascii_case(address) || to.eq_ignore_ascii_case(address) || self.creates(tx, address).await? {
+                    if let Some(hash) = tx["hash"].as_str() {
+                        let key = Cursor { block_number: number, position: position as u64, sub_position: 0 };
+                        keys.push((key, hash.to_string()));
+                    }
+                }
+            }
+        }
+
+        Ok(keys)
+    }
+
+    /// Whether `tx` is a contract creation that deployed `address`. Some nodes name the new
+    /// contract in the transaction's `creates` field; otherwise it is read from the receipt.
+    async fn creates(&self, tx: &Value, address: &str) -> Result<bool> {
+        if !tx["to"].is_null() {
+            return Ok(false);
+        }
+        let created = match tx.get("creates") {
+            Some(creates) => creates.clone(),
+            None => self.call("eth_getTransactionReceipt", json!([tx["hash"]])).await?["contractAddress"].clone(),
+        };
+        Ok(created.as_str().is_some_and(|created| created.eq_ignore_ascii_case(address)))
+    }
+
+    /// Uses `trace_filter` to find calls from or to `address`, either only top-level calls or at
+    /// any depth, from `from_block` on. Returns `None` if the node does not support tracing.
+    async fn transaction_keys_from_traces(&self, address: &str, top_level_only: bool, from_block: u64) -> Result<Option<TransactionKeys>> {
+        let mut seen = BTreeSet::new();
+
+        for direction in ["fromAddress", "toAddress"] {
+            let mut after = 0;
+            loop {
+                let mut filter = Map::new();
+                filter.insert("fromBlock".to_string(), json!(to_hex(from_block)));
+                filter.insert("toBlock".to_string(), json!("latest"));
+                filter.insert(direction.to_string(), json!([address]));
+                filter.insert("after".to_string(), json!(after));
+                filter.insert("count".to_string(), json!(TRACE_PAGE_SIZE));
+
+                let traces = match self.call_optional("trace_filter", json!([filter])).await? {
+                    Some(traces) => traces,
+                    None => return Ok(None),
+                };
+                let traces = traces.as_array().cloned().unwrap_or_default();
+
+                for trace in &traces {
+                    let top_level = trace["traceAddress"].as_array().is_some_and(|a| a.is_empty());
+                    if let (true, Some(hash), Some(block)) = (top_level || !top_level_only, trace["transactionHash"].as_str(), trace["blockNumber"].as_u64()) {
+                        let position = trace["transactionPosition"].as_u64().unwrap_or(0);
+                        let key = Cursor { block_number: block, position, sub_position: 0 };
+                        seen.insert((key, hash.to_string()));
+                    }
+                }
+
+                if (traces.len() as u64) < TRACE_PAGE_SIZE {
+                    break;
+                }
+                after += TRACE_PAGE_SIZE;
+            }
+        }
+
+        Ok(Some(seen.into_iter().collect()))
+    }
+
+    /// Hashes of the page of `address`'s transactions starting at `from`, and where the next page
+    /// starts. With `internal`, includes transactions that only reach the address through an
+    /// internal call, when the node supports `trace_filter`. Transactions are discovered from
+    /// `from`'s block (or `{CHAIN}_RPC_SCAN_FROM`, if later) on the first page, and remembered
+    /// for the rest of the walk.
+    async fn transaction_page(&self, address: &str, internal: bool, from: Cursor) -> Result<(Vec<String>, Option<Cursor>)> {
+        let cache_key = (address.to_string(), internal);
+        let known = self.discovered.lock().unwrap().contains_key(&cache_key);
+        if !known {
+            let from_block = from.block_number.max(self.scan_from_block.unwrap_or(0));
+            let traced = match internal {
+                true => self.transaction_keys_from_traces(address, false, from_block).await?,
+                false => None,
+            };
+            let keys = match traced {
+                Some(keys) => keys,
+                None => self.transaction_keys(address, from_block).await?,
+            };
+            println!("Found {} transactions for {}", keys.len(), address);
+            self.discovered.lock().unwrap().insert(cache_key.clone(), keys);
+        }
+
+        let discovered = self.discovered.lock().unwrap();
+        let keys = &discovered[&cache_key];
+        let start = keys.partition_point(|(key, _)| *key < from);
+        let end = (start + TRANSACTION_PAGE_SIZE).min(keys.len());
+        let hashes = keys[start..end].iter().map(|(_, hash)| hash.clone()).collect();
+        Ok((hashes, keys.get(end).map(|(key, _)| *key)))
+    }
+
+    /// Every call frame of a transaction, from `trace_transaction` or, on Geth-style nodes,
+    /// `debug_traceTransaction` with the built-in call tracer.
+    async fn transaction_traces(&self, hash: &str, timestamps: &mut HashMap<u64, Option<Timestamp>>) -> Result<Vec<Value>> {
+        let traces = self.call_optional("trace_transaction", json!([hash])).await?;
+        if let Some(traces) = traces {
+            let mut rows = Vec::new();
+            for trace in traces.as_array().into_iter().flatten() {
+                let timestamp = match trace["blockNumber"].as_u64() {
+                    Some(number) => self.block_timestamp(number, timestamps).await?,
+                    None => None,
+                };
+                rows.extend(parity_trace_row(trace, timestamp));
+            }
+            return Ok(rows);
+        }
+
+        let frame = self.call_optional("debug_traceTransaction", json!([hash, { "tracer": "callTracer" }])).await?
+            .ok_or_else(|| Error::ProviderResponse("Node supports neither trace_transaction nor debug_traceTransaction".to_string()))?;
+        let tx = self.call("eth_getTransactionByHash", json!([hash])).await?;
+        let block_number = hex_u64(&tx["blockNumber"]).ok_or_else(|| Error::ProviderResponse(format!("Transaction {} is pending", hash)))?;
+        let timestamp = self.block_timestamp(block_number, timestamps).await?;
+
+        Ok(call_frame_rows(&frame, hash, block_number, hex_u64(&tx["transactionIndex"]), timestamp))
+    }
+
+    /// Builds a row matching `ethereum_transactions.sql` from the transaction, its receipt and block.
+    async fn transaction_row(&self, hash: &str, blocks: &mut HashMap<u64, Value>) -> Result<Value> {
+        let tx = self.call("eth_getTransactionByHash", json!([hash])).await?;
+        let receipt = self.call("eth_getTransactionReceipt", json!([hash])).await?;
+        let block_number = hex_u64(&tx["blockNumber"]).ok_or_else(|| Error::ProviderResponse(format!("Transaction {} is pending", hash)))?;
+
+        if let Entry::Vacant(entry) = blocks.entry(block_number) {
+            entry.insert(self.block(block_number, false).await?);
+        }
+        let block = &blocks[&block_number];
+
+        let base_fee = hex_u128(&block["baseFeePerGas"]);
+        let gas_used = hex_u64(&receipt["gasUsed"]).unwrap_or(0);
+        let gas_price = hex_u128(&receipt["effectiveGasPrice"]).or_else(|| hex_u128(&tx["gasPrice"])).unwrap_or(0);
+        let max_fee = hex_u128(&tx["maxFeePerGas"]);
+
+        let fees_burned = base_fee.map(|fee| fee * gas_used as u128);
+        let fees_rewarded = base_fee.map(|fee| gas_price.saturating_sub(fee) * gas_used as u128);
+        let fees_saved = max_fee.map(|max| max.saturating_sub(gas_price) * gas_used as u128);
+
+        let (output, internal_count, internal_failed_count) = match self.call_optional("trace_transaction", json!([hash])).await? {
+            Some(traces) => {
+                let traces = traces.as_array().cloned().unwrap_or_default();
+                let output = traces.first().and_then(|t| t["result"]["output"].as_str()).map(str::to_string);
+                let internal: Vec<_> = traces.iter().filter(|t| t["traceAddress"].as_array().is_some_and(|a| !a.is_empty())).collect();
+                let failed = internal.iter().filter(|t| t.get("error").is_some_and(|e| !e.is_null())).count();
+                (output, Some(internal.len()), Some(failed))
+            }
+            None => (None, None, None),
+        };
+
+        Ok(json!(Transaction {
+            transaction_hash: hash.to_string(),
+            base_fee_per_gas: base_fee.map(Amount::from),
+            block_number,
+            contract_address: hex_string(&receipt["contractAddress"]),
+            fees_burned: fees_burned.map(Amount::from),
+            fees_rewarded: fees_rewarded.map(Amount::from),
+            fees_saved: fees_saved.map(Amount::from),
+            from_address: hex_string(&tx["from"]),
+            gas_limit: hex_u64(&tx["gas"]),
+            gas_price: Amount::from(gas_price),
+            gas_used,
+            input: hex_string(&tx["input"]),
+            internal_failed_transaction_count: internal_failed_count,
+            internal_transaction_count: internal_count,
+            log_count: receipt["logs"].as_array().map(Vec::len),
+            max_fee_per_gas: max_fee.map(Amount::from),
+            max_priority_fee_per_gas: hex_u128(&tx["maxPriorityFeePerGas"]).map(Amount::from),
+            nonce: hex_u64(&tx["nonce"]),
+            output,
+            position: hex_u64(&tx["transactionIndex"]),
+            timestamp: hex_u64(&block["timestamp"]).and_then(Timestamp::from_unix),
+            to_address: hex_string(&tx["to"]),
+            transaction_fee: Amount::from(gas_price * gas_used as u128),
+            transaction_type: hex_u64(&tx["type"]).unwrap_or(0),
+            value: tx["value"].as_str().and_then(Amount::from_hex),
+        }))
+    }
+
+    /// Finds the block a contract was deployed in by binary searching `eth_getCode` over history.
+    /// Requires an archive node; returns `None` if historical state is unavailable.
+    async fn creation_block(&self, address: &str, latest: u64) -> Option<u64> {
+        let (mut low, mut high) = (0, latest);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.code_at(address, &to_hex(mid)).await {
+                Ok(true) => high = mid,
+                Ok(false) => low = mid + 1,
+                Err(_) => return None,
+            }
+        }
+        Some(low)
+    }
+
+    /// Finds who deployed `address` in `block`, checking traces first so factory-created
+    /// contracts are attributed to the factory.
+    async fn creator(&self, address: &str, block: &Value) -> Result<Option<String>> {
+        let number = block["number"].clone();
+        if let Some(traces) = self.call_optional("trace_block", json!([number])).await? {
+            for trace in traces.as_array().into_iter().flatten() {
+                let created = trace["result"]["address"].as_str().is_some_and(|a| a.eq_ignore_ascii_case(address));
+                if created {
+                    return Ok(trace["action"]["from"].as_str().map(str::to_string));
+                }
+            }
+        }
+
+        for tx in block["transactions"].as_array().into_iter().flatten() {
+            if !tx["to"].is_null() {
+                continue;
+            }
+            let receipt = self.call("eth_getTransactionReceipt", json!([tx["hash"]])).await?;
+            if receipt["contractAddress"].as_str().is_some_and(|a| a.eq_ignore_ascii_case(address)) {
+                return Ok(tx["from"].as_str().map(str::to_string));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Calls a view function of an ENS contract that takes a single node. A revert, as from a
+    /// resolver without the function, counts as an unset record.
+    async fn ens_call(&self, to: &str, signature: &str, node: &[u8; 32]) -> Result<Option<Value>> {
+        let call = json!({ "to": to, "data": node_call(signature, node) });
+        reverted_as_none(self.call("eth_call", json!([call, "latest"])).await)
+    }
+
+    /// `ens_call` for many `(contract, node)` pairs at once, in one batch per `BATCH_SIZE` calls.
+    async fn ens_calls(&self, signature: &str, calls: &[(EvmAddress, [u8; 32])]) -> Result<Vec<Option<Value>>> {
+        let calls: Vec<(&str, Value)> = calls.iter()
+            .map(|(to, node)| ("eth_call", json!([{ "to": to.to_string(), "data": node_call(signature, node) }, "latest"])))
+            .collect();
+        self.call_batch(&calls).await?.into_iter().map(reverted_as_none).collect()
+    }
+
+    /// The resolver the registry has on file for each node, batched.
+    async fn ens_resolvers(&self, nodes: &[[u8; 32]]) -> Result<Vec<Option<EvmAddress>>> {
+        let registry: EvmAddress = ENS_REGISTRY.parse().expect("ENS registry address is valid");
+        let calls: Vec<_> = nodes.iter().map(|node| (registry, *node)).collect();
+        Ok(self.ens_calls("resolver(bytes32)", &calls).await?.iter().map(|result| result.as_ref().and_then(decode_address)).collect())
+    }
+
+    /// The resolver the registry has on file for `node`, if any.
+    async fn ens_resolver(&self, node: &[u8; 32]) -> Result<Option<EvmAddress>> {
+        let result = self.ens_call(ENS_REGISTRY, "resolver(bytes32)", node).await?;
+        Ok(result.as_ref().and_then(decode_address))
+    }
+}
+
+#[async_trait]
+impl DataProvider for JsonRpc {
+    fn name(&self) -> &'static str {
+        "rpc"
+    }
+
+    /// Account type comes from `eth_getCode`. Contract creation details are only filled in when
+    /// the node can serve historical state; wallet activity timestamps are left empty because
+    /// plain JSON-RPC has no address index.
+    async fn accounts(&self, _chain: &Chain, address: &EvmAddress) -> Result<Vec<Value>> {
+        self.verify_chain().await?;
+        let address = address.to_string();
+        let is_contract = self.code_at(&address, "latest").await?;
+
+        let (mut created_timestamp, mut creator_address) = (None, None);
+        if is_contract {
+            let latest = self.block_number().await?;
+            if let Some(number) = self.creation_block(&address, latest).await {
+                let block = self.block(number, true).await?;
+                created_timestamp = hex_u64(&block["timestamp"]).and_then(Timestamp::from_unix);
+                creator_address = self.creator(&address, &block).await?;
+            }
+        }
+
+        Ok(vec![json!(Account {
+            address,
+            created_timestamp,
+            creator_address,
+            last_active_timestamp: None,
+            account_type: if is_contract { "contract" } else { "wallet" },
+        })])
+    }
+
+    async fn transactions(&self, _chain: &Chain, address: &EvmAddress, from: Cursor) -> Result<Page> {
+        self.verify_chain().await?;
+        let (hashes, next) = self.transaction_page(&address.to_string(), false, from).await?;
+        let mut blocks = HashMap::new();
+        let mut rows = Vec::new();
+
+        for hash in hashes {
+            rows.push(self.transaction_row(&hash, &mut blocks).await?);
+        }
+
+        Ok(Page { rows, next })
+    }
+
+    async fn logs(&self, _chain: &Chain, address: &EvmAddress, from: Cursor) -> Result<Page> {
+        self.verify_chain().await?;
+        let Some((start, end, next)) = self.log_window(from).await? else {
+            return Ok(Page { rows: Vec::new(), next: None });
+        };
+        let mut timestamps = HashMap::new();
+        let mut rows = Vec::new();
+
+        let logs = self.get_logs(&json!({ "address": address.to_string() }), from, (start, end)).await?;
+        for log in logs {
+            let block_number = hex_u64(&log["blockNumber"]).unwrap_or(0);
+            let timestamp = self.block_timestamp(block_number, &mut timestamps).await?;
+            let topics = log["topics"].as_array().cloned().unwrap_or_default();
+
+            let topic = |index: usize| topics.get(index).and_then(hex_string);
+
+            rows.push(json!(Log {
+                transaction_hash: hex_string(&log["transactionHash"]),
+                log_index: hex_u64(&log["logIndex"]),
+                block_number,
+                transaction_position: hex_u64(&log["transactionIndex"]),
+                timestamp,
+                address: hex_string(&log["address"]),
+                data: hex_string(&log["data"]),
+                topic_0: topic(0),
+                topic_1: topic(1),
+                topic_2: topic(2),
+                topic_3: topic(3),
+            }));
+        }
+
+        Ok(Page { rows, next })
+    }
+
+    /// Finds transfers by filtering `Transfer`, `TransferSingle` and `TransferBatch` logs on the
+    /// indexed from/to topics, across every token contract. Pages are block windows, so a batch
+    /// transfer is never split across pages.
+    async fn token_transfers(&self, _chain: &Chain, address: &EvmAddress, from: Cursor) -> Result<Page> {
+        self.verify_chain().await?;
+        let Some((start, end, next)) = self.log_window(from).await? else {
+            return Ok(Page { rows: Vec::new(), next: None });
+        };
+        let mut timestamps = HashMap::new();
+        let mut rows = Vec::new();
+
+        let topic = address_topic(&address.to_string());
+        let filters = [
+            json!([[TRANSFER_TOPIC], topic]),
+            json!([[TRANSFER_TOPIC], null, topic]),
+            json!([[TRANSFER_SINGLE_TOPIC, TRANSFER_BATCH_TOPIC], null, topic]),
+            json!([[TRANSFER_SINGLE_TOPIC, TRANSFER_BATCH_TOPIC], null, null, topic]),
+        ];
+
+        // Self-transfers match both the from and to filters
+        let mut logs = BTreeMap::new();
+        for topics in filters {
+            // Whole logs only: a cursor inside a batch restarts at the batch's log
+            let window = Cursor { sub_position: 0, ..from };
+            for log in self.get_logs(&json!({ "topics": topics }), window, (start, end)).await? {
+                logs.insert(log_key(&log), log);
+            }
+        }
+
+        for ((block_number, _), log) in logs {
+            let timestamp = self.block_timestamp(block_number, &mut timestamps).await?;
+            rows.extend(decode_transfer_log(&log, timestamp).into_iter().filter(|row| {
+                Cursor::after(row, QueryKind::TokenTransfers.cursor_key()).is_ok_and(|after| after > from)
+            }));
+        }
+
+        Ok(Page { rows, next })
+    }
+
+    /// Full call trees of every transaction in which `address` appears. With `trace_filter` that
+    /// includes transactions where the address is only reached by an internal call; otherwise
+    /// transactions are found the same way as `transactions`.
+    async fn traces(&self, _chain: &Chain, address: &EvmAddress, from: Cursor) -> Result<Page> {
+        self.verify_chain().await?;
+        let (hashes, next) = self.transaction_page(&address.to_string(), true, from).await?;
+        let mut timestamps = HashMap::new();
+        let mut rows = Vec::new();
+
+        for hash in hashes {
+            rows.extend(self.transaction_traces(&hash, &mut timestamps).await?);
+        }
+
+        Ok(Page { rows, next })
+    }
+
+    async fn blocks(&self, _chain: &Chain, from_block: u64, to_block: u64) -> Result<Vec<Value>> {
+        self.verify_chain().await?;
+        let mut rows = Vec::new();
+        for number in from_block..=to_block {
+            let block = self.block(number, false).await?;
+            rows.push(json!(Block {
+                block_number: number,
+                block_hash: hex_string(&block["hash"]),
+                parent_hash: hex_string(&block["parentHash"]),
+                timestamp: hex_u64(&block["timestamp"]).and_then(Timestamp::from_unix),
+                miner: hex_string(&block["miner"]),
+                gas_limit: hex_u64(&block["gasLimit"]),
+                gas_used: hex_u64(&block["gasUsed"]),
+                base_fee_per_gas: block["baseFeePerGas"].as_str().and_then(Amount::from_hex),
+                transaction_count: block["transactions"].as_array().map(Vec::len),
+            }));
+        }
+        Ok(rows)
+    }
+
+    /// Only resolvers' `addr(bytes32)` is read, so names served off-chain through wildcard
+    /// (ENSIP-10) resolvers do not resolve.
+    async fn resolve_name(&self, _chain: &Chain, name: &str) -> Result<Option<EvmAddress>> {
+        self.verify_chain().await?;
+        let node = namehash(name);
+        let Some(resolver) = self.ens_resolver(&node).await? else {
+            return Ok(None);
+        };
+        let result = self.ens_call(&resolver.to_string(), "addr(bytes32)", &node).await?;
+        Ok(result.as_ref().and_then(decode_address))
+    }
+
+    /// Looks every address up in four batched rounds: reverse resolvers, reverse names, the
+    /// names' resolvers and the addresses they resolve to.
+    async fn reverse_names(&self, _chain: &Chain, addresses: &[EvmAddress]) -> Result<Vec<Value>> {
+        self.verify_chain().await?;
+
+        let nodes: Vec<[u8; 32]> = addresses.iter().map(reverse_node).collect();
+        let resolvers = self.ens_resolvers(&nodes).await?;
+        let lookups: Vec<(EvmAddress, [u8; 32], EvmAddress)> = addresses.iter().zip(nodes).zip(resolvers)
+            .filter_map(|((address, node), resolver)| Some((*address, node, resolver?)))
+            .collect();
+
+        let calls: Vec<_> = lookups.iter().map(|(_, node, resolver)| (*resolver, *node)).collect();
+        let names: Vec<(EvmAddress, String)> = lookups.iter().zip(self.ens_calls("name(bytes32)", &calls).await?)
+            .filter_map(|((address, _, _), result)| Some((*address, decode_name(result.as_ref()?)?)))
+            .collect();
+
+        // Anyone can point their reverse record at any name, so it only counts if the name
+        // resolves back to the same address
+        let nodes: Vec<[u8; 32]> = names.iter().map(|(_, name)| namehash(name)).collect();
+        let resolvers = self.ens_resolvers(&nodes).await?;
+        let forward: Vec<(&(EvmAddress, String), EvmAddress, [u8; 32])> = names.iter().zip(nodes).zip(resolvers)
+            .filter_map(|((named, node), resolver)| Some((named, resolver?, node)))
+            .collect();
+
+        let calls: Vec<_> = forward.iter().map(|(_, resolver, node)| (*resolver, *node)).collect();
+        let resolved = self.ens_calls("addr(bytes32)", &calls).await?;
+        Ok(forward.iter().zip(resolved)
+            .filter(|((named, _, _), result)| result.as_ref().and_then(decode_address) == Some(named.0))
+            .map(|(((address, name), _, _), _)| name_row(address, name))
+            .collect())
+    }
+}
+
+/// The result of one JSON-RPC response object, or the error the node answered with.
+fn rpc_result(method: &str, response: &Value) -> Result<Value> {
+    if let Some(error) = response.get("error") {
+        return Err(Error::Rpc(RpcError {
+            method: method.to_string(),
+            code: error.get("code").and_then(Value::as_i64).unwrap_or(0),
+            message: error.get("message").and_then(Value::as_str).unwrap_or("unknown error").to_string(),
+        }));
+    }
+
+    response.get("result").cloned()
+        .ok_or_else(|| Error::ProviderResponse(format!("Unexpected RPC response for {}: {:?}", method, response)))
+}
+
+/// An `eth_call` result, with a reverted call read as no answer: ENS contracts revert for
+/// nodes they know nothing about.
+fn reverted_as_none(result: Result<Value>) -> Result<Option<Value>> {
+    match result {
+        Ok(result) => Ok(Some(result)),
+        Err(Error::Rpc(e)) if e.message.to_lowercase().contains("revert") => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether a call's answer moves with the chain head, either because of the method or because
+/// it names a block by tag, like `eth_call` at `latest` or `trace_filter` up to `latest`.
+fn follows_head(method: &str, params: &Value) -> bool {
+    fn has_block_tag(value: &Value) -> bool {
+        match value {
+            Value::String(s) => matches!(s.as_str(), "latest" | "pending" | "safe" | "finalized"),
+            Value::Array(values) => values.iter().any(has_block_tag),
+            Value::Object(fields) => fields.values().any(has_block_tag),
+            _ => false,
+        }
+    }
+    matches!(method, "eth_blockNumber" | "eth_gasPrice") || has_block_tag(params)
+}
+
+/// Whether a JSON-RPC response has a `null` result, which a node gives for a transaction or
+/// block it has not seen yet.
+pub fn null_result(response: &HttpResponse) -> bool {
+    response.json::<Value>().map_or(true, |response| response["result"].is_null())
+}
+
+/// `(block_number, log_index)` of a raw log.
+fn log_key(log: &Value) -> (u64, u64) {
+    (hex_u64(&log["blockNumber"]).unwrap_or(0), hex_u64(&log["logIndex"]).unwrap_or(0))
+}
+
+pub fn to_hex(value: u64) -> String {
+    format!("0x{:x}", value)
+}
+
+pub fn hex_u64(value: &Value) -> Option<u64> {
+    value.as_str().and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok())
+}
+
+pub fn hex_u128(value: &Value) -> Option<u128> {
+    value.as_str().and_then(|s| u128::from_str_radix(s.trim_start_matches("0x"), 16).ok())
+}
+
+/// A string field such as an address, calldata or error message, or `None` when it is null.
+pub fn hex_string(value: &Value) -> Option<String> {
+    value.as_str().map(str::to_string)
+}
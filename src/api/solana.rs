@@ -0,0 +1,271 @@
+use crate::address::SolanaAddress;
+use crate::api::format_timestamp;
+use crate::api::cache::Reuse;
+use crate::api::http::{HttpClient, HttpResponse};
+use crate::api::rpc::{null_result, RpcError};
+use crate::config::Config;
+use crate::error::{Error, Result};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Maximum page size accepted by `getSignaturesForAddress`.
+const SIGNATURE_PAGE_SIZE: usize = 1000;
+
+/// Rows for the `solana_transactions`, `solana_instructions` and `solana_token_balance_changes` tables.
+#[derive(Default)]
+pub struct SolanaRecords {
+    pub transactions: Vec<Value>,
+    pub instructions: Vec<Value>,
+    pub token_balance_changes: Vec<Value>,
+}
+
+impl SolanaRecords {
+    /// Splits a `getTransaction` result (`json` encoding) into table rows.
+    pub fn push_transaction(&mut self, signature: &str, tx: &Value) -> Result<()> {
+        let meta = &tx["meta"];
+        let message = &tx["transaction"]["message"];
+
+        // Versioned transactions load extra accounts from lookup tables; they follow the static keys
+        let mut account_keys: Vec<String> = message["accountKeys"].as_array().into_iter().flatten()
+            .filter_map(|key| key.as_str().map(str::to_string))
+            .collect();
+        for loaded in ["writable", "readonly"] {
+            account_keys.extend(meta["loadedAddresses"][loaded].as_array().into_iter().flatten()
+                .filter_map(|key| key.as_str().map(str::to_string)));
+        }
+        let key = |index: &Value| index.as_u64().and_then(|i| account_keys.get(i as usize)).cloned();
+
+        let instructions = message["instructions"].as_array().cloned().unwrap_or_default();
+        let error = &meta["err"];
+
+        self.transactions.push(json!({
+            "signature": signature,
+            "slot": tx["slot"],
+            "block_time": tx["blockTime"].as_u64().map(format_timestamp),
+            "fee": meta["fee"],
+            "success": error.is_null(),
+            "error": if error.is_null() { Value::Null } else { json!(error.to_string()) },
+            "fee_payer": account_keys.first(),
+            "account_count": account_keys.len(),
+            "instruction_count": instructions.len(),
+            "compute_units_consumed": meta["computeUnitsConsumed"],
+            "version": tx["version"].to_string().trim_matches('"'),
+        }));
+
+        // Top-level instructions use inner_index 0; inner (CPI) instructions are numbered from 1
+        // under the top-level instruction that invoked them
+        let mut push_instruction = |instruction: &Value, index: u64, inner_index: usize| {
+            let accounts = instruction["accounts"].as_array().into_iter().flatten()
+                .filter_map(&key)
+                .collect::<Vec<_>>()
+                .join(",");
+            self.instructions.push(json!({
+                "signature": signature,
+                "instruction_index": index,
+                "inner_index": inner_index,
+                "program_id": key(&instruction["programIdIndex"]),
+                "accounts": accounts,
+                "data": instruction["data"],
+                "stack_height": instruction["stackHeight"],
+            }));
+        };
+
+        for (index, instruction) in instructions.iter().enumerate() {
+            push_instruction(instruction, index as u64, 0);
+        }
+        for group in meta["innerInstructions"].as_array().into_iter().flatten() {
+            let index = group["index"].as_u64().unwrap_or(0);
+            for (inner_index, instruction) in group["instructions"].as_array().into_iter().flatten().enumerate() {
+                push_instruction(instruction, index, inner_index + 1);
+            }
+        }
+
+        // Token accounts can appear in only one of pre/post (created or closed in this transaction)
+        let mut balances: BTreeMap<u64, (Option<&Value>, Option<&Value>)> = BTreeMap::new();
+        for balance in meta["preTokenBalances"].as_array().into_iter().flatten() {
+            if let Some(index) = balance["accountIndex"].as_u64() {
+                balances.entry(index).or_default().0 = Some(balance);
+            }
+        }
+        for balance in meta["postTokenBalances"].as_array().into_iter().flatten() {
+            if let Some(index) = balance["accountIndex"].as_u64() {
+                balances.entry(index).or_default().1 = Some(balance);
+            }
+        }
+
+        // An account missing from one side has no balance there rather than a zero one, but its
+        // change still counts the whole balance it gained or lost
+        let amount = |balance: Option<&Value>| -> Result<Option<i128>> {
+            let Some(balance) = balance else {
+                return Ok(None);
+            };
+            let amount = &balance["uiTokenAmount"]["amount"];
+            amount.as_str().and_then(|a| a.parse::<i128>().ok()).map(Some)
+                .ok_or_else(|| Error::ProviderResponse(format!("Malformed token amount {} for account index {} in Solana transaction {}", amount, balance["accountIndex"], signature)))
+        };
+        for (index, (pre, post)) in balances {
+            let (pre_amount, post_amount) = (amount(pre)?, amount(post)?);
+            let change = post_amount.unwrap_or(0) - pre_amount.unwrap_or(0);
+            if change == 0 {
+                continue;
+            }
+            let Some(balance) = post.or(pre) else {
+                continue;
+            };
+
+            self.token_balance_changes.push(json!({
+                "signature": signature,
+                "account_index": index,
+                "token_account": account_keys.get(index as usize),
+                "owner": balance["owner"],
+                "mint": balance["mint"],
+                "decimals": balance["uiTokenAmount"]["decimals"],
+                "pre_amount": pre_amount.map(|amount| amount.to_string()),
+                "post_amount": post_amount.map(|amount| amount.to_string()),
+                "change": change.to_string(),
+            }));
+        }
+        Ok(())
+    }
+}
+
+/// Client for a Solana JSON-RPC endpoint.
+pub struct Solana {
+    http: HttpClient,
+    url: String,
+    next_id: AtomicU64,
+    /// Signatures already returned, so a transaction touching two queried addresses is saved once.
+    seen: Mutex<HashSet<String>>,
+}
+
+impl Solana {
+    pub fn new(config: &Config) -> Result<Self> {
+        let url = config.solana_rpc_url();
+        Ok(Solana {
+            http: HttpClient::new(config, "solana", &url)?,
+            url,
+            next_id: AtomicU64::new(1),
+            seen: Mutex::new(HashSet::new()),
+        })
+    }
+
+    async fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        // Account state and the newest page of signatures change as the chain moves
+        let follows_head = match method {
+            "getAccountInfo" => true,
+            "getSignaturesForAddress" => request["params"][1]["before"].is_null(),
+            _ => false,
+        };
+        let reuse = |response: &HttpResponse| match follows_head || null_result(response) {
+            true => Reuse::Volatile,
+            false => Reuse::Stable,
+        };
+        let response = self.http.send(|client| client.post(&self.url).json(&request), reuse).await?;
+        let response: Value = response.json()
+            .map_err(|e| Error::ProviderResponse(format!("Unexpected Solana RPC response for {}: {}", method, e)))?;
+
+        if let Some(error) = response.get("error") {
+            return Err(Error::Rpc(RpcError {
+                method: method.to_string(),
+                code: error.get("code").and_then(Value::as_i64).unwrap_or(0),
+                message: error.get("message").and_then(Value::as_str).unwrap_or("unknown error").to_string(),
+            }));
+        }
+
+        response.get("result").cloned()
+            .ok_or_else(|| Error::ProviderResponse(format!("Unexpected RPC response for {}: {:?}", method, response)))
+    }
+
+    /// Balance, owner program and data size of `address`, shaped like the `solana_accounts` table.
+    /// Empty when the account does not exist, since the RPC then returns a null `value`.
+    pub async fn account(&self, address: &SolanaAddress) -> Result<Vec<Value>> {
+        let info = self.call("getAccountInfo", json!([address.to_string(), { "encoding": "base64" }])).await?;
+        let account = &info["value"];
+        if account.is_null() {
+            return Ok(Vec::new());
+        }
+
+        let data_size = account["data"][0].as_str().map(|data| data.len() / 4 * 3 - data.matches('=').count());
+
+        Ok(vec![json!({
+            "address": address.to_string(),
+            "lamports": account["lamports"].as_u64().unwrap_or(0),
+            "owner": account["owner"],
+            "executable": account["executable"].as_bool().unwrap_or(false),
+            "rent_epoch": account["rentEpoch"],
+            "data_size": data_size,
+        })])
+    }
+
+    /// One page of the transactions that touched `address`, newest first, starting just before
+    /// the signature `before` (or at the newest), and the signature the next page starts before.
+    /// Transactions already returned for another address are left out.
+    pub async fn transaction_page(&self, address: &SolanaAddress, before: Option<&str>) -> Result<(SolanaRecords, Option<String>)> {
+        let mut options = json!({ "limit": SIGNATURE_PAGE_SIZE });
+        if let Some(before) = before {
+            options["before"] = json!(before);
+        }
+
+        let page = self.call("getSignaturesForAddress", json!([address.to_string(), options])).await?;
+        let signatures: Vec<String> = page.as_array().into_iter().flatten()
+            .filter_map(|entry| entry["signature"].as_str().map(str::to_string))
+            .collect();
+        let next = match signatures.len() < SIGNATURE_PAGE_SIZE {
+            true => None,
+            false => signatures.last().cloned(),
+        };
+
+        let mut records = SolanaRecords::default();
+        for signature in signatures {
+            if !self.seen.lock().unwrap().insert(signature.clone()) {
+                continue;
+            }
+            let tx = self.call("getTransaction", json!([signature, { "encoding": "json", "maxSupportedTransactionVersion": 0 }])).await?;
+            if !tx.is_null() {
+                records.push_transaction(&signature, &tx)?;
+            }
+        }
+
+        Ok((records, next))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(pre: Value, post: Value) -> Value {
+        json!({
+            "slot": 1,
+            "transaction": { "message": { "accountKeys": ["Payer", "TokenA", "TokenB"], "instructions": [] } },
+            "meta": { "err": null, "fee": 5000, "preTokenBalances": pre, "postTokenBalances": post },
+        })
+    }
+
+    fn balance(index: u64, amount: Value) -> Value {
+        json!({ "accountIndex": index, "mint": "Mint", "owner": "Payer", "uiTokenAmount": { "amount": amount, "decimals": 6 } })
+    }
+
+    #[test]
+    fn leaves_the_missing_side_of_a_created_or_closed_token_account_null() {
+        let mut records = SolanaRecords::default();
+        let tx = transaction(json!([balance(1, json!("100"))]), json!([balance(2, json!("60"))]));
+        records.push_transaction("sig", &tx).unwrap();
+
+        let changes = &records.token_balance_changes;
+        assert_eq!((&changes[0]["pre_amount"], &changes[0]["post_amount"], &changes[0]["change"]), (&json!("100"), &Value::Null, &json!("-100")));
+        assert_eq!((&changes[1]["pre_amount"], &changes[1]["post_amount"], &changes[1]["change"]), (&Value::Null, &json!("60"), &json!("60")));
+    }
+
+    #[test]
+    fn rejects_malformed_token_amounts() {
+        for amount in [json!("1.5"), json!(100), Value::Null] {
+            let tx = transaction(json!([balance(1, json!("100"))]), json!([balance(1, amount)]));
+            assert!(matches!(SolanaRecords::default().push_transaction("sig", &tx), Err(Error::ProviderResponse(_))));
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::address::EvmAddress;
+use crate::error::{Error, Result};
+use crate::record::Timestamp;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+
+/// A value bound to a template placeholder. The variant must match the kind the placeholder
+/// declares, e.g. `{{wallet_address:address}}` only accepts `Param::Address`.
+#[derive(Clone)]
+pub enum Param {
+    /// An EVM address, rendered lowercased and quoted.
+    Address(EvmAddress),
+    Integer(u64),
+    /// An RFC 3339 timestamp (one without an offset is taken as UTC) or a number of unix
+    /// seconds, rendered as a quoted RFC 3339 timestamp in UTC. No bundled query filters on time yet.
+    #[allow(dead_code)]
+    Timestamp(String),
+    /// One of a fixed set of identifiers, such as a Transpose schema, rendered bare.
+    Enum { value: String, allowed: Vec<&'static str> },
+}
+
+impl Param {
+    fn kind(&self) -> &'static str {
+        match self {
+            Param::Address(_) => "address",
+            Param::Integer(_) => "integer",
+            Param::Timestamp(_) => "timestamp",
+            Param::Enum { .. } => "enum",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TemplateError {
+    pub template: String,
+    pub message: String,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SQL template {}: {}", self.template, self.message)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// A SQL query from `src/sql` with typed `{{name:kind}}` placeholders.
+pub struct SqlTemplate {
+    name: String,
+    source: String,
+}
+
+impl SqlTemplate {
+    pub fn load(filename: &str) -> Result<Self> {
+        let filepath = format!("src/sql/{}", filename);
+        let source = fs::read_to_string(&filepath).map_err(|e| Error::Config(format!("Unable to read query template {}: {}", filepath, e)))?;
+        Ok(SqlTemplate { name: filename.to_string(), source })
+    }
+
+    fn error(&self, message: String) -> TemplateError {
+        TemplateError { template: self.name.clone(), message }
+    }
+
+    /// Substitutes every placeholder with its validated, escaped value. Fails if a placeholder has
+    /// no value, declares an unknown kind, or gets a value of the wrong kind, and if a value is
+    /// bound to a name the template does not use.
+    pub fn render(&self, params: &[(&str, Param)]) -> Result<String, TemplateError> {
+        let mut rendered = String::with_capacity(self.source.len());
+        let mut used = HashSet::new();
+        let mut rest = self.source.as_str();
+
+        while let Some(start) = rest.find("{{") {
+            rendered.push_str(&rest[..start]);
+            let end = rest[start..].find("}}")
+                .ok_or_else(|| self.error("unterminated placeholder".to_string()))?;
+            let placeholder = &rest[start + 2..start + end];
+
+            let (name, kind) = placeholder.split_once(':')
+                .ok_or_else(|| self.error(format!("placeholder {{{{{}}}}} does not declare a kind", placeholder)))?;
+            if !["address", "integer", "timestamp", "enum"].contains(&kind) {
+                return Err(self.error(format!("placeholder {{{{{}}}}} has unknown kind '{}'", placeholder, kind)));
+            }
+
+            let (_, value) = params.iter().find(|(param_name, _)| *param_name == name)
+                .ok_or_else(|| self.error(format!("no value bound for {{{{{}}}}}", placeholder)))?;
+            if value.kind() != kind {
+                return Err(self.error(format!("{{{{{}}}}} expects {} but got {}", placeholder, kind, value.kind())));
+            }
+
+            rendered.push_str(&self.escape(name, value)?);
+            used.insert(name);
+            rest = &rest[start + end + 2..];
+        }
+        rendered.push_str(rest);
+
+        if let Some((name, _)) = params.iter().find(|(name, _)| !used.contains(name)) {
+            return Err(self.error(format!("template has no placeholder named '{}'", name)));
+        }
+
+        Ok(rendered)
+    }
+
+    fn escape(&self, name: &str, value: &Param) -> Result<String, TemplateError> {
+        match value {
+            Param::Address(address) => Ok(format!("'{}'", address)),
+            Param::Integer(value) => Ok(value.to_string()),
+            Param::Timestamp(value) => {
+                let parsed = match value.parse::<u64>() {
+                    Ok(seconds) => Timestamp::from_unix(seconds),
+                    Err(_) => Timestamp::parse(value),
+                };
+                let timestamp = parsed.ok_or_else(|| self.error(format!("'{}' is not an RFC 3339 timestamp or unix seconds for {}", value, name)))?;
+                Ok(format!("'{}'", timestamp))
+            }
+            Param::Enum { value, allowed } => {
+                if !allowed.contains(&value.as_str()) {
+                    return Err(self.error(format!("'{}' is not allowed for {} (expected one of: {})", value, name, allowed.join(", "))));
+                }
+                Ok(value.clone())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(source: &str) -> SqlTemplate {
+        SqlTemplate { name: "test.sql".to_string(), source: source.to_string() }
+    }
+
+    fn schema(value: &str) -> Param {
+        Param::Enum { value: value.to_string(), allowed: vec!["ethereum", "polygon"] }
+    }
+
+    const QUERY: &str = "SELECT * FROM {{schema:enum}}.transactions WHERE from_address = {{address:address}} LIMIT {{limit:integer}}";
+
+    #[test]
+    fn renders_validated_values() {
+        let address: EvmAddress = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".parse().unwrap();
+        let rendered = template(QUERY).render(&[("schema", schema("polygon")), ("address", Param::Address(address)), ("limit", Param::Integer(500))]).unwrap();
+        assert_eq!(rendered, "SELECT * FROM polygon.transactions WHERE from_address = '0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed' LIMIT 500");
+    }
+
+    #[test]
+    fn rejects_injection_payloads() {
+        let template = template("SELECT * FROM {{schema:enum}}.transactions");
+        for payload in [
+            "ethereum'",
+            "ethereum.transactions; DROP TABLE ethereum.transactions",
+            "ethereum.transactions -- ",
+            "ethereum.transactions /* */",
+        ] {
+            assert!(template.render(&[("schema", schema(payload))]).is_err(), "{} was rendered", payload);
+        }
+
+        // Addresses can only be bound once parsed, and parsing rejects anything but hex
+        for payload in ["0x5aaeb6053f3e94c9b9a09f33669435e7ef1bea'", "0x5aaeb6053f3e94c9b9a09f33669435e7ef1be;--"] {
+            assert!(payload.parse::<EvmAddress>().is_err(), "{} was parsed", payload);
+        }
+    }
+
+    #[test]
+    fn renders_timestamps_in_utc() {
+        let template = template("WHERE timestamp >= {{since:timestamp}}");
+        for (value, rendered) in [
+            ("2024-01-31T13:00:00+01:00", "WHERE timestamp >= '2024-01-31T12:00:00Z'"),
+            ("2024-01-31 12:00:00", "WHERE timestamp >= '2024-01-31T12:00:00Z'"),
+            ("1706702400", "WHERE timestamp >= '2024-01-31T12:00:00Z'"),
+        ] {
+            assert_eq!(template.render(&[("since", Param::Timestamp(value.to_string()))]).unwrap(), rendered);
+        }
+    }
+
+    #[test]
+    fn rejects_timestamp_payloads() {
+        let template = template("WHERE timestamp >= {{since:timestamp}}");
+        for payload in [
+            "2024-01-31T12:00:00Z'",
+            "2024-01-31T12:00:00Z'; DROP TABLE ethereum.transactions; --",
+            "2024-01-31T12:00:00Z /* */",
+            "-1",
+            "yesterday",
+        ] {
+            assert!(template.render(&[("since", Param::Timestamp(payload.to_string()))]).is_err(), "{} was rendered", payload);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_and_mismatched_kinds() {
+        let error = template("LIMIT {{limit:number}}").render(&[("limit", Param::Integer(1))]).unwrap_err();
+        assert!(error.message.contains("unknown kind"), "{}", error);
+
+        let error = template("LIMIT {{limit}}").render(&[("limit", Param::Integer(1))]).unwrap_err();
+        assert!(error.message.contains("does not declare a kind"), "{}", error);
+
+        let error = template("FROM {{schema:enum}}.logs").render(&[("schema", Param::Integer(1))]).unwrap_err();
+        assert!(error.message.contains("expects enum but got integer"), "{}", error);
+
+        let error = template("LIMIT {{limit:integer}}").render(&[("limit", Param::Timestamp("1706702400".to_string()))]).unwrap_err();
+        assert!(error.message.contains("expects integer but got timestamp"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_missing_unused_and_unterminated_placeholders() {
+        assert!(template("LIMIT {{limit:integer}}").render(&[]).is_err());
+        assert!(template("LIMIT 1").render(&[("limit", Param::Integer(1))]).is_err());
+        assert!(template("LIMIT {{limit:integer").render(&[("limit", Param::Integer(1))]).is_err());
+    }
+}
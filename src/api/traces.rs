@@ -0,0 +1,158 @@
+use crate::api::rpc::{hex_string, hex_u64};
+use crate::record::evm::Trace;
+use crate::record::{Amount, Timestamp};
+use serde_json::{json, Value};
+
+fn trace_address(path: &[u64]) -> String {
+    path.iter().map(u64::to_string).collect::<Vec<_>>().join(",")
+}
+
+fn amount(value: &Value) -> Option<Amount> {
+    value.as_str().and_then(Amount::from_hex)
+}
+
+/// Builds a `{chain}_traces` row from a Parity-style trace, as returned by `trace_transaction`
+/// and `trace_filter`. Block reward traces belong to no transaction and are skipped.
+pub fn parity_trace_row(trace: &Value, timestamp: Option<Timestamp>) -> Option<Value> {
+    let action = &trace["action"];
+    let result = &trace["result"];
+
+    let (trace_type, from, to, value, input, output) = match trace["type"].as_str()? {
+        "call" => ("call", &action["from"], &action["to"], &action["value"], &action["input"], &result["output"]),
+        "create" => ("create", &action["from"], &result["address"], &action["value"], &action["init"], &result["code"]),
+        "suicide" => ("suicide", &action["address"], &action["refundAddress"], &action["balance"], &Value::Null, &Value::Null),
+        _ => return None,
+    };
+    let path: Vec<u64> = trace["traceAddress"].as_array()?.iter().filter_map(Value::as_u64).collect();
+
+    Some(json!(Trace {
+        transaction_hash: hex_string(&trace["transactionHash"]),
+        trace_address: trace_address(&path),
+        block_number: trace["blockNumber"].as_u64(),
+        timestamp,
+        transaction_position: trace["transactionPosition"].as_u64(),
+        trace_type,
+        call_type: hex_string(&action["callType"]),
+        from_address: hex_string(from),
+        to_address: hex_string(to),
+        value: amount(value),
+        input: hex_string(input),
+        output: hex_string(output),
+        gas: hex_u64(&action["gas"]),
+        gas_used: hex_u64(&result["gasUsed"]),
+        subtraces: trace["subtraces"].as_u64(),
+        error: hex_string(&trace["error"]),
+    }))
+}
+
+/// Flattens a `debug_traceTransaction` call tracer frame and its children into `{chain}_traces`
+/// rows, numbering them with Parity-style trace addresses so both node flavours produce the
+/// same keys.
+pub fn call_frame_rows(frame: &Value, transaction_hash: &str, block_number: u64, position: Option<u64>, timestamp: Option<Timestamp>) -> Vec<Value> {
+    let mut rows = Vec::new();
+    let mut stack = vec![(frame, Vec::new())];
+
+    while let Some((frame, path)) = stack.pop() {
+        let frame_type = frame["type"].as_str().unwrap_or_default().to_lowercase();
+        let (trace_type, call_type) = match frame_type.as_str() {
+            "create" | "create2" => ("create", None),
+            "selfdestruct" => ("suicide", None),
+            call_type => ("call", Some(call_type.to_string())),
+        };
+
+        rows.push(json!(Trace {
+            transaction_hash: Some(transaction_hash.to_string()),
+            trace_address: trace_address(&path),
+            block_number: Some(block_number),
+            timestamp,
+            transaction_position: position,
+            trace_type,
+            call_type,
+            from_address: hex_string(&frame["from"]),
+            to_address: hex_string(&frame["to"]),
+            value: amount(&frame["value"]),
+            input: hex_string(&frame["input"]),
+            output: hex_string(&frame["output"]),
+            gas: hex_u64(&frame["gas"]),
+            gas_used: hex_u64(&frame["gasUsed"]),
+            subtraces: Some(frame["calls"].as_array().map_or(0, Vec::len) as u64),
+            error: hex_string(&frame["error"]),
+        }));
+
+        // Pushed in reverse so children pop off the stack in call order
+        if let Some(calls) = frame["calls"].as_array() {
+            for (index, child) in calls.iter().enumerate().rev() {
+                let mut child_path = path.clone();
+                child_path.push(index as u64);
+                stack.push((child, child_path));
+            }
+        }
+    }
+
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_nested_call_frames_with_parity_trace_addresses_in_call_order() {
+        let frame = json!({
+            "type": "CALL",
+            "from": "0x00000000000000000000000000000000000000aa",
+            "to": "0x00000000000000000000000000000000000000bb",
+            "value": "0xde0b6b3a7640000",
+            "gas": "0x30d40",
+            "gasUsed": "0x1d4c0",
+            "input": "0x",
+            "calls": [
+                {
+                    "type": "DELEGATECALL",
+                    "from": "0x00000000000000000000000000000000000000bb",
+                    "to": "0x00000000000000000000000000000000000000cc",
+                    "gas": "0x186a0",
+                    "gasUsed": "0x7530",
+                    "input": "0xa9059cbb",
+                    "calls": [{
+                        "type": "CREATE2",
+                        "from": "0x00000000000000000000000000000000000000bb",
+                        "to": "0x00000000000000000000000000000000000000dd",
+                        "value": "0x0",
+                        "gas": "0xc350",
+                        "gasUsed": "0x2710",
+                        "input": "0x6080",
+                    }],
+                },
+                {
+                    "type": "STATICCALL",
+                    "from": "0x00000000000000000000000000000000000000bb",
+                    "to": "0x00000000000000000000000000000000000000ee",
+                    "gas": "0x2710",
+                    "gasUsed": "0x2710",
+                    "input": "0x70a08231",
+                    "error": "out of gas",
+                },
+            ],
+        });
+        let hash = "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060";
+
+        let rows = call_frame_rows(&frame, hash, 17000000, Some(4), None);
+        let summary: Vec<(&str, &str, Value, u64)> = rows.iter()
+            .map(|row| (row["trace_address"].as_str().unwrap(), row["trace_type"].as_str().unwrap(), row["call_type"].clone(), row["subtraces"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(summary, vec![
+            ("", "call", json!("call"), 2),
+            ("0", "call", json!("delegatecall"), 1),
+            ("0,0", "create", Value::Null, 0),
+            ("1", "call", json!("staticcall"), 0),
+        ]);
+
+        assert!(rows.iter().all(|row| row["transaction_hash"] == hash && row["block_number"] == 17000000 && row["transaction_position"] == 4));
+        assert_eq!(rows[0]["value"], "1000000000000000000");
+        assert_eq!(rows[0]["gas_used"], 120000);
+        assert_eq!(rows[1]["value"], Value::Null);
+        assert_eq!(rows[2]["to_address"], "0x00000000000000000000000000000000000000dd");
+        assert_eq!(rows[3]["error"], "out of gas");
+    }
+}
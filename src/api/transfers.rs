@@ -0,0 +1,138 @@
+use crate::api::rpc::{hex_string, hex_u64};
+use crate::record::evm::TokenTransfer;
+use crate::record::{Amount, Timestamp};
+use serde_json::{json, Value};
+
+/// `Transfer(address,address,uint256)`, shared by ERC-20 and ERC-721.
+pub const TRANSFER_TOPIC: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+/// ERC-1155 `TransferSingle(address,address,address,uint256,uint256)`.
+pub const TRANSFER_SINGLE_TOPIC: &str = "0xc3d58168c5ae7397731d063d5bbf3d657854427343f4c083240f7aacaa2d0f62";
+/// ERC-1155 `TransferBatch(address,address,address,uint256[],uint256[])`.
+pub const TRANSFER_BATCH_TOPIC: &str = "0x4a39dc06d4c0dbc64b70af90fd698a233a518aa5d07e595d983b8c0526c8f7fb";
+
+/// Left-pads an address to a 32-byte topic for `eth_getLogs` filters.
+pub fn address_topic(address: &str) -> String {
+    format!("0x{:0>64}", address.trim_start_matches("0x").to_lowercase())
+}
+
+fn topic_address(topic: Option<&Value>) -> Option<String> {
+    let topic = topic?.as_str()?;
+    topic.get(topic.len().checked_sub(40)?..).map(|address| format!("0x{}", address))
+}
+
+/// Splits ABI-encoded log data into 32-byte words.
+fn data_words(log: &Value) -> Vec<&str> {
+    let data = log["data"].as_str().unwrap_or_default().trim_start_matches("0x");
+    (0..data.len() / 64).map(|i| &data[i * 64..(i + 1) * 64]).collect()
+}
+
+/// Reads a dynamic `uint256[]` whose offset is stored in word `head`.
+fn word_array<'a>(words: &[&'a str], head: usize) -> Vec<&'a str> {
+    let word_index = |word: Option<&&str>| word.and_then(|w| usize::from_str_radix(w, 16).ok());
+    let Some(start) = word_index(words.get(head)).map(|offset| offset / 32) else {
+        return Vec::new();
+    };
+    let length = word_index(words.get(start)).unwrap_or(0);
+    words.iter().skip(start + 1).take(length).copied().collect()
+}
+
+/// Decodes a raw `Transfer`, `TransferSingle` or `TransferBatch` log into `{chain}_token_transfers`
+/// rows. ERC-1155 batches produce one row per token id, numbered by `batch_index`; every other
+/// log produces a single row with `batch_index` 0. Logs that match none of the three events,
+/// or use a non-standard layout, produce no rows.
+pub fn decode_transfer_log(log: &Value, timestamp: Option<Timestamp>) -> Vec<Value> {
+    let topics = log["topics"].as_array().cloned().unwrap_or_default();
+    let words = data_words(log);
+
+    let (standard, from, to, transfers) = match topics.first().and_then(Value::as_str) {
+        Some(TRANSFER_TOPIC) if topics.len() == 3 && !words.is_empty() => {
+            ("erc20", topics.get(1), topics.get(2), vec![(Amount::from_hex(words[0]), None)])
+        }
+        Some(TRANSFER_TOPIC) if topics.len() == 4 => {
+            let token_id = topics[3].as_str().and_then(Amount::from_hex);
+            ("erc721", topics.get(1), topics.get(2), vec![(Some(Amount::from(1u64)), token_id)])
+        }
+        Some(TRANSFER_SINGLE_TOPIC) if topics.len() == 4 && words.len() >= 2 => {
+            ("erc1155", topics.get(2), topics.get(3), vec![(Amount::from_hex(words[1]), Amount::from_hex(words[0]))])
+        }
+        Some(TRANSFER_BATCH_TOPIC) if topics.len() == 4 => {
+            let ids = word_array(&words, 0);
+            let values = word_array(&words, 1);
+            let transfers = ids.iter().zip(values.iter())
+                .map(|(id, value)| (Amount::from_hex(value), Amount::from_hex(id)))
+                .collect();
+            ("erc1155", topics.get(2), topics.get(3), transfers)
+        }
+        _ => return Vec::new(),
+    };
+
+    transfers.into_iter().enumerate().map(|(batch_index, (amount, token_id))| json!(TokenTransfer {
+        transaction_hash: hex_string(&log["transactionHash"]),
+        log_index: hex_u64(&log["logIndex"]),
+        batch_index,
+        block_number: hex_u64(&log["blockNumber"]),
+        timestamp,
+        token_address: log["address"].as_str().map(str::to_lowercase),
+        token_standard: standard,
+        from_address: topic_address(from),
+        to_address: topic_address(to),
+        amount,
+        token_id,
+    })).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+    const FROM: &str = "0x00000000000000000000000000000000000000aa";
+    const TO: &str = "0x00000000000000000000000000000000000000bb";
+
+    fn log(topics: Vec<String>, data: &str) -> Value {
+        json!({
+            "address": TOKEN,
+            "topics": topics,
+            "data": data,
+            "blockNumber": "0x10d4f",
+            "transactionHash": "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060",
+            "logIndex": "0x2",
+        })
+    }
+
+    #[test]
+    fn decodes_an_erc20_transfer_with_the_amount_in_data() {
+        let topics = vec![TRANSFER_TOPIC.to_string(), address_topic(FROM), address_topic(TO)];
+        let data = format!("0x{:064x}", 1_500_000u64);
+        let timestamp = Timestamp::parse("2024-01-31T12:00:00Z");
+
+        let rows = decode_transfer_log(&log(topics, &data), timestamp);
+        assert_eq!(rows, vec![json!({
+            "transaction_hash": "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060",
+            "log_index": 2,
+            "batch_index": 0,
+            "block_number": 68943,
+            "timestamp": "2024-01-31T12:00:00Z",
+            "token_address": TOKEN.to_lowercase(),
+            "token_standard": "erc20",
+            "from_address": FROM,
+            "to_address": TO,
+            "amount": "1500000",
+            "token_id": null,
+        })]);
+    }
+
+    #[test]
+    fn decodes_an_erc721_transfer_with_the_token_id_in_the_fourth_topic() {
+        let topics = vec![TRANSFER_TOPIC.to_string(), address_topic(FROM), address_topic(TO), format!("0x{:064x}", 7804u64)];
+
+        let rows = decode_transfer_log(&log(topics, "0x"), None);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["token_standard"], "erc721");
+        assert_eq!(rows[0]["from_address"], FROM);
+        assert_eq!(rows[0]["to_address"], TO);
+        assert_eq!(rows[0]["amount"], "1");
+        assert_eq!(rows[0]["token_id"], "7804");
+        assert_eq!(rows[0]["timestamp"], Value::Null);
+    }
+}
@@ -1,86 +1,229 @@
+use crate::address::EvmAddress;
+use crate::api::cache::Reuse;
+use crate::api::http::{HttpClient, HttpResponse};
+use crate::api::provider::{Cursor, DataProvider, Page, QueryKind};
+use crate::api::template::{Param, SqlTemplate};
+use crate::config::chains::{Chain, CHAINS};
 use crate::config::Config;
-use reqwest::Client;
+use crate::error::{Error, Result};
+use crate::record;
+use async_trait::async_trait;
 use serde_json::Value;
-use std::fs;
-use std::time::{Duration, Instant};
-use tokio::time::sleep;
+use std::collections::BTreeSet;
 
-pub async fn load_sql_query(filename: &str) -> String {
-    let filepath = format!("src/sql/{}", filename);
-    fs::read_to_string(&filepath).expect(&format!("Unable to read file: {}", filepath))
+const PAGE_SIZE: u64 = 100;
+const URL: &str = "https://api.transpose.io/sql";
+
+/// Data provider backed by the Transpose SQL API.
+pub struct Transpose {
+    api_key: String,
+    http: HttpClient,
 }
 
-pub async fn query_transpose(config: &Config, sql_query: &str, params: &[(&str, &str)]) -> Result<Vec<Value>, Box<dyn std::error::Error>> {
-    let client = Client::new();
-    let url = "https://api.transpose.io/sql";
+impl Transpose {
+    /// Offline, the key is not needed, since cached responses are keyed without it.
+    pub fn new(config: &Config) -> Result<Self> {
+        let api_key = match config.transpose_api_key() {
+            Some(api_key) => api_key,
+            None if config.offline => String::new(),
+            None => return Err(Error::Config("Transpose API key is not set. Please run 'setup' to set it.".to_string())),
+        };
 
-    let mut query = sql_query.to_string();
-    for (key, value) in params {
-        query = query.replace(&format!("{{{{{}}}}}", key), value);
+        Ok(Transpose {
+            api_key,
+            http: HttpClient::new(config, "transpose", URL)?,
+        })
     }
 
-    let response = client.post(url)
-        .header("Content-Type", "application/json")
-        .header("X-API-KEY", config.transpose_api_key().unwrap())
-        .json(&serde_json::json!({ "sql": query }))
-        .send()
-        .await?;
+    /// Runs `sql`. Its results are reused by later runs only when `complete` says they cannot
+    /// change, since anything reaching the head of a history gains rows as new blocks land.
+    async fn query(&self, sql: &str, complete: fn(&[Value]) -> bool) -> Result<Vec<Value>> {
+        let reuse = |response: &HttpResponse| {
+            let results = response.json::<Value>().ok();
+            match results.as_ref().and_then(|result| result.get("results")?.as_array()) {
+                Some(rows) if complete(rows) => Reuse::Stable,
+                _ => Reuse::Volatile,
+            }
+        };
+        let response = self.http.send(|client| client.post(URL)
+            .header("Content-Type", "application/json")
+            .header("X-API-KEY", &self.api_key)
+            .json(&serde_json::json!({ "sql": sql })),
+            reuse,
+        ).await?;
+
+        let result: Value = response.json()
+            .map_err(|e| Error::ProviderResponse(format!("Unexpected Transpose response: {}", e)))?;
 
-    let result: Value = response.json().await?;
-    
-    if let Some(results) = result.get("results").and_then(|v| v.as_array()) {
-        Ok(results.to_vec())
-    } else {
-        Err(format!("Unexpected API response: {:?}", result).into())
+        if let Some(results) = result.get("results").and_then(|v| v.as_array()) {
+            Ok(results.to_vec())
+        } else {
+            Err(Error::ProviderResponse(format!("Unexpected Transpose response: {:?}", result)))
+        }
     }
-}
 
-pub async fn query_ethereum_account(config: &Config, address: &str) -> Result<Vec<Value>, Box<dyn std::error::Error>> {
-    let sql_query = load_sql_query("ethereum_accounts.sql").await;
-    query_transpose(config, &sql_query, &[("address", address)]).await
-}
+    /// The chain's Transpose schema as a template parameter, restricted to the schemas in the registry.
+    fn schema(chain: &Chain) -> Result<Param> {
+        let schema = chain.transpose_schema
+            .ok_or_else(|| Error::Config(format!("Transpose provider does not support chain '{}'", chain.name)))?;
+        Ok(Param::Enum {
+            value: schema.to_string(),
+            allowed: CHAINS.iter().filter_map(|chain| chain.transpose_schema).collect(),
+        })
+    }
 
-pub async fn query_ethereum_transactions(config: &Config, addresses: &[String]) -> Result<Vec<Value>, Box<dyn std::error::Error>> {
-    let sql_query = load_sql_query("ethereum_transactions.sql").await;
-    let mut all_transactions = Vec::new();
-    let mut last_request_time = Instant::now();
+    /// Runs one page of a keyset-paginated query. Each page starts at the cursor bound into
+    /// `params`, so a long history is walked in order without `OFFSET` rescanning everything
+    /// before it.
+    async fn query_page(&self, template: &SqlTemplate, mut params: Vec<(&str, Param)>, full: fn(&[Value]) -> bool) -> Result<Vec<Value>> {
+        params.push(("limit", Param::Integer(PAGE_SIZE)));
+        let query = template.render(&params)?;
+        self.query(&query, full).await
+    }
 
-    for address in addresses {
-        let mut offset = 0;
-        let limit = 100;
+    /// Whether a page of rows is full, so more may follow. Only a full page is complete; a
+    /// short one is the end of the history so far.
+    fn is_full(rows: &[Value]) -> bool {
+        rows.len() as u64 == PAGE_SIZE
+    }
 
-        loop {
-            // Ensure at least 1 second has passed since the last request
-            let elapsed = last_request_time.elapsed();
-            if elapsed < Duration::from_secs(1) {
-                sleep(Duration::from_secs(1) - elapsed).await;
-            }
+    /// Pages of traces are whole transactions, so the page is full when it holds `PAGE_SIZE` of them.
+    fn is_full_of_transactions(rows: &[Value]) -> bool {
+        let transactions: BTreeSet<&str> = rows.iter().filter_map(|row| row["transaction_hash"].as_str()).collect();
+        transactions.len() as u64 == PAGE_SIZE
+    }
 
-            let limit_str = limit.to_string();
-            let offset_str = offset.to_string();
-            let params = vec![
-                ("wallet_address", address.as_str()),
-                ("limit", &limit_str),
-                ("offset", &offset_str),
-            ];
+    /// Binds the parts of `from` named in `key` as the template's cursor parameters.
+    fn cursor_params(from: Cursor, key: &[&'static str]) -> Vec<(&'static str, Param)> {
+        let parts = [from.block_number, from.position, from.sub_position];
+        ["from_block", "from_position", "from_sub_position"].into_iter()
+            .zip(parts)
+            .take(key.len())
+            .map(|(name, part)| (name, Param::Integer(part)))
+            .collect()
+    }
 
-            let transactions = query_transpose(config, &sql_query, &params).await?;
-            last_request_time = Instant::now();
+    /// Converts Transpose's rows into `kind`'s columns, so they reach the sinks in the same shape
+    /// and types as rows built from JSON-RPC.
+    fn typed(rows: Vec<Value>, kind: QueryKind) -> Result<Vec<Value>> {
+        rows.iter()
+            .map(|row| record::typed_row(kind.as_str(), kind.columns(), row)
+                .map_err(|e| Error::ProviderResponse(format!("Unexpected Transpose row: {}", e))))
+            .collect()
+    }
 
-            if transactions.is_empty() {
-                break;
-            }
+    /// A page of `kind`'s rows, ordered by its cursor key. A full page continues just past its
+    /// last row; a short one is the end of the history.
+    fn page(rows: Vec<Value>, full: bool, kind: QueryKind) -> Result<Page> {
+        let rows = Self::typed(rows, kind)?;
+        let next = match rows.last() {
+            Some(last) if full => Some(Cursor::after(last, kind.cursor_key())?),
+            _ => None,
+        };
+        Ok(Page { rows, next })
+    }
 
-            all_transactions.extend(transactions);
-            offset += limit;
+    async fn keyset_page(&self, filename: &str, mut params: Vec<(&str, Param)>, from: Cursor, kind: QueryKind) -> Result<Page> {
+        let template = SqlTemplate::load(filename)?;
+        params.extend(Self::cursor_params(from, kind.cursor_key()));
+        let rows = self.query_page(&template, params, Self::is_full).await?;
+        let full = Self::is_full(&rows);
+        Self::page(rows, full, kind)
+    }
+}
 
-            // Check if we've reached the 1 MB response size limit (approximate)
-            if all_transactions.len() * 1000 > 1_000_000 {
-                println!("Warning: Reached approximate 1 MB response size limit. Some transactions may be missing.");
-                break;
-            }
+#[async_trait]
+impl DataProvider for Transpose {
+    fn name(&self) -> &'static str {
+        "transpose"
+    }
+
+    async fn accounts(&self, chain: &Chain, address: &EvmAddress) -> Result<Vec<Value>> {
+        let template = SqlTemplate::load("evm_accounts.sql")?;
+        let schema = Self::schema(chain)?;
+        let query = template.render(&[("schema", schema), ("address", Param::Address(*address))])?;
+        // Account details change with every new transaction
+        let rows = self.query(&query, |_| false).await?;
+        Self::typed(rows, QueryKind::Accounts)
+    }
+
+    async fn transactions(&self, chain: &Chain, address: &EvmAddress, from: Cursor) -> Result<Page> {
+        let schema = Self::schema(chain)?;
+        let params = vec![("schema", schema), ("wallet_address", Param::Address(*address))];
+        self.keyset_page("evm_transactions.sql", params, from, QueryKind::Transactions).await
+    }
+
+    async fn logs(&self, chain: &Chain, address: &EvmAddress, from: Cursor) -> Result<Page> {
+        let schema = Self::schema(chain)?;
+        let params = vec![("schema", schema), ("address", Param::Address(*address))];
+        self.keyset_page("evm_logs.sql", params, from, QueryKind::Logs).await
+    }
+
+    async fn blocks(&self, chain: &Chain, from_block: u64, to_block: u64) -> Result<Vec<Value>> {
+        let template = SqlTemplate::load("evm_blocks.sql")?;
+        let schema = Self::schema(chain)?;
+        let mut blocks = Vec::new();
+        let mut from = Some(from_block);
+
+        while let Some(from_block) = from {
+            let params = vec![("schema", schema.clone()), ("from_block", Param::Integer(from_block)), ("to_block", Param::Integer(to_block))];
+            let rows = self.query_page(&template, params, Self::is_full).await?;
+            let full = Self::is_full(&rows);
+            let page = Self::page(rows, full, QueryKind::Blocks)?;
+            blocks.extend(page.rows);
+            from = page.next.map(|cursor| cursor.block_number);
         }
+
+        Ok(blocks)
     }
 
-    Ok(all_transactions)
+    async fn token_transfers(&self, chain: &Chain, address: &EvmAddress, from: Cursor) -> Result<Page> {
+        let schema = Self::schema(chain)?;
+        let params = vec![("schema", schema), ("wallet_address", Param::Address(*address))];
+        self.keyset_page("evm_token_transfers.sql", params, from, QueryKind::TokenTransfers).await
+    }
+
+    async fn traces(&self, chain: &Chain, address: &EvmAddress, from: Cursor) -> Result<Page> {
+        let template = SqlTemplate::load("evm_traces.sql")?;
+        let schema = Self::schema(chain)?;
+        let mut params = vec![("schema", schema), ("wallet_address", Param::Address(*address))];
+        params.extend(Self::cursor_params(from, QueryKind::Traces.cursor_key()));
+        let rows = self.query_page(&template, params, Self::is_full_of_transactions).await?;
+        let full = Self::is_full_of_transactions(&rows);
+        Self::page(rows, full, QueryKind::Traces)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn types_rows_through_the_dataset_columns_before_paginating() {
+        let rows = vec![json!({
+            "block_number": "17000000",
+            "block_hash": "0xab",
+            "parent_hash": "0xaa",
+            "timestamp": "2023-04-08 22:13:35",
+            "miner": "0x95222290dd7278aa3ddd389cc1e1d165cc4bafe5",
+            "gas_limit": 30000000,
+            "gas_used": "0x1c9c380",
+            "base_fee_per_gas": 30123456789u64,
+            "transaction_count": 150,
+            "internal_id": 7,
+        })];
+
+        let page = Transpose::page(rows, true, QueryKind::Blocks).unwrap();
+        let names: Vec<&String> = page.rows[0].as_object().unwrap().keys().collect();
+        assert_eq!(names, QueryKind::Blocks.columns().iter().map(|column| column.name).collect::<Vec<_>>());
+        assert_eq!(page.rows[0]["block_number"], json!(17000000));
+        assert_eq!(page.rows[0]["gas_used"], json!(30000000));
+        assert_eq!(page.rows[0]["base_fee_per_gas"], json!("30123456789"));
+        assert_eq!(page.rows[0]["timestamp"], json!("2023-04-08T22:13:35Z"));
+        assert_eq!(page.next, Some(Cursor { block_number: 17000001, position: 0, sub_position: 0 }));
+
+        let malformed = vec![json!({ "block_number": "latest" })];
+        assert!(matches!(Transpose::page(malformed, false, QueryKind::Blocks), Err(Error::ProviderResponse(_))));
+    }
 }
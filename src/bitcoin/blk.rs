@@ -0,0 +1,234 @@
+use crate::bitcoin::decode::{decode_header, sha256d};
+use crate::bitcoin::script::Network;
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Where a block's serialized bytes live inside the `blk*.dat` files.
+#[derive(Clone, Copy)]
+pub struct BlockLocation {
+    pub file: u32,
+    pub offset: u64,
+    pub size: u32,
+}
+
+pub struct IndexedBlock {
+    pub hash: [u8; 32],
+    pub prev_hash: [u8; 32],
+    pub location: BlockLocation,
+}
+
+/// Reader for the raw block files in a Bitcoin Core data directory.
+///
+/// Since v28 Bitcoin Core obfuscates block files with an 8-byte key stored in `blocks/xor.dat`;
+/// the key is picked up automatically unless one is passed explicitly.
+pub struct BlockFiles {
+    dir: PathBuf,
+    xor_key: [u8; 8],
+    cached: Option<(u32, Vec<u8>)>,
+}
+
+impl BlockFiles {
+    pub fn open(data_dir: &Path, xor_key: Option<[u8; 8]>) -> Result<Self> {
+        let dir = if data_dir.join("blocks").is_dir() { data_dir.join("blocks") } else { data_dir.to_path_buf() };
+
+        let xor_key = match xor_key {
+            Some(key) => key,
+            None => match fs::read(dir.join("xor.dat")) {
+                Ok(bytes) => bytes.as_slice().try_into().map_err(|_| Error::Validation("blocks/xor.dat must contain exactly 8 bytes".to_string()))?,
+                Err(_) => [0; 8],
+            },
+        };
+
+        Ok(BlockFiles { dir, xor_key, cached: None })
+    }
+
+    fn path(&self, file: u32) -> PathBuf {
+        self.dir.join(format!("blk{:05}.dat", file))
+    }
+
+    /// Numbers of the `blkNNNNN.dat` files present, in ascending order.
+    pub fn file_numbers(&self) -> Result<Vec<u32>> {
+        let mut numbers = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let name = entry?.file_name().to_string_lossy().to_string();
+            if let Some(number) = name.strip_prefix("blk").and_then(|n| n.strip_suffix(".dat")).and_then(|n| n.parse().ok()) {
+                numbers.push(number);
+            }
+        }
+        numbers.sort_unstable();
+        Ok(numbers)
+    }
+
+    fn deobfuscate(&self, bytes: &mut [u8], file_offset: u64) {
+        if self.xor_key == [0; 8] {
+            return;
+        }
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte ^= self.xor_key[((file_offset + i as u64) % 8) as usize];
+        }
+    }
+
+    /// Reads just the record headers of every block file, so the chain can be ordered without
+    /// loading full blocks into memory.
+    pub fn index(&self) -> Result<(Network, Vec<IndexedBlock>)> {
+        let mut network = None;
+        let mut blocks = Vec::new();
+
+        for file_number in self.file_numbers()? {
+            let mut file = File::open(self.path(file_number))?;
+            let len = file.metadata()?.len();
+            let mut offset = 0u64;
+
+            // Each record is: 4-byte network magic, 4-byte block size, then the block itself
+            while offset + 88 <= len {
+                let mut record = [0u8; 88];
+                file.seek(SeekFrom::Start(offset))?;
+                file.read_exact(&mut record)?;
+                self.deobfuscate(&mut record, offset);
+
+                let magic: [u8; 4] = record[..4].try_into().unwrap();
+                if magic == [0; 4] {
+                    // Bitcoin Core preallocates block files; the zeroed tail is unused space
+                    break;
+                }
+                let record_network = Network::from_magic(magic)
+                    .ok_or_else(|| Error::Validation(format!("Unknown network magic {} in {}", hex::encode(magic), self.path(file_number).display())))?;
+                if *network.get_or_insert(record_network) != record_network {
+                    return Err(Error::Validation(format!("{} mixes blocks from different networks", self.dir.display())));
+                }
+
+                let size = u32::from_le_bytes(record[4..8].try_into().unwrap());
+                let header = decode_header(&record[8..88])
+                    .map_err(|e| Error::Validation(format!("Bad block header in {}: {}", self.path(file_number).display(), e)))?;
+                blocks.push(IndexedBlock {
+                    hash: sha256d(&record[8..88]),
+                    prev_hash: header.prev_hash,
+                    location: BlockLocation { file: file_number, offset: offset + 8, size },
+                });
+
+                offset += 8 + size as u64;
+            }
+        }
+
+        let network = network.ok_or_else(|| Error::Validation(format!("No blocks found in {}", self.dir.display())))?;
+        Ok((network, blocks))
+    }
+
+    /// Returns a block's bytes, keeping the most recently used file in memory since consecutive
+    /// heights usually sit next to each other on disk.
+    pub fn read_block(&mut self, location: BlockLocation) -> Result<Vec<u8>> {
+        if self.cached.as_ref().map(|(file, _)| *file) != Some(location.file) {
+            let mut bytes = fs::read(self.path(location.file))?;
+            self.deobfuscate(&mut bytes, 0);
+            self.cached = Some((location.file, bytes));
+        }
+
+        let (_, bytes) = self.cached.as_ref().unwrap();
+        let start = location.offset as usize;
+        let end = start + location.size as usize;
+        bytes.get(start..end)
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| Error::Validation(format!("Block at blk{:05}.dat:{} is truncated", location.file, location.offset)))
+    }
+}
+
+/// Orders indexed blocks into the best chain starting at genesis, dropping stale forks and
+/// blocks whose ancestors are missing. Uses chain length as a proxy for accumulated work and,
+/// like Bitcoin Core, prefers the first-seen block when two tips have the same height.
+pub fn main_chain(blocks: Vec<IndexedBlock>) -> Vec<IndexedBlock> {
+    let mut children: HashMap<[u8; 32], Vec<usize>> = HashMap::new();
+    for (i, block) in blocks.iter().enumerate() {
+        children.entry(block.prev_hash).or_default().push(i);
+    }
+
+    let mut heights = vec![None; blocks.len()];
+    let mut parents = vec![None; blocks.len()];
+    let mut stack: Vec<usize> = children.get(&[0; 32]).cloned().unwrap_or_default();
+    for &genesis in &stack {
+        heights[genesis] = Some(0u64);
+    }
+
+    while let Some(i) = stack.pop() {
+        let height = heights[i].unwrap();
+        for &child in children.get(&blocks[i].hash).into_iter().flatten() {
+            if heights[child].is_none() {
+                heights[child] = Some(height + 1);
+                parents[child] = Some(i);
+                stack.push(child);
+            }
+        }
+    }
+
+    let Some(mut tip) = (0..blocks.len()).rev().filter(|&i| heights[i].is_some()).max_by_key(|&i| heights[i]) else {
+        return Vec::new();
+    };
+
+    let mut chain_indexes = vec![tip];
+    while let Some(parent) = parents[tip] {
+        chain_indexes.push(parent);
+        tip = parent;
+    }
+    chain_indexes.reverse();
+
+    let mut slots: Vec<Option<IndexedBlock>> = blocks.into_iter().map(Some).collect();
+    chain_indexes.into_iter().filter_map(|i| slots[i].take()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::decode::hash_to_hex;
+    use crate::bitcoin::decode::tests::genesis_block;
+
+    fn indexed(hash: u8, prev_hash: u8) -> IndexedBlock {
+        let prev_hash = if prev_hash == 0 { [0; 32] } else { [prev_hash; 32] };
+        IndexedBlock { hash: [hash; 32], prev_hash, location: BlockLocation { file: 0, offset: 0, size: 0 } }
+    }
+
+    #[test]
+    fn main_chain_follows_the_longest_branch_from_genesis() {
+        // 1 <- 2 <- 3 <- {6, 7}, with a stale fork 4 off 1 and an orphan 5, listed out of order
+        let blocks = vec![indexed(3, 2), indexed(4, 1), indexed(1, 0), indexed(6, 3), indexed(5, 99), indexed(2, 1), indexed(7, 3)];
+        let chain: Vec<u8> = main_chain(blocks).iter().map(|block| block.hash[0]).collect();
+        // 6 and 7 tie, and 6 was seen first
+        assert_eq!(chain, vec![1, 2, 3, 6]);
+    }
+
+    #[test]
+    fn main_chain_is_empty_without_genesis() {
+        assert!(main_chain(vec![indexed(2, 1), indexed(3, 2)]).is_empty());
+    }
+
+    #[test]
+    fn reads_blocks_obfuscated_with_the_xor_key() {
+        let dir = std::env::temp_dir().join(format!("sleuth-blk-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("blocks")).unwrap();
+
+        let block = genesis_block();
+        let mut file = vec![0xf9, 0xbe, 0xb4, 0xd9];
+        file.extend_from_slice(&(block.len() as u32).to_le_bytes());
+        file.extend_from_slice(&block);
+        // Preallocated, zeroed space after the last block
+        file.extend_from_slice(&[0; 100]);
+
+        let key = [0x5a, 0x17, 0xc3, 0x01, 0xee, 0x80, 0x42, 0x9d];
+        let obfuscated: Vec<u8> = file.iter().enumerate().map(|(i, byte)| byte ^ key[i % 8]).collect();
+        fs::write(dir.join("blocks/blk00000.dat"), obfuscated).unwrap();
+        fs::write(dir.join("blocks/xor.dat"), key).unwrap();
+
+        let mut files = BlockFiles::open(&dir, None).unwrap();
+        let (network, blocks) = files.index().unwrap();
+        assert_eq!(network, Network::Mainnet);
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(hash_to_hex(&blocks[0].hash), "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f");
+        assert_eq!(files.read_block(blocks[0].location).unwrap(), block);
+
+        // Without the key the network magic is unrecognizable
+        assert!(BlockFiles::open(&dir, Some([0; 8])).unwrap().index().is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
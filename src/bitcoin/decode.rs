@@ -0,0 +1,271 @@
+use sha2::{Digest, Sha256};
+
+/// The parts of an 80-byte block header needed to order blocks and timestamp transactions.
+pub struct BlockHeader {
+    pub prev_hash: [u8; 32],
+    pub time: u32,
+}
+
+pub struct TxInput {
+    pub prev_txid: [u8; 32],
+    pub prev_vout: u32,
+    pub script_sig: Vec<u8>,
+    pub sequence: u32,
+    pub witness: Vec<Vec<u8>>,
+}
+
+impl TxInput {
+    pub fn is_coinbase(&self) -> bool {
+        self.prev_txid == [0; 32] && self.prev_vout == u32::MAX
+    }
+}
+
+pub struct TxOutput {
+    pub value: u64,
+    pub script_pubkey: Vec<u8>,
+}
+
+pub struct Transaction {
+    pub txid: [u8; 32],
+    pub version: i32,
+    pub locktime: u32,
+    pub size: usize,
+    pub weight: usize,
+    pub inputs: Vec<TxInput>,
+    pub outputs: Vec<TxOutput>,
+}
+
+pub struct Block {
+    pub hash: [u8; 32],
+    pub header: BlockHeader,
+    pub transactions: Vec<Transaction>,
+}
+
+/// Double SHA-256, the hash Bitcoin uses for block and transaction ids.
+pub fn sha256d(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(data)).into()
+}
+
+/// Formats an internal byte-order hash the way block explorers display it.
+pub fn hash_to_hex(hash: &[u8; 32]) -> String {
+    let mut reversed = *hash;
+    reversed.reverse();
+    hex::encode(reversed)
+}
+
+/// Cursor over a serialized block.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.data.len())
+            .ok_or_else(|| format!("Unexpected end of data at byte {}", self.pos))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn hash(&mut self) -> Result<[u8; 32], String> {
+        Ok(self.take(32)?.try_into().unwrap())
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        match self.u8()? {
+            0xfd => Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()) as u64),
+            0xfe => Ok(self.u32()? as u64),
+            0xff => self.u64(),
+            n => Ok(n as u64),
+        }
+    }
+
+    /// Capacity to reserve for `count` items read from untrusted data. Every item takes at least
+    /// a byte, so a corrupt count cannot reserve more than the bytes left.
+    fn capacity(&self, count: u64) -> usize {
+        count.min((self.data.len() - self.pos) as u64) as usize
+    }
+
+    fn var_bytes(&mut self) -> Result<Vec<u8>, String> {
+        let len = self.varint()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+}
+
+pub fn decode_header(data: &[u8]) -> Result<BlockHeader, String> {
+    let mut reader = Reader { data, pos: 4 };
+    let prev_hash = reader.hash()?;
+    reader.take(32)?;
+    let time = reader.u32()?;
+    Ok(BlockHeader { prev_hash, time })
+}
+
+pub fn decode_block(data: &[u8]) -> Result<Block, String> {
+    if data.len() < 80 {
+        return Err(format!("Block is {} bytes, shorter than its 80-byte header", data.len()));
+    }
+    let header = decode_header(data)?;
+    let hash = sha256d(&data[..80]);
+
+    let mut reader = Reader { data, pos: 80 };
+    let count = reader.varint()?;
+    let mut transactions = Vec::with_capacity(reader.capacity(count));
+    for _ in 0..count {
+        transactions.push(decode_transaction(&mut reader)?);
+    }
+
+    Ok(Block { hash, header, transactions })
+}
+
+/// Decodes one transaction, handling the BIP 144 segwit serialization used by segwit and
+/// taproot spends. The txid is computed over the legacy serialization, without witness data.
+fn decode_transaction(reader: &mut Reader) -> Result<Transaction, String> {
+    let start = reader.pos;
+    let version = reader.i32()?;
+
+    let mut segwit = false;
+    if reader.data.get(reader.pos) == Some(&0x00) && reader.data.get(reader.pos + 1) == Some(&0x01) {
+        segwit = true;
+        reader.pos += 2;
+    }
+
+    let io_start = reader.pos;
+    let input_count = reader.varint()?;
+    let mut inputs = Vec::with_capacity(reader.capacity(input_count));
+    for _ in 0..input_count {
+        inputs.push(TxInput {
+            prev_txid: reader.hash()?,
+            prev_vout: reader.u32()?,
+            script_sig: reader.var_bytes()?,
+            sequence: reader.u32()?,
+            witness: Vec::new(),
+        });
+    }
+
+    let output_count = reader.varint()?;
+    let mut outputs = Vec::with_capacity(reader.capacity(output_count));
+    for _ in 0..output_count {
+        outputs.push(TxOutput {
+            value: reader.u64()?,
+            script_pubkey: reader.var_bytes()?,
+        });
+    }
+    let io_end = reader.pos;
+
+    if segwit {
+        for input in &mut inputs {
+            let items = reader.varint()?;
+            for _ in 0..items {
+                input.witness.push(reader.var_bytes()?);
+            }
+        }
+    }
+
+    let locktime_start = reader.pos;
+    let locktime = reader.u32()?;
+    let end = reader.pos;
+
+    let mut legacy = Vec::with_capacity(8 + io_end - io_start);
+    legacy.extend_from_slice(&reader.data[start..start + 4]);
+    legacy.extend_from_slice(&reader.data[io_start..io_end]);
+    legacy.extend_from_slice(&reader.data[locktime_start..end]);
+
+    let size = end - start;
+    Ok(Transaction {
+        txid: sha256d(&legacy),
+        version,
+        locktime,
+        size,
+        weight: legacy.len() * 3 + size,
+        inputs,
+        outputs,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    const GENESIS_HEADER: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c";
+    const GENESIS_COINBASE: &str = "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+    /// The genesis block: its header, one transaction, then the coinbase.
+    pub fn genesis_block() -> Vec<u8> {
+        hex::decode(format!("{}01{}", GENESIS_HEADER, GENESIS_COINBASE)).unwrap()
+    }
+
+    /// The genesis coinbase re-serialized as a segwit transaction, with the marker and flag after
+    /// the version and a 32-byte witness reserved value before the locktime.
+    fn segwit_coinbase() -> Vec<u8> {
+        let legacy = hex::decode(GENESIS_COINBASE).unwrap();
+        let (body, locktime) = legacy.split_at(legacy.len() - 4);
+        let mut segwit = body[..4].to_vec();
+        segwit.extend_from_slice(&[0x00, 0x01]);
+        segwit.extend_from_slice(&body[4..]);
+        segwit.extend_from_slice(&[0x01, 0x20]);
+        segwit.extend_from_slice(&[0; 32]);
+        segwit.extend_from_slice(locktime);
+        segwit
+    }
+
+    #[test]
+    fn decodes_the_genesis_block() {
+        let block = decode_block(&genesis_block()).unwrap();
+        assert_eq!(hash_to_hex(&block.hash), "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f");
+        assert_eq!(block.header.prev_hash, [0; 32]);
+        assert_eq!(block.header.time, 1231006505);
+
+        let coinbase = &block.transactions[0];
+        assert_eq!(hash_to_hex(&coinbase.txid), "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b");
+        assert!(coinbase.inputs[0].is_coinbase());
+        assert_eq!(coinbase.outputs[0].value, 50 * 100_000_000);
+        assert_eq!((coinbase.size, coinbase.weight), (204, 816));
+    }
+
+    #[test]
+    fn segwit_txid_leaves_out_the_witness() {
+        let raw = segwit_coinbase();
+        let mut reader = Reader { data: &raw, pos: 0 };
+        let tx = decode_transaction(&mut reader).unwrap();
+
+        assert_eq!(hash_to_hex(&tx.txid), "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b");
+        assert_eq!(tx.inputs[0].witness, vec![vec![0; 32]]);
+        // The wtxid covers the whole serialization, witness included
+        assert_eq!(tx.size, raw.len());
+        assert_eq!(hash_to_hex(&sha256d(&raw[..tx.size])), "07e82d7dea159429c792524f5fa62c4b3feacb35941c954d72b5d87b736eedb0");
+        // Base size times three plus total size
+        assert_eq!(tx.weight, 204 * 3 + 240);
+    }
+
+    #[test]
+    fn rejects_a_truncated_header() {
+        let block = genesis_block();
+        assert!(decode_block(&block[..75]).is_err());
+    }
+
+    #[test]
+    fn corrupt_counts_do_not_reserve_beyond_the_data() {
+        let mut block = genesis_block();
+        // A transaction count of 2^64 - 1 followed by nothing
+        block.truncate(80);
+        block.extend_from_slice(&[0xff; 9]);
+        assert!(decode_block(&block).is_err());
+    }
+}
@@ -0,0 +1,280 @@
+use crate::api::esplora::BitcoinRecords;
+use crate::api::format_timestamp;
+use crate::bitcoin::blk::{main_chain, BlockFiles, IndexedBlock};
+use crate::bitcoin::decode::{decode_block, hash_to_hex, Block};
+use crate::bitcoin::script::{script_address, script_type, Network};
+use crate::error::{Error, Result};
+use crate::helpers::sink::{Dataset, Sinks};
+use crate::record::columns;
+use colored::*;
+use serde_json::{json, Value};
+use sqlx::postgres::PgPool;
+use sqlx::{Row, SqlitePool};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Upper bound on outputs kept in memory for prevout resolution. Past this, the cache is dropped
+/// after a flush and older prevouts are looked up in `bitcoin_outputs` instead.
+const MAX_CACHED_OUTPUTS: usize = 5_000_000;
+
+pub struct IngestOptions {
+    pub data_dir: PathBuf,
+    pub xor_key: Option<[u8; 8]>,
+    pub batch_blocks: u64,
+    pub to_height: Option<u64>,
+}
+
+/// Resolves the value and address an input spends, from outputs seen earlier in this run or
+/// from rows already written to storage.
+struct PrevoutResolver<'a> {
+    outputs: HashMap<([u8; 32], u32), (u64, Option<String>)>,
+    sqlite_pool: Option<&'a SqlitePool>,
+    pg_pool: Option<&'a PgPool>,
+}
+
+impl PrevoutResolver<'_> {
+    /// The output's value and address, or `None` if it is not known. A failed lookup is an
+    /// error rather than a missing output, so it does not leave fees blank.
+    async fn resolve(&mut self, txid: &[u8; 32], vout: u32) -> Result<Option<(u64, Option<String>)>, sqlx::Error> {
+        if let Some(output) = self.outputs.remove(&(*txid, vout)) {
+            return Ok(Some(output));
+        }
+
+        let txid_hex = hash_to_hex(txid);
+        let row = if let Some(pool) = self.sqlite_pool {
+            let row = sqlx::query("SELECT CAST(value AS INTEGER) AS value, address FROM bitcoin_outputs WHERE txid = ? AND output_index = ?")
+                .bind(&txid_hex)
+                .bind(vout as i64)
+                .fetch_optional(pool).await?;
+            row.map(|row| Ok::<_, sqlx::Error>((row.try_get("value")?, row.try_get("address")?))).transpose()?
+        } else if let Some(pool) = self.pg_pool {
+            let row = sqlx::query("SELECT value::BIGINT AS value, address FROM bitcoin_outputs WHERE txid = $1 AND output_index = $2")
+                .bind(&txid_hex)
+                .bind(vout as i32)
+                .fetch_optional(pool).await?;
+            row.map(|row| Ok::<_, sqlx::Error>((row.try_get("value")?, row.try_get("address")?))).transpose()?
+        } else {
+            None
+        };
+
+        let Some((value, address)): Option<(Option<i64>, Option<String>)> = row else {
+            return Ok(None);
+        };
+        Ok(value.map(|value| (value as u64, address)))
+    }
+}
+
+/// Builds the Bitcoin tables straight from a Bitcoin Core data directory, walking the best
+/// chain in height order and recording progress so an interrupted run picks up where it stopped.
+/// Rows go to every enabled sink; a database is still needed to keep progress and look up the
+/// outputs older inputs spend.
+pub async fn ingest(options: &IngestOptions, sinks: &Sinks, sqlite_pool: Option<&SqlitePool>, pg_pool: Option<&PgPool>) -> Result<()> {
+    if sqlite_pool.is_none() && pg_pool.is_none() {
+        return Err(Error::Config("Block file ingestion needs SQLite or PostgreSQL storage to be enabled".to_string()));
+    }
+
+    let source = options.data_dir.canonicalize()?.to_string_lossy().to_string();
+    let mut files = BlockFiles::open(&options.data_dir, options.xor_key)?;
+
+    println!("{}", "[Step 1] Indexing block files".yellow());
+    let (network, blocks) = files.index()?;
+    let chain = main_chain(blocks);
+    let Some(tip_height) = (chain.len() as u64).checked_sub(1) else {
+        return Err(Error::Validation("No chain starting at the genesis block was found".to_string()));
+    };
+    println!("Found {:?} chain with tip at height {}", network, tip_height);
+
+    let start_height = match load_progress(&source, sqlite_pool, pg_pool).await? {
+        Some((height, block_hash)) => {
+            check_resume_point(&chain, height, &block_hash)?;
+            println!("Resuming after height {}", height);
+            height + 1
+        }
+        None => 0,
+    };
+    let end_height = options.to_height.map_or(tip_height, |height| height.min(tip_height));
+    if start_height > end_height {
+        println!("{}", "Block data is already ingested up to the requested height.".green());
+        return Ok(());
+    }
+
+    println!("{}", format!("[Step 2] Ingesting blocks {} to {}", start_height, end_height).yellow());
+    let mut resolver = PrevoutResolver { outputs: HashMap::new(), sqlite_pool, pg_pool };
+    let mut records = BitcoinRecords::default();
+    let batch_blocks = options.batch_blocks.max(1);
+
+    for height in start_height..=end_height {
+        let indexed = &chain[height as usize];
+        let block = decode_block(&files.read_block(indexed.location)?)
+            .map_err(|e| Error::Validation(format!("Failed to decode block {} at height {}: {}", hash_to_hex(&indexed.hash), height, e)))?;
+        push_block(&mut records, &mut resolver, &block, height, network).await?;
+
+        if (height + 1 - start_height) % batch_blocks == 0 || height == end_height {
+            flush(&mut records, sinks).await?;
+            save_progress(&source, height, &hash_to_hex(&block.hash), sqlite_pool, pg_pool).await?;
+            if resolver.outputs.len() > MAX_CACHED_OUTPUTS {
+                resolver.outputs.clear();
+            }
+            println!("Ingested up to height {} ({:.1}%)", height, (height as f64 / end_height.max(1) as f64) * 100.0);
+        }
+    }
+
+    println!("{}", format!("\nIngested blocks {} to {} from {}", start_height, end_height, source).green());
+    Ok(())
+}
+
+async fn push_block(records: &mut BitcoinRecords, resolver: &mut PrevoutResolver<'_>, block: &Block, height: u64, network: Network) -> Result<()> {
+    let block_hash = hash_to_hex(&block.hash);
+    let block_time = format_timestamp(block.header.time as u64);
+
+    for tx in &block.transactions {
+        let txid = hash_to_hex(&tx.txid);
+        let is_coinbase = tx.inputs.first().is_some_and(|input| input.is_coinbase());
+        let mut input_value = Some(0u64);
+
+        for (index, input) in tx.inputs.iter().enumerate() {
+            let prevout = if is_coinbase { None } else { resolver.resolve(&input.prev_txid, input.prev_vout).await? };
+            input_value = match (input_value, &prevout) {
+                (Some(total), Some((value, _))) => Some(total + value),
+                _ => None,
+            };
+
+            records.inputs.push(json!({
+                "txid": txid,
+                "input_index": index,
+                "prev_txid": if is_coinbase { Value::Null } else { json!(hash_to_hex(&input.prev_txid)) },
+                "prev_vout": if is_coinbase { Value::Null } else { json!(input.prev_vout) },
+                "address": prevout.as_ref().and_then(|(_, address)| address.clone()),
+                "value": prevout.as_ref().map(|(value, _)| *value),
+                "script_sig": hex::encode(&input.script_sig),
+                "witness": input.witness.iter().map(hex::encode).collect::<Vec<_>>().join(","),
+                "sequence": input.sequence,
+            }));
+        }
+
+        let output_value: u64 = tx.outputs.iter().map(|output| output.value).sum();
+        for (index, output) in tx.outputs.iter().enumerate() {
+            let address = script_address(&output.script_pubkey, network);
+            resolver.outputs.insert((tx.txid, index as u32), (output.value, address.clone()));
+
+            records.outputs.push(json!({
+                "txid": txid,
+                "output_index": index,
+                "address": address,
+                "value": output.value,
+                "script_pubkey": hex::encode(&output.script_pubkey),
+                "script_type": script_type(&output.script_pubkey),
+            }));
+        }
+
+        let input_value = if is_coinbase { None } else { input_value };
+        records.transactions.push(json!({
+            "txid": txid,
+            "block_height": height,
+            "block_hash": block_hash,
+            "block_time": block_time,
+            "version": tx.version,
+            "locktime": tx.locktime,
+            "size": tx.size,
+            "weight": tx.weight,
+            "fee": input_value.map(|value| value.saturating_sub(output_value)),
+            "input_count": tx.inputs.len(),
+            "output_count": tx.outputs.len(),
+            "input_value": input_value,
+            "output_value": output_value,
+            "is_coinbase": is_coinbase,
+        }));
+    }
+    Ok(())
+}
+
+async fn flush(records: &mut BitcoinRecords, sinks: &Sinks) -> Result<()> {
+    let batch = std::mem::take(records);
+    sinks.write(&Dataset::new("bitcoin_transactions", columns::BITCOIN_TRANSACTIONS).on_chain("bitcoin"), &batch.transactions).await?;
+    sinks.write(&Dataset::new("bitcoin_inputs", columns::BITCOIN_INPUTS).on_chain("bitcoin"), &batch.inputs).await?;
+    sinks.write(&Dataset::new("bitcoin_outputs", columns::BITCOIN_OUTPUTS).on_chain("bitcoin"), &batch.outputs).await
+}
+
+/// Checks that the last ingested block is still on the best chain. If the data directory has
+/// since followed a reorg past it, the rows already written include blocks that are no longer
+/// on the chain, and they cannot be taken back out of every sink.
+fn check_resume_point(chain: &[IndexedBlock], height: u64, block_hash: &str) -> Result<()> {
+    let current = chain.get(height as usize).map(|indexed| hash_to_hex(&indexed.hash));
+    if current.as_deref() == Some(block_hash) {
+        return Ok(());
+    }
+
+    let found = match current {
+        Some(current) => format!("block {} is there now", current),
+        None => format!("the best chain now ends at height {}", chain.len().saturating_sub(1)),
+    };
+    Err(Error::Validation(format!(
+        "Ingest stopped at block {} at height {}, but {}. The data directory's best chain no longer includes it, after a reorg or because it is another copy; delete the ingested Bitcoin rows and this source's row in bitcoin_ingest_progress to ingest it again.",
+        block_hash, height, found
+    )))
+}
+
+/// The height and hash of the last block ingested from `source`.
+async fn load_progress(source: &str, sqlite_pool: Option<&SqlitePool>, pg_pool: Option<&PgPool>) -> Result<Option<(u64, String)>, sqlx::Error> {
+    let progress = if let Some(pool) = sqlite_pool {
+        sqlx::query("SELECT height, block_hash FROM bitcoin_ingest_progress WHERE source = ?")
+            .bind(source)
+            .fetch_optional(pool).await?
+            .map(|row| (row.get::<i64, _>("height"), row.get::<Option<String>, _>("block_hash")))
+    } else if let Some(pool) = pg_pool {
+        sqlx::query("SELECT height::BIGINT AS height, block_hash FROM bitcoin_ingest_progress WHERE source = $1")
+            .bind(source)
+            .fetch_optional(pool).await?
+            .map(|row| (row.get::<i64, _>("height"), row.get::<Option<String>, _>("block_hash")))
+    } else {
+        None
+    };
+
+    Ok(progress.map(|(height, block_hash)| (height as u64, block_hash.unwrap_or_default())))
+}
+
+async fn save_progress(source: &str, height: u64, block_hash: &str, sqlite_pool: Option<&SqlitePool>, pg_pool: Option<&PgPool>) -> Result<(), sqlx::Error> {
+    let updated_at = chrono::Utc::now().to_rfc3339();
+
+    if let Some(pool) = sqlite_pool {
+        sqlx::query("INSERT OR REPLACE INTO bitcoin_ingest_progress (source, height, block_hash, updated_at) VALUES (?, ?, ?, ?)")
+            .bind(source)
+            .bind(height as i64)
+            .bind(block_hash)
+            .bind(&updated_at)
+            .execute(pool).await?;
+    }
+
+    if let Some(pool) = pg_pool {
+        sqlx::query(
+            "INSERT INTO bitcoin_ingest_progress (source, height, block_hash, updated_at) VALUES ($1, $2, $3, $4)
+             ON CONFLICT (source) DO UPDATE SET height = $2, block_hash = $3, updated_at = $4"
+        )
+            .bind(source)
+            .bind(height as i64)
+            .bind(block_hash)
+            .bind(&updated_at)
+            .execute(pool).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::blk::BlockLocation;
+
+    fn chain(hashes: &[u8]) -> Vec<IndexedBlock> {
+        hashes.iter().map(|&hash| IndexedBlock { hash: [hash; 32], prev_hash: [0; 32], location: BlockLocation { file: 0, offset: 0, size: 0 } }).collect()
+    }
+
+    #[test]
+    fn resumes_only_from_a_block_still_on_the_best_chain() {
+        let chain = chain(&[1, 2, 3]);
+        assert!(check_resume_point(&chain, 1, &hash_to_hex(&[2; 32])).is_ok());
+        // a reorg replaced block 2, or the data directory is behind the ingested height
+        assert!(matches!(check_resume_point(&chain, 1, &hash_to_hex(&[9; 32])), Err(Error::Validation(_))));
+        assert!(matches!(check_resume_point(&chain, 5, &hash_to_hex(&[2; 32])), Err(Error::Validation(_))));
+    }
+}
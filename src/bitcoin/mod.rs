@@ -0,0 +1,4 @@
+pub mod blk;
+pub mod decode;
+pub mod ingest;
+pub mod script;
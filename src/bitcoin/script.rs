@@ -0,0 +1,73 @@
+use bech32::{hrp, Hrp};
+
+/// The Bitcoin network a data directory belongs to, detected from the block file magic bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+impl Network {
+    pub fn from_magic(magic: [u8; 4]) -> Option<Self> {
+        match magic {
+            [0xf9, 0xbe, 0xb4, 0xd9] => Some(Network::Mainnet),
+            [0x0b, 0x11, 0x09, 0x07] | [0x1c, 0x16, 0x3f, 0x28] => Some(Network::Testnet),
+            [0x0a, 0x03, 0xcf, 0x40] => Some(Network::Signet),
+            [0xfa, 0xbf, 0xb5, 0xda] => Some(Network::Regtest),
+            _ => None,
+        }
+    }
+
+    fn p2pkh_version(&self) -> u8 {
+        match self {
+            Network::Mainnet => 0x00,
+            _ => 0x6f,
+        }
+    }
+
+    fn p2sh_version(&self) -> u8 {
+        match self {
+            Network::Mainnet => 0x05,
+            _ => 0xc4,
+        }
+    }
+
+    fn hrp(&self) -> Hrp {
+        match self {
+            Network::Mainnet => hrp::BC,
+            Network::Testnet | Network::Signet => hrp::TB,
+            Network::Regtest => hrp::BCRT,
+        }
+    }
+}
+
+/// Classifies an output script using the same type names Esplora reports, so rows from
+/// `blk*.dat` ingestion and from the API line up.
+pub fn script_type(script: &[u8]) -> &'static str {
+    match script {
+        [] => "empty",
+        [0x76, 0xa9, 0x14, .., 0x88, 0xac] if script.len() == 25 => "p2pkh",
+        [0xa9, 0x14, .., 0x87] if script.len() == 23 => "p2sh",
+        [0x00, 0x14, ..] if script.len() == 22 => "v0_p2wpkh",
+        [0x00, 0x20, ..] if script.len() == 34 => "v0_p2wsh",
+        [0x51, 0x20, ..] if script.len() == 34 => "v1_p2tr",
+        [0x6a, ..] => "op_return",
+        [0x21, .., 0xac] if script.len() == 35 => "p2pk",
+        [0x41, .., 0xac] if script.len() == 67 => "p2pk",
+        [.., 0xae] => "multisig",
+        _ => "unknown",
+    }
+}
+
+/// Encodes the address paying to `script`, if it is one of the standard address types.
+pub fn script_address(script: &[u8], network: Network) -> Option<String> {
+    match script_type(script) {
+        "p2pkh" => Some(bs58::encode(&script[3..23]).with_check_version(network.p2pkh_version()).into_string()),
+        "p2sh" => Some(bs58::encode(&script[2..22]).with_check_version(network.p2sh_version()).into_string()),
+        "v0_p2wpkh" | "v0_p2wsh" => bech32::segwit::encode_v0(network.hrp(), &script[2..]).ok(),
+        "v1_p2tr" => bech32::segwit::encode_v1(network.hrp(), &script[2..]).ok(),
+        _ => None,
+    }
+}
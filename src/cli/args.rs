@@ -0,0 +1,209 @@
+use crate::address::{BitcoinAddress, NameOrAddress, SolanaAddress};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(name = "sleuth", version, about = "A flexible OSINT toolkit for blockchain investigations")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Answer every provider request from the response cache, failing on anything not cached
+    #[arg(long, global = true)]
+    pub offline: bool,
+}
+
+impl Cli {
+    /// Whether the command manages the schema itself, so startup leaves migrations to it.
+    pub fn manages_schema(&self) -> bool {
+        matches!(self.command, Some(Command::Db { .. }))
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Set up the database schema
+    Setup,
+    /// Query EVM account details for one or more addresses or ENS names
+    Account {
+        #[arg(required_unless_present = "files")]
+        addresses: Vec<NameOrAddress>,
+        /// Read more addresses from a CSV, JSON or text address list; repeat for several files
+        #[arg(long = "file", value_name = "PATH")]
+        files: Vec<PathBuf>,
+        /// Chains to query, comma separated (see `sleuth chains`)
+        #[arg(long = "chain", value_delimiter = ',', default_value = "ethereum")]
+        chains: Vec<String>,
+    },
+    /// Query EVM transactions for one or more addresses or ENS names
+    Txs {
+        #[arg(required_unless_present = "files")]
+        addresses: Vec<NameOrAddress>,
+        /// Read more addresses from a CSV, JSON or text address list; repeat for several files
+        #[arg(long = "file", value_name = "PATH")]
+        files: Vec<PathBuf>,
+        /// Chains to query, comma separated (see `sleuth chains`)
+        #[arg(long = "chain", value_delimiter = ',', default_value = "ethereum")]
+        chains: Vec<String>,
+        /// Ignore the saved sync state and fetch the whole history again
+        #[arg(long)]
+        full: bool,
+    },
+    /// Query ERC-20, ERC-721 and ERC-1155 token transfers for one or more addresses
+    Transfers {
+        #[arg(required_unless_present = "files")]
+        addresses: Vec<NameOrAddress>,
+        /// Read more addresses from a CSV, JSON or text address list; repeat for several files
+        #[arg(long = "file", value_name = "PATH")]
+        files: Vec<PathBuf>,
+        /// Chains to query, comma separated (see `sleuth chains`)
+        #[arg(long = "chain", value_delimiter = ',', default_value = "ethereum")]
+        chains: Vec<String>,
+        /// Ignore the saved sync state and fetch the whole history again
+        #[arg(long)]
+        full: bool,
+    },
+    /// Query the internal calls (traces) of every transaction one or more addresses took part in
+    Traces {
+        #[arg(required_unless_present = "files")]
+        addresses: Vec<NameOrAddress>,
+        /// Read more addresses from a CSV, JSON or text address list; repeat for several files
+        #[arg(long = "file", value_name = "PATH")]
+        files: Vec<PathBuf>,
+        /// Chains to query, comma separated (see `sleuth chains`)
+        #[arg(long = "chain", value_delimiter = ',', default_value = "ethereum")]
+        chains: Vec<String>,
+        /// Ignore the saved sync state and fetch the whole history again
+        #[arg(long)]
+        full: bool,
+    },
+    /// Query EVM event logs emitted by a contract address
+    Logs {
+        address: NameOrAddress,
+        /// Chains to query, comma separated (see `sleuth chains`)
+        #[arg(long = "chain", value_delimiter = ',', default_value = "ethereum")]
+        chains: Vec<String>,
+        /// Ignore the saved sync state and fetch the whole history again
+        #[arg(long)]
+        full: bool,
+    },
+    /// Query EVM blocks in an inclusive height range
+    Blocks {
+        from_block: u64,
+        to_block: u64,
+        #[arg(long, default_value = "ethereum")]
+        chain: String,
+    },
+    /// List the EVM chains in the registry
+    Chains,
+    /// Query Bitcoin addresses and transactions through an Esplora-compatible API
+    Bitcoin {
+        #[command(subcommand)]
+        action: BitcoinCommand,
+    },
+    /// Query Solana accounts and transactions through a Solana JSON-RPC endpoint
+    Solana {
+        #[command(subcommand)]
+        action: SolanaCommand,
+    },
+    /// Show or change settings
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+    /// Manage the cache of provider responses
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommand,
+    },
+    /// Manage the database schema
+    Db {
+        #[command(subcommand)]
+        action: DbCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BitcoinCommand {
+    /// Query funding and spending totals for an address
+    Account {
+        address: BitcoinAddress,
+    },
+    /// Query the transaction history of one or more addresses
+    Txs {
+        #[arg(required_unless_present = "files")]
+        addresses: Vec<BitcoinAddress>,
+        /// Read more addresses from a CSV, JSON or text address list; repeat for several files
+        #[arg(long = "file", value_name = "PATH")]
+        files: Vec<PathBuf>,
+    },
+    /// Build the Bitcoin tables offline from a Bitcoin Core data directory's blk*.dat files
+    Ingest {
+        /// Bitcoin Core data directory, or its `blocks` subdirectory
+        data_dir: PathBuf,
+        /// 8-byte block file obfuscation key as hex; read from blocks/xor.dat when omitted
+        #[arg(long)]
+        xor_key: Option<String>,
+        /// Number of blocks written per batch; progress is saved after each batch
+        #[arg(long, default_value_t = 100)]
+        batch_blocks: u64,
+        /// Stop after this height instead of the chain tip
+        #[arg(long)]
+        to_height: Option<u64>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SolanaCommand {
+    /// Query the balance, owner program and data size of an account
+    Account {
+        address: SolanaAddress,
+    },
+    /// Query the transaction history of one or more addresses, with instructions and token balance changes
+    Txs {
+        #[arg(required_unless_present = "files")]
+        addresses: Vec<SolanaAddress>,
+        /// Read more addresses from a CSV, JSON or text address list; repeat for several files
+        #[arg(long = "file", value_name = "PATH")]
+        files: Vec<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// Delete every cached response
+    Clear,
+}
+
+#[derive(Subcommand)]
+pub enum DbCommand {
+    /// Apply pending schema migrations to every enabled database
+    Migrate,
+    /// Show each enabled database's schema version and pending migrations
+    Status,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Print the current settings
+    Show,
+    /// Change a single setting
+    Set {
+        key: ConfigKey,
+        value: String,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ConfigKey {
+    SaveAsCsv,
+    SaveAsSqlite,
+    SaveAsPostgres,
+    SaveAsParquet,
+    TransposeApiKey,
+    PostgresUrl,
+    DefaultProvider,
+    ResolveEnsNames,
+    Workers,
+    BatchSize,
+    Cache,
+}
@@ -1,15 +1,45 @@
+pub mod args;
+
+use crate::error::{Error, Result};
 use colored::*;
 use dialoguer::{theme::ColorfulTheme, Select, Input, MultiSelect};
+use crate::abi::AbiRegistry;
+use crate::address::{AddressError, AddressList, BitcoinAddress, EvmAddress, NameOrAddress, SolanaAddress};
+use crate::address::list::{ListedAddress, Origin};
 use crate::config::Config;
-use crate::api::transpose;
+use crate::config::chains::{self, Chain, CHAINS};
+use crate::api;
+use crate::api::ens::{self, EnsCache};
+use crate::api::esplora::Esplora;
+use crate::api::solana::Solana;
+use crate::api::provider::{Cursor, Page, QueryKind};
+use crate::bitcoin::ingest::{self, IngestOptions};
+use crate::helpers::migrations::{self, Database};
+use crate::helpers::sink::{Dataset, Sinks};
+use crate::record::columns;
 use crate::helpers::storage;
-use crate::helpers::setup_schema;
-use crate::helpers::postgres;
+use crate::helpers::sync_state::{self, SyncState};
 use sqlx::SqlitePool;
 use sqlx::postgres::PgPool;
+use serde_json::{json, Value};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet};
 use std::env;
-use std::fs::OpenOptions;
-use std::io::Write;
+use std::fmt;
+use std::fs;
+use std::future::{poll_fn, Future};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::Poll;
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+
+pub use args::Cli;
+use args::{BitcoinCommand, CacheCommand, Command, ConfigCommand, ConfigKey, DbCommand, SolanaCommand};
 
 const SLEUTH_LOGO: &str = r#"
 ███████╗██╗     ███████╗██╗   ██╗████████╗██╗  ██╗    ██╗  ██╗██╗████████╗
@@ -20,11 +50,112 @@ const SLEUTH_LOGO: &str = r#"
 ╚══════╝╚══════╝╚══════╝ ╚═════╝    ╚═╝   ╚═╝  ╚═╝    ╚═╝  ╚═╝╚═╝   ╚═╝   
 "#;
 
-pub async fn run_cli(
+pub async fn run(
+    cli: Cli,
+    config: &mut Config,
+    sqlite_pool: Option<&SqlitePool>,
+    pg_pool: Option<&PgPool>
+) -> Result<()> {
+    config.offline = cli.offline;
+    match cli.command {
+        Some(command) => {
+            let sinks = Sinks::enabled(config, sqlite_pool, pg_pool);
+            run_command(command, config, &sinks, sqlite_pool, pg_pool).await
+        },
+        None => run_cli(config, sqlite_pool, pg_pool).await,
+    }
+}
+
+async fn run_command(
+    command: Command,
+    config: &mut Config,
+    sinks: &Sinks,
+    sqlite_pool: Option<&SqlitePool>,
+    pg_pool: Option<&PgPool>
+) -> Result<()> {
+    match command {
+        Command::Setup => setup_schema(sinks).await?,
+        Command::Account { addresses, files, chains } => {
+            let addresses = resolve_listed(config, sinks, &address_list(&addresses, &files)?).await?;
+            for chain in resolve_chains(&chains)? {
+                query_evm_accounts(config, sinks, chain, &addresses).await?;
+            }
+        },
+        Command::Txs { addresses, files, chains, full } => {
+            let addresses = resolve_listed(config, sinks, &address_list(&addresses, &files)?).await?;
+            for chain in resolve_chains(&chains)? {
+                query_evm_transactions(config, sinks, chain, &addresses, full, sqlite_pool, pg_pool).await?;
+            }
+        },
+        Command::Transfers { addresses, files, chains, full } => {
+            let addresses = resolve_listed(config, sinks, &address_list(&addresses, &files)?).await?;
+            for chain in resolve_chains(&chains)? {
+                query_evm_token_transfers(config, sinks, chain, &addresses, full, sqlite_pool, pg_pool).await?;
+            }
+        },
+        Command::Traces { addresses, files, chains, full } => {
+            let addresses = resolve_listed(config, sinks, &address_list(&addresses, &files)?).await?;
+            for chain in resolve_chains(&chains)? {
+                query_evm_traces(config, sinks, chain, &addresses, full, sqlite_pool, pg_pool).await?;
+            }
+        },
+        Command::Logs { address, chains, full } => {
+            let address = resolve_address(config, &address).await?;
+            for chain in resolve_chains(&chains)? {
+                query_evm_logs(config, sinks, chain, &address, full, sqlite_pool, pg_pool).await?;
+            }
+        },
+        Command::Blocks { from_block, to_block, chain } => {
+            let chain = chains::chain(&chain)?;
+            query_evm_blocks(config, sinks, chain, from_block, to_block).await?
+        },
+        Command::Chains => print_chains(config),
+        Command::Bitcoin { action } => match action {
+            BitcoinCommand::Account { address } => query_bitcoin_address(config, sinks, &address).await?,
+            BitcoinCommand::Txs { addresses, files } => {
+                let addresses = listed_addresses(sinks, &address_list(&addresses, &files)?).await?;
+                query_bitcoin_transactions(config, sinks, &addresses).await?
+            },
+            BitcoinCommand::Ingest { data_dir, xor_key, batch_blocks, to_height } => {
+                let xor_key = xor_key.as_deref().map(parse_xor_key).transpose()?;
+                let options = IngestOptions { data_dir, xor_key, batch_blocks, to_height };
+                ingest::ingest(&options, sinks, sqlite_pool, pg_pool).await?
+            },
+        },
+        Command::Solana { action } => match action {
+            SolanaCommand::Account { address } => query_solana_account(config, sinks, &address).await?,
+            SolanaCommand::Txs { addresses, files } => {
+                let addresses = listed_addresses(sinks, &address_list(&addresses, &files)?).await?;
+                query_solana_transactions(config, sinks, &addresses).await?
+            },
+        },
+        Command::Config { action } => match action {
+            ConfigCommand::Show => print_settings(config),
+            ConfigCommand::Set { key, value } => {
+                set_config_value(config, key, &value)?;
+                config.save()?;
+            }
+        },
+        Command::Cache { action } => match action {
+            CacheCommand::Clear => {
+                let cleared = api::cache::clear()?;
+                println!("{}", format!("Deleted {} cached responses", cleared).green());
+            }
+        },
+        Command::Db { action } => match action {
+            DbCommand::Migrate => migrate_databases(sqlite_pool, pg_pool).await?,
+            DbCommand::Status => print_schema_status(sqlite_pool, pg_pool).await?,
+        },
+    }
+
+    Ok(())
+}
+
+async fn run_cli(
     config: &mut Config,
     sqlite_pool: Option<&SqlitePool>,
     pg_pool: Option<&PgPool>
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<()> {
     println!("{}", SLEUTH_LOGO.cyan());
     println!("{}", "Sleuth Kit is a flexible and extensible toolkit for blockchain investigation and intelligence gathering.".green());
 
@@ -36,15 +167,49 @@ pub async fn run_cli(
         let selection = Select::with_theme(&ColorfulTheme::default())
             .with_prompt("Choose an option")
             .default(0)
-            .items(&["Setup", "Query Ethereum Account", "Query Ethereum Transactions", "Settings", "Exit"])
+            .items(&["Setup", "Query Ethereum Account", "Query Ethereum Transactions", "Query Ethereum Token Transfers", "Query Bitcoin Address", "Query Bitcoin Transactions", "Query Solana Account", "Query Solana Transactions", "Settings", "Exit"])
             .interact()?;
 
+        // Storage settings can change from one pass to the next
+        let sinks = &Sinks::enabled(config, sqlite_pool, pg_pool);
         match selection {
-            0 => setup(config, sqlite_pool, pg_pool).await?,
-            1 => query_ethereum_account(config, sqlite_pool, pg_pool).await?,
-            2 => query_ethereum_transactions(config, sqlite_pool, pg_pool).await?,
-            3 => settings_menu(config).await?,
-            4 => break,
+            0 => setup(config, sinks).await?,
+            1 => {
+                let addresses = resolve_listed(config, sinks, &prompt_addresses()?).await?;
+                for chain in prompt_chains()? {
+                    query_evm_accounts(config, sinks, chain, &addresses).await?;
+                }
+            },
+            2 => {
+                let addresses = resolve_listed(config, sinks, &prompt_addresses()?).await?;
+                for chain in prompt_chains()? {
+                    query_evm_transactions(config, sinks, chain, &addresses, false, sqlite_pool, pg_pool).await?;
+                }
+            },
+            3 => {
+                let addresses = resolve_listed(config, sinks, &prompt_addresses()?).await?;
+                for chain in prompt_chains()? {
+                    query_evm_token_transfers(config, sinks, chain, &addresses, false, sqlite_pool, pg_pool).await?;
+                }
+            },
+            4 => {
+                let address = prompt_bitcoin_address()?;
+                query_bitcoin_address(config, sinks, &address).await?
+            },
+            5 => {
+                let address = prompt_bitcoin_address()?;
+                query_bitcoin_transactions(config, sinks, &[address]).await?
+            },
+            6 => {
+                let address = prompt_solana_address()?;
+                query_solana_account(config, sinks, &address).await?
+            },
+            7 => {
+                let address = prompt_solana_address()?;
+                query_solana_transactions(config, sinks, &[address]).await?
+            },
+            8 => settings_menu(config).await?,
+            9 => break,
             _ => unreachable!(),
         }
 
@@ -54,15 +219,117 @@ pub async fn run_cli(
     Ok(())
 }
 
-async fn setup(config: &mut Config, sqlite_pool: Option<&SqlitePool>, pg_pool: Option<&PgPool>) -> Result<(), Box<dyn std::error::Error>> {
-    println!("{}", "[Step 1] Setting up database schema".yellow());
-    if let Some(pool) = sqlite_pool {
-        setup_schema::setup_database_schema(pool).await?;
+/// Asks for an address, an ENS name, or the path of an address list file.
+fn prompt_addresses() -> Result<AddressList<NameOrAddress>> {
+    let input: String = Input::new()
+        .with_prompt("Enter Ethereum address, ENS name or path to an address list")
+        .validate_with(|input: &String| -> Result<(), String> {
+            match Path::new(input.trim()).is_file() {
+                true => Ok(()),
+                false => input.parse::<NameOrAddress>().map(|_| ()).map_err(|e| e.to_string()),
+            }
+        })
+        .interact_text()?;
+
+    let path = Path::new(input.trim());
+    match path.is_file() {
+        true => address_list(&[], &[path.to_path_buf()]),
+        false => Ok(AddressList::new(&[input.parse()?])),
+    }
+}
+
+fn prompt_chains() -> Result<Vec<&'static Chain>> {
+    let names: Vec<&str> = CHAINS.iter().map(|chain| chain.display_name).collect();
+    let defaults: Vec<bool> = CHAINS.iter().map(|chain| chain.name == "ethereum").collect();
+    let selections = MultiSelect::with_theme(&ColorfulTheme::default())
+        .with_prompt("Select chains to query (use space to select multiple)")
+        .items(&names)
+        .defaults(&defaults)
+        .interact()?;
+    Ok(selections.into_iter().map(|i| &CHAINS[i]).collect())
+}
+
+fn resolve_chains(names: &[String]) -> Result<Vec<&'static Chain>> {
+    let mut resolved = Vec::new();
+    for name in names {
+        resolved.push(chains::chain(name)?);
     }
-    if let Some(pool) = pg_pool {
-        postgres::setup_postgres_schema(pool).await?;
+    Ok(resolved)
+}
+
+fn prompt_bitcoin_address() -> Result<BitcoinAddress> {
+    let address: BitcoinAddress = Input::new()
+        .with_prompt("Enter Bitcoin address")
+        .interact_text()?;
+    Ok(address)
+}
+
+fn prompt_solana_address() -> Result<SolanaAddress> {
+    let address: SolanaAddress = Input::new()
+        .with_prompt("Enter Solana address")
+        .interact_text()?;
+    Ok(address)
+}
+
+/// An EVM address in EIP-55 form, followed by its ENS name when it has one.
+fn labelled(address: &EvmAddress, names: &BTreeMap<EvmAddress, String>) -> String {
+    match names.get(address) {
+        Some(name) => format!("{} ({})", address.checksummed(), name),
+        None => address.checksummed(),
     }
+}
+
+fn labelled_list(addresses: &[EvmAddress], names: &BTreeMap<EvmAddress, String>) -> String {
+    addresses.iter().map(|address| labelled(address, names)).collect::<Vec<_>>().join(", ")
+}
+
+fn join_addresses<T: ToString>(addresses: &[T]) -> String {
+    addresses.iter().map(T::to_string).collect::<Vec<_>>().join(", ")
+}
+
+async fn setup_schema(sinks: &Sinks) -> Result<()> {
+    println!("{}", "Setting up database schema".yellow());
+    sinks.setup().await?;
     println!("{}", "Database schema set up successfully.".green());
+    Ok(())
+}
+
+fn enabled_databases<'a>(sqlite_pool: Option<&'a SqlitePool>, pg_pool: Option<&'a PgPool>) -> Result<Vec<Database<'a>>> {
+    let databases = Database::enabled(sqlite_pool, pg_pool);
+    if databases.is_empty() {
+        return Err(Error::Config("No database is available. Enable SQLite or PostgreSQL storage with `sleuth config set`".to_string()));
+    }
+    Ok(databases)
+}
+
+async fn migrate_databases(sqlite_pool: Option<&SqlitePool>, pg_pool: Option<&PgPool>) -> Result<()> {
+    for database in enabled_databases(sqlite_pool, pg_pool)? {
+        match database.migrate().await? {
+            0 => println!("{} schema is up to date.", database.name()),
+            applied => println!("{}", format!("Applied {} migrations to {}.", applied, database.name()).green()),
+        }
+    }
+    Ok(())
+}
+
+async fn print_schema_status(sqlite_pool: Option<&SqlitePool>, pg_pool: Option<&PgPool>) -> Result<()> {
+    for database in enabled_databases(sqlite_pool, pg_pool)? {
+        let applied = database.applied().await?;
+        let version = applied.keys().next_back().copied().unwrap_or(0);
+        println!("{}", format!("{} schema version {}", database.name(), version).yellow());
+        for migration in migrations::MIGRATIONS {
+            match applied.get(&migration.version) {
+                Some(applied_at) => println!("  {:>3}  {}  (applied {})", migration.version, migration.description, applied_at),
+                None => println!("  {:>3}  {}  {}", migration.version, migration.description, "(pending)".red()),
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn setup(config: &mut Config, sinks: &Sinks) -> Result<()> {
+    println!("{}", "[Step 1] Setting up database schema".yellow());
+    setup_schema(sinks).await?;
 
     println!("{}", "[Step 2] Configuring API keys".yellow());
     if config.transpose_api_key().is_none() {
@@ -72,7 +339,7 @@ async fn setup(config: &mut Config, sqlite_pool: Option<&SqlitePool>, pg_pool: O
     }
 
     println!("{}", "[Step 3] Configuring storage options".yellow());
-    let storage_options = vec!["CSV", "SQLite", "PostgreSQL"];
+    let storage_options = vec!["CSV", "SQLite", "PostgreSQL", "Parquet"];
     let storage_selections = MultiSelect::with_theme(&ColorfulTheme::default())
         .with_prompt("Select how you would like to store the data (use space to select multiple)")
         .items(&storage_options)
@@ -81,6 +348,7 @@ async fn setup(config: &mut Config, sqlite_pool: Option<&SqlitePool>, pg_pool: O
     config.save_as_csv = storage_selections.contains(&0);
     config.save_as_sqlite = storage_selections.contains(&1);
     config.save_as_postgres = storage_selections.contains(&2);
+    config.save_as_parquet = storage_selections.contains(&3);
 
     if config.save_as_postgres {
         set_postgres_credentials(config).await?;
@@ -88,7 +356,7 @@ async fn setup(config: &mut Config, sqlite_pool: Option<&SqlitePool>, pg_pool: O
             match PgPool::connect(postgres_url).await {
                 Ok(pool) => {
                     println!("Successfully connected to PostgreSQL.");
-                    match postgres::setup_postgres_schema(&pool).await {
+                    match Database::Postgres(&pool).migrate().await {
                         Ok(_) => println!("PostgreSQL schema created successfully."),
                         Err(e) => eprintln!("Error creating PostgreSQL schema: {}", e),
                     }
@@ -101,7 +369,7 @@ async fn setup(config: &mut Config, sqlite_pool: Option<&SqlitePool>, pg_pool: O
     Ok(())
 }
 
-async fn set_postgres_credentials(config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+async fn set_postgres_credentials(config: &mut Config) -> Result<()> {
     let workspace_id: String = Input::new().with_prompt("Enter your PostgreSQL workspace ID").interact_text()?;
     let api_key: String = Input::new().with_prompt("Enter your PostgreSQL API key").interact_text()?;
     let region: String = Input::new().with_prompt("Enter your PostgreSQL region").interact_text()?;
@@ -113,119 +381,818 @@ async fn set_postgres_credentials(config: &mut Config) -> Result<(), Box<dyn std
         workspace_id, api_key, region, database_name, branch_name
     );
 
-    // Save the PostgreSQL URL to the .env file
-    let mut file = OpenOptions::new()
-        .create(true)
-        .write(true)
-        .append(true)
-        .open(".env")?;
-
-    writeln!(file, "POSTGRES_URL={}", postgres_url)?;
-
-    env::set_var("POSTGRES_URL", &postgres_url);
+    save_env_var("POSTGRES_URL", &postgres_url)?;
     config.save_as_postgres = true;
     println!("{}", "PostgreSQL credentials saved successfully.".green());
 
     Ok(())
 }
 
-async fn query_ethereum_account(config: &Config, sqlite_pool: Option<&SqlitePool>, pg_pool: Option<&PgPool>) -> Result<(), Box<dyn std::error::Error>> {
-    if config.transpose_api_key().is_none() {
-        println!("{}", "Transpose API key is not set. Please run 'setup' to set it.".red());
-        return Ok(());
+/// Queries the account details of every address in `addresses`, several at once, then looks up
+/// ENS names for all of them together.
+async fn query_evm_accounts(config: &Config, sinks: &Sinks, chain: &Chain, addresses: &[EvmAddress]) -> Result<()> {
+    let provider = api::provider_for(config, chain, QueryKind::Accounts)?;
+
+    println!("{}", format!("[Step 1] Querying {} account details via {}", chain.display_name, provider.name()).yellow());
+    let counterparties = RefCell::new(BTreeSet::new());
+    let retrieved = RefCell::new(Vec::new());
+    let report = query_addresses(config, addresses, EvmAddress::checksummed, async |address: &EvmAddress| {
+        let account_data = provider.accounts(chain, address).await?;
+        sinks.upsert(&Dataset::evm(chain, "accounts", columns::ACCOUNTS), &account_data).await?;
+        ens::counterparties(&account_data, &mut counterparties.borrow_mut());
+        retrieved.borrow_mut().push(*address);
+        Ok(account_data.len())
+    }).await;
+
+    let retrieved = retrieved.into_inner();
+    let names = record_ens_names(config, sinks, counterparties.into_inner(), &retrieved).await?;
+    for address in &retrieved {
+        println!("{}", format!("\nRetrieved {} account data for address {}", chain.display_name, labelled(address, &names)).green());
+        println!("View on explorer: {}", chain.address_url(&address.checksummed()));
     }
+    report.finish()
+}
 
-    let address: String = Input::new()
-        .with_prompt("Enter Ethereum address")
-        .interact_text()?;
+async fn query_evm_transactions(config: &Config, sinks: &Sinks, chain: &Chain, addresses: &[EvmAddress], full: bool, sqlite_pool: Option<&SqlitePool>, pg_pool: Option<&PgPool>) -> Result<()> {
+    let provider = api::provider_for(config, chain, QueryKind::Transactions)?;
+
+    println!("{}", format!("[Step 1] Querying {} transactions via {}", chain.display_name, provider.name()).yellow());
+    let scope = SyncScope { chain, kind: QueryKind::Transactions, provider: provider.name(), sinks, full };
+    let transactions = Dataset::evm(chain, "transactions", columns::TRANSACTIONS);
+    prepare_sync(&[&transactions, &Dataset::evm(chain, "decoded_calls", columns::DECODED_CALLS)], sqlite_pool, pg_pool);
+    let abis = AbiRegistry::load(&config.abi_dir());
+    let counterparties = RefCell::new(BTreeSet::new());
+
+    let report = sync_addresses(config, &scope, addresses, sqlite_pool, pg_pool,
+        async |address: &EvmAddress, from| provider.transactions(chain, address, from).await,
+        async |sinks: &Sinks, rows: &[Value]| -> Result<()> {
+            sinks.write(&transactions, rows).await?;
+            save_decoded_calls(&abis, sinks, chain, rows).await?;
+            ens::counterparties(rows, &mut counterparties.borrow_mut());
+            Ok(())
+        },
+    ).await;
+
+    if report.rows == 0 {
+        println!("{}", "No new transactions found for the provided addresses".yellow());
+        return report.finish();
+    }
+
+    let names = record_ens_names(config, sinks, counterparties.into_inner(), addresses).await?;
+
+    println!("{}", format!("\nRetrieved and processed {} {} transactions for {}", report.rows, chain.display_name, labelled_list(addresses, &names)).green());
+    report.finish()
+}
+
+async fn query_evm_token_transfers(config: &Config, sinks: &Sinks, chain: &Chain, addresses: &[EvmAddress], full: bool, sqlite_pool: Option<&SqlitePool>, pg_pool: Option<&PgPool>) -> Result<()> {
+    let provider = api::provider_for(config, chain, QueryKind::TokenTransfers)?;
+
+    println!("{}", format!("[Step 1] Querying {} token transfers via {}", chain.display_name, provider.name()).yellow());
+    let scope = SyncScope { chain, kind: QueryKind::TokenTransfers, provider: provider.name(), sinks, full };
+    let transfers = Dataset::evm(chain, "token_transfers", columns::TOKEN_TRANSFERS);
+    prepare_sync(&[&transfers], sqlite_pool, pg_pool);
+    let counterparties = RefCell::new(BTreeSet::new());
+
+    let report = sync_addresses(config, &scope, addresses, sqlite_pool, pg_pool,
+        async |address: &EvmAddress, from| provider.token_transfers(chain, address, from).await,
+        async |sinks: &Sinks, rows: &[Value]| -> Result<()> {
+            sinks.write(&transfers, rows).await?;
+            ens::counterparties(rows, &mut counterparties.borrow_mut());
+            Ok(())
+        },
+    ).await;
+
+    if report.rows == 0 {
+        println!("{}", "No new token transfers found for the provided addresses".yellow());
+        return report.finish();
+    }
+
+    let names = record_ens_names(config, sinks, counterparties.into_inner(), addresses).await?;
+
+    println!("{}", format!("\nRetrieved and processed {} {} token transfers for {}", report.rows, chain.display_name, labelled_list(addresses, &names)).green());
+    report.finish()
+}
+
+async fn query_evm_traces(config: &Config, sinks: &Sinks, chain: &Chain, addresses: &[EvmAddress], full: bool, sqlite_pool: Option<&SqlitePool>, pg_pool: Option<&PgPool>) -> Result<()> {
+    let provider = api::provider_for(config, chain, QueryKind::Traces)?;
+
+    println!("{}", format!("[Step 1] Querying {} traces via {}", chain.display_name, provider.name()).yellow());
+    let scope = SyncScope { chain, kind: QueryKind::Traces, provider: provider.name(), sinks, full };
+    let traces = Dataset::evm(chain, "traces", columns::TRACES);
+    prepare_sync(&[&traces, &Dataset::evm(chain, "decoded_calls", columns::DECODED_CALLS)], sqlite_pool, pg_pool);
+    let abis = AbiRegistry::load(&config.abi_dir());
+    let counterparties = RefCell::new(BTreeSet::new());
+
+    let report = sync_addresses(config, &scope, addresses, sqlite_pool, pg_pool,
+        async |address: &EvmAddress, from| provider.traces(chain, address, from).await,
+        async |sinks: &Sinks, rows: &[Value]| -> Result<()> {
+            sinks.write(&traces, rows).await?;
+            save_decoded_calls(&abis, sinks, chain, rows).await?;
+            ens::counterparties(rows, &mut counterparties.borrow_mut());
+            Ok(())
+        },
+    ).await;
+
+    if report.rows == 0 {
+        println!("{}", "No new traces found for the provided addresses".yellow());
+        return report.finish();
+    }
+
+    let names = record_ens_names(config, sinks, counterparties.into_inner(), addresses).await?;
+
+    println!("{}", format!("\nRetrieved and processed {} {} traces for {}", report.rows, chain.display_name, labelled_list(addresses, &names)).green());
+    report.finish()
+}
+
+async fn query_evm_logs(config: &Config, sinks: &Sinks, chain: &Chain, address: &EvmAddress, full: bool, sqlite_pool: Option<&SqlitePool>, pg_pool: Option<&PgPool>) -> Result<()> {
+    let provider = api::provider_for(config, chain, QueryKind::Logs)?;
+
+    println!("{}", format!("[Step 1] Querying {} logs via {}", chain.display_name, provider.name()).yellow());
+    let scope = SyncScope { chain, kind: QueryKind::Logs, provider: provider.name(), sinks, full };
+    let (logs_dataset, decoded_events) = (Dataset::evm(chain, "logs", columns::LOGS), Dataset::evm(chain, "decoded_events", columns::DECODED_EVENTS));
+    prepare_sync(&[&logs_dataset, &decoded_events], sqlite_pool, pg_pool);
+    let abis = AbiRegistry::load(&config.abi_dir());
+    let counterparties = RefCell::new(BTreeSet::new());
+
+    let report = sync_addresses(config, &scope, std::slice::from_ref(address), sqlite_pool, pg_pool,
+        async |address: &EvmAddress, from| provider.logs(chain, address, from).await,
+        async |sinks: &Sinks, logs: &[Value]| -> Result<()> {
+            sinks.write(&logs_dataset, logs).await?;
+
+            let decoded = abis.decoded_event_rows(logs);
+            if !decoded.is_empty() {
+                println!("{}", format!("Decoded {} of {} logs", decoded.len(), logs.len()).yellow());
+                sinks.write(&decoded_events, &decoded).await?;
+            }
+
+            ens::counterparties(logs, &mut counterparties.borrow_mut());
+            Ok(())
+        },
+    ).await;
 
-    println!("{}", "[Step 1] Querying Ethereum account details".yellow());
-    let account_data = transpose::query_ethereum_account(config, &address).await?;
+    if report.rows == 0 {
+        println!("{}", "No new logs found for the provided address".yellow());
+        return report.finish();
+    }
+
+    let names = record_ens_names(config, sinks, counterparties.into_inner(), std::slice::from_ref(address)).await?;
+
+    println!("{}", format!("\nRetrieved and processed {} logs emitted by {}", report.rows, labelled(address, &names)).green());
+    report.finish()
+}
+
+/// One kind of history on one chain, fetched through one provider and synced page by page.
+struct SyncScope<'a> {
+    chain: &'a Chain,
+    kind: QueryKind,
+    provider: &'static str,
+    /// Where pages are saved, which the sync state records.
+    sinks: &'a Sinks,
+    /// Start from the beginning instead of where the saved sync state left off.
+    full: bool,
+}
+
+/// Set by the first Ctrl-C during a sync, so walks stop at the next page boundary.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// The task answering Ctrl-C. Once tokio takes over SIGINT it keeps it for the rest of the
+/// process, so outside a sync a watcher has to stand in for the default of quitting at once.
+static CTRL_C_WATCHER: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+
+/// Makes `watcher` the one Ctrl-C handler, stopping the one before it.
+fn watch_ctrl_c(watcher: impl Future<Output = ()> + Send + 'static) {
+    let previous = CTRL_C_WATCHER.lock().unwrap().replace(tokio::spawn(watcher));
+    if let Some(previous) = previous {
+        previous.abort();
+    }
+}
+
+/// Ctrl-C handling while a sync runs: the first press stops after the current page, a second
+/// quits at once. Dropping it goes back to quitting on the first press.
+struct SyncInterrupt;
+
+impl SyncInterrupt {
+    fn start() -> Self {
+        INTERRUPTED.store(false, Ordering::Relaxed);
+        watch_ctrl_c(async {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                println!("{}", "\nStopping after the current page. Press Ctrl-C again to quit now.".yellow());
+                INTERRUPTED.store(true, Ordering::Relaxed);
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    std::process::exit(130);
+                }
+            }
+        });
+        SyncInterrupt
+    }
+}
+
+impl Drop for SyncInterrupt {
+    fn drop(&mut self) {
+        watch_ctrl_c(async {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        });
+    }
+}
 
-    if config.save_as_csv {
-        println!("{}", "[Step 2] Saving data to CSV".yellow());
-        storage::save_to_csv(&account_data, "data/csv/ethereum-accounts.csv", &["address", "created_timestamp", "creator_address", "last_active_timestamp", "type"]).await?;
+/// Gets a sync ready to run. Sync state lives in the database, so without one every query
+/// fetches the whole history and rewrites its CSV files. With one, the CSV files of `datasets`
+/// keep accumulating across runs like the tables do.
+fn prepare_sync(datasets: &[&Dataset], sqlite_pool: Option<&SqlitePool>, pg_pool: Option<&PgPool>) {
+    if sqlite_pool.is_none() && pg_pool.is_none() {
+        println!("{}", "Sync state needs SQLite or PostgreSQL storage; fetching the whole history.".yellow());
+        return;
     }
+    for dataset in datasets {
+        storage::continue_csv(&dataset.csv_path);
+    }
+}
 
-    if config.save_as_sqlite {
-        if let Some(pool) = sqlite_pool {
-            println!("{}", "[Step 3] Saving data to SQLite".yellow());
-            storage::save_to_sqlite(pool, &account_data, "ethereum_accounts").await?;
-        } else {
-            println!("SQLite pool is not available. Skipping SQLite save.");
+/// The outcome of syncing a set of addresses.
+struct SyncReport {
+    /// Rows saved across every address, including those that later failed.
+    rows: usize,
+    failed: usize,
+    addresses: usize,
+    /// The first failure caused by a setting, which running again will not get past.
+    config_error: Option<Error>,
+}
+
+impl SyncReport {
+    /// Adds up the rows each address saved and lists the addresses that failed, given as
+    /// `(address, rows, result)`.
+    fn tally(outcomes: Vec<(String, usize, Result<()>)>) -> Self {
+        let mut report = SyncReport { rows: 0, failed: 0, addresses: outcomes.len(), config_error: None };
+        let mut failures = Vec::new();
+        for (address, rows, result) in outcomes {
+            report.rows += rows;
+            if let Err(e) = result {
+                failures.push((address, e));
+            }
         }
+
+        if !failures.is_empty() {
+            report.failed = failures.len();
+            println!("{}", format!("\n{} of {} addresses did not sync completely:", failures.len(), report.addresses).red());
+            for (address, e) in &failures {
+                println!("  {} {}", address, e);
+            }
+            report.config_error = failures.into_iter().map(|(_, e)| e).find(|e| matches!(e, Error::Config(_)));
+        }
+        report
     }
 
-    if config.save_as_postgres {
-        if let Some(pool) = pg_pool {
-            println!("{}", "[Step 4] Saving data to PostgreSQL".yellow());
-            match postgres::save_to_postgres(pool, &account_data, "ethereum_accounts").await {
-                Ok(_) => println!("Data saved to PostgreSQL successfully."),
-                Err(e) => eprintln!("Error saving data to PostgreSQL: {}", e),
+    /// Fails the query if any address did not sync completely, once everything that did has
+    /// been saved and reported. A configuration error wins, since resuming would not help.
+    fn finish(self) -> Result<()> {
+        if let Some(e) = self.config_error {
+            return Err(e);
+        }
+        match self.failed {
+            0 => Ok(()),
+            _ if INTERRUPTED.load(Ordering::Relaxed) => Err(Error::Interrupted),
+            failed => Err(Error::Incomplete { failed, total: self.addresses }),
+        }
+    }
+}
+
+/// Syncs every address in `addresses`, with up to `config.workers` of them in flight at once.
+/// They share one provider, so its rate limiter paces them all together. An address that fails
+/// is reported and left for the next run while the others carry on. Ctrl-C stops every walk at
+/// its next page boundary.
+async fn sync_addresses(
+    config: &Config,
+    scope: &SyncScope<'_>,
+    addresses: &[EvmAddress],
+    sqlite_pool: Option<&SqlitePool>,
+    pg_pool: Option<&PgPool>,
+    fetch: impl AsyncFn(&EvmAddress, Cursor) -> Result<Page>,
+    save: impl AsyncFn(&Sinks, &[Value]) -> Result<()>,
+) -> SyncReport {
+    let _interrupt = SyncInterrupt::start();
+    let workers = Semaphore::new(config.workers.max(1));
+    let (workers, fetch, save) = (&workers, &fetch, &save);
+    let walks = addresses.iter().map(|address| async move {
+        let _permit = workers.acquire().await;
+        let walked = walk_pages(scope, address, sqlite_pool, pg_pool, fetch, save).await;
+        (address, walked)
+    });
+
+    let outcomes = join_all(walks.collect()).await.into_iter()
+        .map(|(address, (rows, result))| (address.checksummed(), rows, result))
+        .collect();
+    SyncReport::tally(outcomes)
+}
+
+/// Runs `query` for every address in `addresses`, with up to `config.workers` of them in flight
+/// at once like `sync_addresses`. `query` returns how many rows it saved. An address that fails
+/// is reported, by its `label`, while the others carry on.
+async fn query_addresses<T>(config: &Config, addresses: &[T], label: fn(&T) -> String, query: impl AsyncFn(&T) -> Result<usize>) -> SyncReport {
+    let workers = Semaphore::new(config.workers.max(1));
+    let (workers, query) = (&workers, &query);
+    let queries = addresses.iter().map(|address| async move {
+        let _permit = workers.acquire().await;
+        let result = query(address).await;
+        (label(address), *result.as_ref().unwrap_or(&0), result.map(|_| ()))
+    });
+    SyncReport::tally(join_all(queries.collect()).await)
+}
+
+/// Drives every future in `tasks` concurrently on the current task and returns their outputs
+/// in order. Each wake-up starts polling one future further along, so when they contend for a
+/// rate limiter no one of them always gets the next token.
+async fn join_all<F: Future>(tasks: Vec<F>) -> Vec<F::Output> {
+    let mut tasks: Vec<Pin<Box<F>>> = tasks.into_iter().map(Box::pin).collect();
+    let mut outputs: Vec<Option<F::Output>> = tasks.iter().map(|_| None).collect();
+    let mut first = 0;
+
+    poll_fn(|cx| {
+        let mut pending = false;
+        for offset in 0..tasks.len() {
+            let i = (first + offset) % tasks.len();
+            if outputs[i].is_none() {
+                match tasks[i].as_mut().poll(cx) {
+                    Poll::Ready(value) => outputs[i] = Some(value),
+                    Poll::Pending => pending = true,
+                }
+            }
+        }
+        first += 1;
+        if pending { Poll::Pending } else { Poll::Ready(()) }
+    }).await;
+
+    outputs.into_iter().flatten().collect()
+}
+
+/// Syncs one address's history page by page, handing each page to `save` as soon as it
+/// arrives rather than holding the whole history in memory. The walk starts where the saved
+/// sync state left off, and the state is saved after every page, so an interrupted walk resumes
+/// from the page it stopped at and a finished one only fetches newer activity next time.
+/// Backends enabled since the last sync are backfilled: the walk starts over, giving them alone
+/// the rows the others already hold. Returns the number of new rows saved, even when the walk
+/// fails partway, and reports progress after each page: the totals once it reaches the end, or
+/// exactly what was saved and where it stopped otherwise.
+async fn walk_pages(
+    scope: &SyncScope<'_>,
+    address: &EvmAddress,
+    sqlite_pool: Option<&SqlitePool>,
+    pg_pool: Option<&PgPool>,
+    fetch: &impl AsyncFn(&EvmAddress, Cursor) -> Result<Page>,
+    save: &impl AsyncFn(&Sinks, &[Value]) -> Result<()>,
+) -> (usize, Result<()>) {
+    let label = scope.kind.as_str().replace('_', " ");
+    let saved = match scope.full {
+        true => Ok(None),
+        false => sync_state::load(scope.chain, address, scope.kind, sqlite_pool, pg_pool).await,
+    };
+    let mut state = match saved {
+        Ok(Some(state)) if state.provider != scope.provider => {
+            println!("{} for {} were synced with {}, which numbers positions differently; fetching the whole history again with {}", label, address.checksummed(), state.provider, scope.provider);
+            SyncState::new(scope.provider)
+        }
+        Ok(Some(state)) if state.complete => {
+            println!("Fetching new {} for {} since {}", label, address.checksummed(), state.cursor);
+            state
+        }
+        Ok(Some(state)) => {
+            println!("Resuming {} for {} at {}, after {} saved so far", label, address.checksummed(), state.cursor, state.rows_synced);
+            state
+        }
+        Ok(None) => SyncState::new(scope.provider),
+        Err(e) => return (0, Err(e.into())),
+    };
+
+    let enabled = scope.sinks.names();
+    let behind: Vec<&str> = match &state.sinks {
+        Some(synced) if state.cursor != Cursor::default() => enabled.iter().copied().filter(|name| !synced.iter().any(|synced| synced == name)).collect(),
+        _ => Vec::new(),
+    };
+    // The backends to backfill and the saved cursor they catch up to
+    let mut backfill = match behind.is_empty() {
+        true => None,
+        false => {
+            println!("Backfilling {} for {} into {}, enabled since the last sync", label, address.checksummed(), behind.join(" and "));
+            Some((scope.sinks.only(&behind), state.cursor))
+        }
+    };
+    state.sinks = Some(enabled.iter().map(|name| name.to_string()).collect());
+    let mut cursor = match backfill {
+        Some(_) => Cursor::default(),
+        None => state.cursor,
+    };
+    let (mut rows, mut pages, mut backfilled) = (0, 0, 0);
+
+    loop {
+        let fetched = match INTERRUPTED.load(Ordering::Relaxed) {
+            true => Err(Error::Interrupted),
+            false => fetch(address, cursor).await,
+        };
+        let result = match fetched {
+            Ok(page) if page.next.is_some_and(|next| next <= cursor) => Err(Error::ProviderResponse(format!("{} pagination did not advance past {}", label, cursor))),
+            Ok(page) => save_page(&page.rows, scope.sinks, backfill.as_ref(), scope.kind, save).await.map(|old| (page, old)),
+            Err(e) => Err(e),
+        };
+        let (page, old) = match result {
+            Ok(saved) => saved,
+            Err(e) => {
+                let message = format!("Incomplete: saved {} {} for {} in {} pages before stopping at {}", rows, label, address.checksummed(), pages, cursor);
+                println!("{}", message.red());
+                return (rows, Err(e));
+            }
+        };
+        let new = &page.rows[old..];
+        backfilled += old;
+        rows += new.len();
+        pages += 1;
+
+        // The backfill ends with the page that reaches the saved cursor; until then the state
+        // keeps pointing at it
+        if let Some((_, resume)) = backfill {
+            match page.next {
+                Some(next) if next < resume => {
+                    println!("{}: page {} backfilled, {} {} so far", address.checksummed(), pages, backfilled, label);
+                    cursor = next;
+                    continue;
+                }
+                _ => backfill = None,
+            }
+        }
+
+        state.rows_synced += new.len() as u64;
+        state.complete = page.next.is_none();
+        if let Some(last) = new.last() {
+            state.last_block = last["block_number"].as_u64().or_else(|| last["block_number"].as_str()?.parse().ok());
+            state.last_timestamp = last["timestamp"].as_str().map(str::to_string);
+        }
+        // A finished walk picks up just past its newest row next time
+        let next = match (page.next, new.last()) {
+            (Some(next), _) => Ok(next),
+            (None, Some(last)) => Cursor::after(last, scope.kind.cursor_key()),
+            (None, None) => Ok(state.cursor),
+        };
+        let recorded = match next {
+            Ok(next) => {
+                state.cursor = next;
+                sync_state::save(scope.chain, address, scope.kind, &state, sqlite_pool, pg_pool).await.map_err(Into::into)
             }
-        } else {
-            println!("PostgreSQL pool is not available. Skipping PostgreSQL save.");
+            Err(e) => Err(e),
+        };
+        if let Err(e) = recorded {
+            return (rows, Err(e));
         }
+
+        if state.complete {
+            break;
+        }
+        cursor = state.cursor;
+        println!("{}: page {} saved, {} {} so far, next at {}", address.checksummed(), pages, rows, label, state.cursor);
     }
 
-    println!("{}", format!("\nRetrieved account data for address {}", address).green());
-    Ok(())
+    if backfilled > 0 {
+        println!("{}", format!("Backfilled {} {} for {}", backfilled, label, address.checksummed()).cyan());
+    }
+    println!("{}", format!("Complete: saved {} new {} for {} in {} pages, {} in all", rows, label, address.checksummed(), pages, state.rows_synced).cyan());
+    (rows, Ok(()))
 }
 
-async fn query_ethereum_transactions(config: &Config, sqlite_pool: Option<&SqlitePool>, pg_pool: Option<&PgPool>) -> Result<(), Box<dyn std::error::Error>> {
-    if config.transpose_api_key().is_none() {
-        println!("{}", "Transpose API key is not set. Please run 'setup' to set it.".red());
-        return Ok(());
+/// Saves one page of a walk. While backfilling, the rows before the saved cursor go to the
+/// backfilled backends alone and the rest to every backend. Returns how many were backfilled.
+async fn save_page(
+    rows: &[Value],
+    sinks: &Sinks,
+    backfill: Option<&(Sinks, Cursor)>,
+    kind: QueryKind,
+    save: &impl AsyncFn(&Sinks, &[Value]) -> Result<()>,
+) -> Result<usize> {
+    let mut old = 0;
+    if let Some((behind, resume)) = backfill {
+        for row in rows {
+            if Cursor::after(row, kind.cursor_key())? > *resume {
+                break;
+            }
+            old += 1;
+        }
+        if old > 0 {
+            save(behind, &rows[..old]).await?;
+        }
+    }
+    if old < rows.len() {
+        save(sinks, &rows[old..]).await?;
     }
+    Ok(old)
+}
 
-    let address: String = Input::new()
-        .with_prompt("Enter Ethereum address")
-        .interact_text()?;
+/// Resolves an address typed as an ENS name. Names are resolved on Ethereum, whichever chain is
+/// queried afterwards.
+async fn resolve_address(config: &Config, input: &NameOrAddress) -> Result<EvmAddress> {
+    let name = match input {
+        NameOrAddress::Address(address) => return Ok(*address),
+        NameOrAddress::Name(name) => name,
+    };
+
+    let mut cache = EnsCache::load();
+    let address = match cache.address(name) {
+        Some(address) => address,
+        None => {
+            let ethereum = chains::chain("ethereum")?;
+            let provider = api::provider_for(config, ethereum, QueryKind::Ens)?;
+            let address = provider.resolve_name(ethereum, name).await?
+                .ok_or_else(|| Error::Validation(format!("ENS name {} does not resolve to an address", name)))?;
+            cache.set_address(name, address);
+            cache.save()?;
+            address
+        }
+    };
+
+    println!("Resolved {} to {}", name, address.checksummed());
+    Ok(address)
+}
+
+/// Gathers the addresses given on the command line and in address list `files`, reporting
+/// what each file added and the entries that were skipped as invalid.
+fn address_list<T: FromStr<Err = AddressError> + fmt::Display + Clone>(addresses: &[T], files: &[PathBuf]) -> Result<AddressList<T>> {
+    let mut list = AddressList::new(addresses);
+    for file in files {
+        let summary = list.load(file)?;
+        for invalid in &summary.invalid {
+            println!("{}", format!("Skipping {}", invalid).red());
+        }
+        println!("{}", format!("Loaded {} addresses from {} ({} duplicates, {} invalid)", summary.added, file.display(), summary.duplicates, summary.invalid.len()).yellow());
+    }
+
+    if list.entries().is_empty() {
+        return Err(Error::Validation("No valid addresses to query".to_string()));
+    }
+    Ok(list)
+}
+
+/// Resolves the ENS names in `list` and records where its file entries came from. A name read
+/// from a file that does not resolve is skipped rather than failing the whole list, and
+/// addresses several names resolve to are only queried once.
+async fn resolve_listed(config: &Config, sinks: &Sinks, list: &AddressList<NameOrAddress>) -> Result<Vec<EvmAddress>> {
+    let mut addresses = Vec::new();
+    let mut inputs = Vec::new();
+    for listed in list.entries() {
+        let address = match (resolve_address(config, &listed.address).await, listed.origins.first()) {
+            (Ok(address), _) => address,
+            (Err(e), Some(origin)) => {
+                println!("{}", format!("Skipping {}: {}", origin, e).red());
+                continue;
+            }
+            (Err(e), None) => return Err(e),
+        };
+        if !addresses.contains(&address) {
+            addresses.push(address);
+        }
+        inputs.extend(listed.origins.iter().map(|origin| address_input_row(&address.to_string(), listed, origin)));
+    }
 
-    println!("{}", "[Step 1] Querying Ethereum transactions".yellow());
-    let transactions = transpose::query_ethereum_transactions(config, &[address.clone()]).await?;
+    if addresses.is_empty() {
+        return Err(Error::Validation("None of the listed addresses resolved".to_string()));
+    }
+    record_address_inputs(sinks, &inputs).await?;
+    Ok(addresses)
+}
+
+/// The addresses in `list`, after recording where its file entries came from.
+async fn listed_addresses<T: fmt::Display + Clone>(sinks: &Sinks, list: &AddressList<T>) -> Result<Vec<T>> {
+    let inputs: Vec<Value> = list.entries().iter()
+        .flat_map(|listed| listed.origins.iter().map(|origin| address_input_row(&listed.address.to_string(), listed, origin)))
+        .collect();
+    record_address_inputs(sinks, &inputs).await?;
+    Ok(list.addresses())
+}
 
-    if transactions.is_empty() {
-        println!("{}", "No transactions found for the provided address".yellow());
+fn address_input_row<T>(address: &str, listed: &ListedAddress<T>, origin: &Origin) -> Value {
+    json!({
+        "address": address,
+        "source_file": origin.file,
+        "line": origin.line.to_string(),
+        "input": listed.input,
+        "label": listed.label,
+        "notes": listed.notes,
+        "imported_at": chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+/// Stores `address_inputs` rows, so every address queried from a file can be traced back to it.
+async fn record_address_inputs(sinks: &Sinks, inputs: &[Value]) -> Result<()> {
+    if inputs.is_empty() {
         return Ok(());
     }
+    sinks.upsert(&Dataset::new("address_inputs", columns::ADDRESS_INPUTS), inputs).await
+}
 
-    let total_transactions = transactions.len();
+/// Looks up reverse ENS names for the queried addresses and the `counterparties` found in their
+/// results, stores newly found names in `ens_names` and prints all known ones. A failed lookup
+/// is reported but does not fail the query it labels.
+async fn record_ens_names(config: &Config, sinks: &Sinks, mut counterparties: BTreeSet<EvmAddress>, addresses: &[EvmAddress]) -> Result<BTreeMap<EvmAddress, String>> {
+    let mut names = BTreeMap::new();
+    if !config.resolve_ens_names {
+        return Ok(names);
+    }
 
-    if config.save_as_csv {
-        println!("{}", "[Step 2] Saving data to CSV".yellow());
-        storage::save_to_csv(&transactions, "data/csv/ethereum-transactions.csv", &["transaction_hash", "base_fee_per_gas", "block_number", "contract_address", "fees_burned", "fees_rewarded", "fees_saved", "from_address", "gas_limit", "gas_price", "gas_used", "input", "internal_failed_transaction_count", "internal_transaction_count", "log_count", "max_fee_per_gas", "max_priority_fee_per_gas", "nonce", "output", "position", "timestamp", "to_address", "transaction_fee", "type", "value"]).await?;
+    let mut cache = EnsCache::load();
+    counterparties.extend(addresses);
+    let candidates = counterparties;
+    let missing: Vec<EvmAddress> = candidates.iter().filter(|address| cache.name(address).is_none()).copied().collect();
+
+    if !missing.is_empty() {
+        println!("{}", format!("Looking up ENS names for {} addresses", missing.len()).yellow());
+        let ethereum = chains::chain("ethereum")?;
+        let found = match api::provider_for(config, ethereum, QueryKind::Ens) {
+            Ok(provider) => provider.reverse_names(ethereum, &missing).await,
+            Err(e) => Err(e),
+        };
+
+        match found {
+            Ok(found) => {
+                for address in &missing {
+                    let name = found.iter().find(|row| row["address"] == address.to_string()).and_then(|row| row["name"].as_str());
+                    cache.set_name(address, name);
+                }
+                cache.save()?;
+                if !found.is_empty() {
+                    sinks.upsert(&Dataset::new("ens_names", columns::ENS_NAMES), &found).await?;
+                }
+            },
+            Err(e) => println!("{}", format!("Skipping ENS name lookup: {}", e).red()),
+        }
     }
 
-    if config.save_as_sqlite {
-        if let Some(pool) = sqlite_pool {
-            println!("{}", "[Step 3] Saving data to SQLite".yellow());
-            storage::save_to_sqlite(pool, &transactions, "ethereum_transactions").await?;
-        } else {
-            println!("SQLite pool is not available. Skipping SQLite save.");
+    for address in &candidates {
+        if let Some(Some(name)) = cache.name(address) {
+            names.insert(*address, name.to_string());
         }
     }
 
-    if config.save_as_postgres {
-        if let Some(pool) = pg_pool {
-            println!("{}", "[Step 4] Saving data to PostgreSQL".yellow());
-            postgres::save_to_postgres(pool, &transactions, "ethereum_transactions").await?;
-        } else {
-            println!("PostgreSQL pool is not available. Skipping PostgreSQL save.");
+    if !names.is_empty() {
+        println!("{}", format!("ENS names for {} of {} addresses:", names.len(), candidates.len()).cyan());
+        for (address, name) in &names {
+            println!("  {} {}", address.checksummed(), name);
+        }
+    }
+
+    Ok(names)
+}
+
+/// Decodes the calldata of transaction or trace rows against the known ABIs and stores the
+/// calls that matched.
+async fn save_decoded_calls(abis: &AbiRegistry, sinks: &Sinks, chain: &Chain, rows: &[serde_json::Value]) -> Result<()> {
+    let decoded = abis.decoded_call_rows(rows);
+    if decoded.is_empty() {
+        return Ok(());
+    }
+
+    println!("{}", format!("Decoded {} of {} calls", decoded.len(), rows.len()).yellow());
+    sinks.write(&Dataset::evm(chain, "decoded_calls", columns::DECODED_CALLS), &decoded).await
+}
+
+async fn query_evm_blocks(config: &Config, sinks: &Sinks, chain: &Chain, from_block: u64, to_block: u64) -> Result<()> {
+    let provider = api::provider_for(config, chain, QueryKind::Blocks)?;
+
+    println!("{}", format!("[Step 1] Querying {} blocks via {}", chain.display_name, provider.name()).yellow());
+    let blocks = provider.blocks(chain, from_block, to_block).await?;
+
+    sinks.write(&Dataset::evm(chain, "blocks", columns::BLOCKS), &blocks).await?;
+
+    println!("{}", format!("\nRetrieved and processed {} blocks from {} to {}", blocks.len(), from_block, to_block).green());
+    Ok(())
+}
+
+async fn query_bitcoin_address(config: &Config, sinks: &Sinks, address: &BitcoinAddress) -> Result<()> {
+    let esplora = Esplora::new(config)?;
+
+    println!("{}", "[Step 1] Querying Bitcoin address details".yellow());
+    let address_data = esplora.address(address).await?;
+
+    sinks.upsert(&Dataset::new("bitcoin_addresses", columns::BITCOIN_ADDRESSES).on_chain("bitcoin"), &address_data).await?;
+
+    println!("{}", format!("\nRetrieved address data for {}", address).green());
+    Ok(())
+}
+
+async fn query_bitcoin_transactions(config: &Config, sinks: &Sinks, addresses: &[BitcoinAddress]) -> Result<()> {
+    let esplora = Esplora::new(config)?;
+
+    println!("{}", "[Step 1] Querying Bitcoin transactions".yellow());
+    // Counts every saved page, including those of an address that fails later on
+    let saved = Cell::new(0);
+    let report = query_addresses(config, addresses, BitcoinAddress::to_string, async |address: &BitcoinAddress| {
+        let (mut after_txid, mut found) = (None, 0);
+        loop {
+            let (records, next) = esplora.transaction_page(address, after_txid.as_deref()).await?;
+            sinks.write(&Dataset::new("bitcoin_transactions", columns::BITCOIN_TRANSACTIONS).on_chain("bitcoin"), &records.transactions).await?;
+            sinks.write(&Dataset::new("bitcoin_inputs", columns::BITCOIN_INPUTS).on_chain("bitcoin"), &records.inputs).await?;
+            sinks.write(&Dataset::new("bitcoin_outputs", columns::BITCOIN_OUTPUTS).on_chain("bitcoin"), &records.outputs).await?;
+            found += records.transactions.len();
+            saved.set(saved.get() + records.transactions.len());
+            match next {
+                Some(next) => after_txid = Some(next),
+                None => break,
+            }
         }
+        println!("Found {} new transactions for {}", found, address);
+        Ok(found)
+    }).await;
+
+    if saved.get() == 0 {
+        println!("{}", "No transactions found for the provided addresses".yellow());
+    } else {
+        println!("{}", format!("\nRetrieved and processed {} transactions for {}", saved.get(), join_addresses(addresses)).green());
     }
+    report.finish()
+}
+
+async fn query_solana_account(config: &Config, sinks: &Sinks, address: &SolanaAddress) -> Result<()> {
+    let solana = Solana::new(config)?;
 
-    println!("{}", format!("\nRetrieved and processed {} transactions for address {}", total_transactions, address).green());
+    println!("{}", "[Step 1] Querying Solana account details".yellow());
+    let account_data = solana.account(address).await?;
+
+    if account_data.is_empty() {
+        println!("{}", format!("No Solana account found for {}", address).yellow());
+        return Ok(());
+    }
+
+    sinks.upsert(&Dataset::new("solana_accounts", columns::SOLANA_ACCOUNTS).on_chain("solana"), &account_data).await?;
+
+    println!("{}", format!("\nRetrieved Solana account data for {}", address).green());
     Ok(())
 }
 
-async fn settings_menu(config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+async fn query_solana_transactions(config: &Config, sinks: &Sinks, addresses: &[SolanaAddress]) -> Result<()> {
+    let solana = Solana::new(config)?;
+
+    println!("{}", "[Step 1] Querying Solana transactions".yellow());
+    // Counts every saved page, including those of an address that fails later on
+    let saved = Cell::new(0);
+    let report = query_addresses(config, addresses, SolanaAddress::to_string, async |address: &SolanaAddress| {
+        let (mut before, mut found) = (None, 0);
+        loop {
+            let (records, next) = solana.transaction_page(address, before.as_deref()).await?;
+            sinks.write(&Dataset::new("solana_transactions", columns::SOLANA_TRANSACTIONS).on_chain("solana"), &records.transactions).await?;
+            sinks.write(&Dataset::new("solana_instructions", columns::SOLANA_INSTRUCTIONS).on_chain("solana"), &records.instructions).await?;
+            sinks.write(&Dataset::new("solana_token_balance_changes", columns::SOLANA_TOKEN_BALANCE_CHANGES).on_chain("solana"), &records.token_balance_changes).await?;
+            found += records.transactions.len();
+            saved.set(saved.get() + records.transactions.len());
+            match next {
+                Some(next) => before = Some(next),
+                None => break,
+            }
+        }
+        println!("Found {} new transactions for {}", found, address);
+        Ok(found)
+    }).await;
+
+    if saved.get() == 0 {
+        println!("{}", "No transactions found for the provided addresses".yellow());
+    } else {
+        println!("{}", format!("\nRetrieved and processed {} Solana transactions for {}", saved.get(), join_addresses(addresses)).green());
+    }
+    report.finish()
+}
+
+fn print_chains(config: &Config) {
+    for chain in CHAINS {
+        println!("{}", format!("{} ({})", chain.display_name, chain.name).cyan());
+        println!("  Chain ID: {}", chain.chain_id);
+        println!("  Native Symbol: {}", chain.native_symbol);
+        println!("  Explorer: {}", chain.explorer);
+        println!("  RPC Endpoint: {}", config.rpc_url(chain));
+        println!("  Transpose Schema: {}", chain.transpose_schema.unwrap_or("Not Supported"));
+    }
+}
+
+fn print_settings(config: &Config) {
     println!("\nCurrent Settings:");
+    println!("Default Provider: {}", config.default_provider);
+    println!("Resolve ENS Names: {}", config.resolve_ens_names);
+    for (chain, selection) in &config.providers {
+        println!("Provider Overrides for {}: {}", chain, serde_json::to_string(selection).unwrap_or_default());
+    }
+    println!("Workers: {}", config.workers);
+    println!("Batch Size: {}", config.batch_size);
+    println!("Response Cache: {}", config.cache.enabled);
+    for (provider, ttl) in &config.cache.ttl_seconds {
+        println!("Cache TTL for {}: {}s", provider, ttl);
+    }
+    println!("HTTP Timeout: {}s, Retries: {}", config.http.timeout_seconds, config.http.max_retries);
+    for (provider, limit) in &config.http.rate_limits {
+        println!("Rate Limit for {}: {} requests/s, burst {}", provider, limit.requests_per_second, limit.burst);
+    }
     println!("Transpose API Key: {}", if config.transpose_api_key().is_some() { "Set" } else { "Not Set" });
     println!("Save as CSV: {}", config.save_as_csv);
     println!("Save as SQLite: {}", config.save_as_sqlite);
     println!("Save as PostgreSQL: {}", config.save_as_postgres);
+    println!("Save as Parquet: {}", config.save_as_parquet);
     println!("PostgreSQL URL: {}", if config.postgres_url().is_some() { "{workspace_id}:{api_key}@{region}.sql.xata.sh:5432/{database_name}:{branch_name}" } else { "Not Set" });
+}
+
+async fn settings_menu(config: &mut Config) -> Result<()> {
+    print_settings(config);
 
     let selection = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("Settings")
@@ -243,12 +1210,13 @@ async fn settings_menu(config: &mut Config) -> Result<(), Box<dyn std::error::Er
     Ok(())
 }
 
-async fn configure_storage_options(config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
-    let storage_options = vec!["CSV", "SQLite", "PostgreSQL"];
-    let mut initial_selection = vec![false, false, false];
+async fn configure_storage_options(config: &mut Config) -> Result<()> {
+    let storage_options = ["CSV", "SQLite", "PostgreSQL", "Parquet"];
+    let mut initial_selection = vec![false, false, false, false];
     if config.save_as_csv { initial_selection[0] = true; }
     if config.save_as_sqlite { initial_selection[1] = true; }
     if config.save_as_postgres { initial_selection[2] = true; }
+    if config.save_as_parquet { initial_selection[3] = true; }
 
     let storage_selections = MultiSelect::with_theme(&ColorfulTheme::default())
         .with_prompt("Select how you would like to store the data (use space to select multiple)")
@@ -259,6 +1227,7 @@ async fn configure_storage_options(config: &mut Config) -> Result<(), Box<dyn st
     config.save_as_csv = storage_selections.contains(&0);
     config.save_as_sqlite = storage_selections.contains(&1);
     config.save_as_postgres = storage_selections.contains(&2);
+    config.save_as_parquet = storage_selections.contains(&3);
 
     if config.save_as_postgres && config.postgres_url().is_none() {
         set_postgres_credentials(config).await?;
@@ -268,19 +1237,95 @@ async fn configure_storage_options(config: &mut Config) -> Result<(), Box<dyn st
     Ok(())
 }
 
-async fn set_transpose_api_key(_config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+async fn set_transpose_api_key(_config: &mut Config) -> Result<()> {
     let api_key: String = Input::new().with_prompt("Enter your Transpose API key").interact_text()?;
-    
-    // Save the API key to the .env file
-    let mut file = OpenOptions::new()
-        .create(true)
-        .write(true)
-        .append(true)
-        .open(".env")?;
-
-    writeln!(file, "TRANSPOSE_API_KEY={}", api_key)?;
+    save_env_var("TRANSPOSE_API_KEY", &api_key)?;
 
     println!("{}", "Transpose API key saved successfully.".green());
 
+    Ok(())
+}
+
+fn set_config_value(config: &mut Config, key: ConfigKey, value: &str) -> Result<()> {
+    match key {
+        ConfigKey::SaveAsCsv => config.save_as_csv = parse_bool(value)?,
+        ConfigKey::SaveAsSqlite => config.save_as_sqlite = parse_bool(value)?,
+        ConfigKey::SaveAsPostgres => config.save_as_postgres = parse_bool(value)?,
+        ConfigKey::SaveAsParquet => config.save_as_parquet = parse_bool(value)?,
+        ConfigKey::TransposeApiKey => save_env_var("TRANSPOSE_API_KEY", value)?,
+        ConfigKey::PostgresUrl => save_env_var("POSTGRES_URL", value)?,
+        ConfigKey::DefaultProvider => config.default_provider = match api::PROVIDERS.contains(&value) {
+            true => value.to_string(),
+            false => return Err(Error::Validation(format!("Unknown data provider '{}'; expected one of {}", value, api::PROVIDERS.join(", ")))),
+        },
+        ConfigKey::ResolveEnsNames => {
+            config.resolve_ens_names = parse_bool(value)?;
+            if config.resolve_ens_names && env::var("ETHEREUM_RPC_URL").is_err() {
+                let ethereum = chains::chain("ethereum")?;
+                println!("{}", format!("ENS lookups will send queried addresses and their counterparties to {}. Set ETHEREUM_RPC_URL to use your own node.", ethereum.rpc_url).yellow());
+            }
+        }
+        ConfigKey::Workers => config.workers = match value.parse() {
+            Ok(workers) if workers > 0 => workers,
+            _ => return Err(Error::Validation(format!("Expected a worker count above 0, got '{}'", value))),
+        },
+        ConfigKey::BatchSize => config.batch_size = match value.parse() {
+            Ok(batch_size) if batch_size > 0 => batch_size,
+            _ => return Err(Error::Validation(format!("Expected a batch size above 0, got '{}'", value))),
+        },
+        ConfigKey::Cache => config.cache.enabled = parse_bool(value)?,
+    }
+
+    println!("{}", "Setting saved successfully.".green());
+    Ok(())
+}
+
+fn parse_xor_key(value: &str) -> Result<[u8; 8]> {
+    let bytes = hex::decode(value.trim_start_matches("0x"))
+        .map_err(|e| Error::Validation(format!("XOR key is not valid hex: {}", e)))?;
+    bytes.as_slice().try_into().map_err(|_| Error::Validation("XOR key must be exactly 8 bytes (16 hex characters)".to_string()))
+}
+
+fn parse_bool(value: &str) -> Result<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err(Error::Validation(format!("Expected a boolean value (true/false), got '{}'", value))),
+    }
+}
+
+/// Sets `key=value` in the .env file and makes it visible to the running process. An existing
+/// definition of `key` is replaced in place, since dotenv keeps the first one it reads.
+fn save_env_var(key: &str, value: &str) -> Result<()> {
+    let contents = match fs::read_to_string(".env") {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    let definition = format!("{}={}", key, value);
+    let mut found = false;
+    let mut lines: Vec<String> = Vec::new();
+    for line in contents.lines() {
+        let defines_key = line.trim_start().trim_start_matches("export ").trim_start()
+            .split_once('=')
+            .is_some_and(|(name, _)| name.trim() == key);
+        match defines_key {
+            // Later duplicates, written by older versions that appended, are dropped
+            true if found => {}
+            true => {
+                found = true;
+                lines.push(definition.clone());
+            }
+            false => lines.push(line.to_string()),
+        }
+    }
+    if !found {
+        lines.push(definition);
+    }
+
+    fs::write(".env", lines.join("\n") + "\n")?;
+    env::set_var(key, value);
+
     Ok(())
 }
\ No newline at end of file
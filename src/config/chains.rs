@@ -0,0 +1,88 @@
+use crate::error::{Error, Result};
+
+/// Static description of an EVM chain Sleuth Kit can query.
+pub struct Chain {
+    /// Short name used in config, CLI flags, table names (`{name}_transactions`) and CSV file names.
+    pub name: &'static str,
+    pub display_name: &'static str,
+    pub chain_id: u64,
+    pub native_symbol: &'static str,
+    pub explorer: &'static str,
+    /// Public JSON-RPC endpoint used when `{NAME}_RPC_URL` is not set.
+    pub rpc_url: &'static str,
+    /// Schema holding this chain's tables in the Transpose SQL API, if Transpose indexes it.
+    pub transpose_schema: Option<&'static str>,
+}
+
+/// Adding a chain also needs a schema migration creating its tables; see `helpers::migrations`.
+pub const CHAINS: &[Chain] = &[
+    Chain {
+        name: "ethereum",
+        display_name: "Ethereum",
+        chain_id: 1,
+        native_symbol: "ETH",
+        explorer: "https://etherscan.io",
+        rpc_url: "https://ethereum-rpc.publicnode.com",
+        transpose_schema: Some("ethereum"),
+    },
+    Chain {
+        name: "base",
+        display_name: "Base",
+        chain_id: 8453,
+        native_symbol: "ETH",
+        explorer: "https://basescan.org",
+        rpc_url: "https://mainnet.base.org",
+        transpose_schema: Some("base"),
+    },
+    Chain {
+        name: "arbitrum",
+        display_name: "Arbitrum One",
+        chain_id: 42161,
+        native_symbol: "ETH",
+        explorer: "https://arbiscan.io",
+        rpc_url: "https://arb1.arbitrum.io/rpc",
+        transpose_schema: Some("arbitrum"),
+    },
+    Chain {
+        name: "optimism",
+        display_name: "OP Mainnet",
+        chain_id: 10,
+        native_symbol: "ETH",
+        explorer: "https://optimistic.etherscan.io",
+        rpc_url: "https://mainnet.optimism.io",
+        transpose_schema: Some("optimism"),
+    },
+    Chain {
+        name: "polygon",
+        display_name: "Polygon PoS",
+        chain_id: 137,
+        native_symbol: "POL",
+        explorer: "https://polygonscan.com",
+        rpc_url: "https://polygon-rpc.com",
+        transpose_schema: Some("polygon"),
+    },
+];
+
+impl Chain {
+    pub fn table(&self, kind: &str) -> String {
+        format!("{}_{}", self.name, kind)
+    }
+
+    pub fn csv_path(&self, kind: &str) -> String {
+        format!("data/csv/{}-{}.csv", self.name, kind)
+    }
+
+    pub fn address_url(&self, address: &str) -> String {
+        format!("{}/address/{}", self.explorer, address)
+    }
+}
+
+/// Looks up a chain by name, listing the known chains if it is not registered.
+pub fn chain(name: &str) -> Result<&'static Chain> {
+    CHAINS.iter()
+        .find(|chain| chain.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| {
+            let known = CHAINS.iter().map(|chain| chain.name).collect::<Vec<_>>().join(", ");
+            Error::Validation(format!("Unknown chain '{}'. Known chains: {}", name, known))
+        })
+}
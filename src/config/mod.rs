@@ -1,40 +1,206 @@
+pub mod chains;
+
+use crate::error::{Error, Result};
 use dotenv::dotenv;
+use std::collections::HashMap;
 use std::env;
 use std::fs;
+use std::io;
+use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
+use crate::api::provider::QueryKind;
+use chains::Chain;
 
 #[derive(Serialize, Deserialize)]
 pub struct Config {
     pub save_as_csv: bool,
     pub save_as_sqlite: bool,
     pub save_as_postgres: bool,
+    /// Whether to append results to the Parquet data lake in `data/parquet`.
+    #[serde(default)]
+    pub save_as_parquet: bool,
+    #[serde(default = "default_provider")]
+    pub default_provider: String,
+    /// Per-chain provider overrides, keyed by chain name.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub providers: HashMap<String, ProviderSelection>,
+    /// Whether to look up reverse ENS names for the addresses in query results. Off unless
+    /// asked for, since every queried address and counterparty is sent to the Ethereum node.
+    #[serde(default)]
+    pub resolve_ens_names: bool,
+    #[serde(default)]
+    pub http: HttpSettings,
+    /// How many addresses are fetched at once in multi-address queries.
+    #[serde(default = "default_workers")]
+    pub workers: usize,
+    /// Rows written per multi-row `INSERT` or `COPY` when saving to a database.
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    #[serde(default)]
+    pub cache: CacheSettings,
+    /// Answer provider requests from the response cache alone. Set for one run by `--offline`.
+    #[serde(skip)]
+    pub offline: bool,
+}
+
+/// The on-disk cache of provider responses in `data/cache/http`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CacheSettings {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Per-provider overrides of how long a response is served from the cache, keyed by provider
+    /// name. 0 stops caching the provider's responses.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub ttl_seconds: HashMap<String, u64>,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        CacheSettings { enabled: true, ttl_seconds: HashMap::new() }
+    }
+}
+
+/// Timeouts, retries and rate limits for every provider's HTTP requests.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HttpSettings {
+    /// Seconds before a request with no response is abandoned and retried.
+    #[serde(default = "default_timeout_seconds")]
+    pub timeout_seconds: u64,
+    /// Retries after a timeout, a 429 or a 5xx before the request fails.
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Per-provider overrides of the built-in rate limits, keyed by provider name (`transpose`,
+    /// `rpc`, `esplora`, `solana`).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub rate_limits: HashMap<String, RateLimit>,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        HttpSettings {
+            timeout_seconds: default_timeout_seconds(),
+            max_retries: default_max_retries(),
+            rate_limits: HashMap::new(),
+        }
+    }
+}
+
+/// A token bucket: `requests_per_second` on average, with up to `burst` requests at once.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct RateLimit {
+    pub requests_per_second: f64,
+    #[serde(default = "default_burst")]
+    pub burst: u32,
+}
+
+/// Provider choice for one chain. `default` applies to every query kind without its own entry.
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct ProviderSelection {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accounts: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transactions: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logs: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocks: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_transfers: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traces: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ens: Option<String>,
+}
+
+impl ProviderSelection {
+    fn for_kind(&self, kind: QueryKind) -> Option<&str> {
+        let specific = match kind {
+            QueryKind::Accounts => &self.accounts,
+            QueryKind::Transactions => &self.transactions,
+            QueryKind::Logs => &self.logs,
+            QueryKind::Blocks => &self.blocks,
+            QueryKind::TokenTransfers => &self.token_transfers,
+            QueryKind::Traces => &self.traces,
+            // Transpose has no ENS lookups, so `default` does not apply
+            QueryKind::Ens => return self.ens.as_deref(),
+        };
+        specific.as_deref().or(self.default.as_deref())
+    }
+}
+
+fn default_provider() -> String {
+    "transpose".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_workers() -> usize {
+    4
+}
+
+fn default_batch_size() -> usize {
+    1000
+}
+
+fn default_timeout_seconds() -> u64 {
+    30
+}
+
+fn default_max_retries() -> u32 {
+    5
+}
+
+fn default_burst() -> u32 {
+    1
 }
 
 impl Config {
-    pub fn new() -> Self {
+    /// Loads config.json, or builds the settings from the environment when there is none yet.
+    /// A config.json that cannot be read or parsed is an error rather than silently ignored.
+    pub fn new() -> Result<Self> {
         dotenv().ok();
 
-        if let Ok(config) = Self::load() {
-            config
+        if let Some(config) = Self::load()? {
+            Ok(config)
         } else {
-            Config {
+            Ok(Config {
                 save_as_csv: env::var("SAVE_AS_CSV").unwrap_or_else(|_| "true".to_string()).to_lowercase() == "true",
                 save_as_sqlite: env::var("SAVE_AS_SQLITE").unwrap_or_else(|_| "true".to_string()).to_lowercase() == "true",
                 save_as_postgres: env::var("SAVE_AS_POSTGRES").unwrap_or_else(|_| "false".to_string()).to_lowercase() == "true",
-            }
+                save_as_parquet: env::var("SAVE_AS_PARQUET").unwrap_or_else(|_| "false".to_string()).to_lowercase() == "true",
+                default_provider: env::var("DEFAULT_PROVIDER").unwrap_or_else(|_| default_provider()),
+                providers: HashMap::new(),
+                resolve_ens_names: env::var("RESOLVE_ENS_NAMES").unwrap_or_else(|_| "false".to_string()).to_lowercase() == "true",
+                http: HttpSettings::default(),
+                workers: env::var("WORKERS").ok().and_then(|v| v.parse().ok()).unwrap_or_else(default_workers),
+                batch_size: env::var("BATCH_SIZE").ok().and_then(|v| v.parse().ok()).unwrap_or_else(default_batch_size),
+                cache: CacheSettings::default(),
+                offline: false,
+            })
         }
     }
 
-    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let config_json = serde_json::to_string(self)?;
+    pub fn save(&self) -> Result<()> {
+        let config_json = serde_json::to_string(self)
+            .map_err(|e| Error::Config(format!("Unable to serialize config: {}", e)))?;
         fs::write("config.json", config_json)?;
         Ok(())
     }
 
-    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
-        let config_json = fs::read_to_string("config.json")?;
-        let config: Config = serde_json::from_str(&config_json)?;
-        Ok(config)
+    /// Reads config.json, or `None` if it does not exist.
+    pub fn load() -> Result<Option<Self>> {
+        let config_json = match fs::read_to_string("config.json") {
+            Ok(config_json) => config_json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Error::Config(format!("Unable to read config.json: {}", e))),
+        };
+        let config: Config = serde_json::from_str(&config_json)
+            .map_err(|e| Error::Config(format!("config.json is not valid: {}", e)))?;
+        Ok(Some(config))
     }
 
     pub fn transpose_api_key(&self) -> Option<String> {
@@ -44,4 +210,45 @@ impl Config {
     pub fn postgres_url(&self) -> Option<String> {
         env::var("POSTGRES_URL").ok()
     }
-}
\ No newline at end of file
+
+    /// JSON-RPC endpoint for `chain`, read from `{CHAIN}_RPC_URL` (e.g. `BASE_RPC_URL`).
+    /// Defaults to the public endpoint in the chain registry.
+    pub fn rpc_url(&self, chain: &Chain) -> String {
+        env::var(format!("{}_RPC_URL", chain.name.to_uppercase())).unwrap_or_else(|_| chain.rpc_url.to_string())
+    }
+
+    /// First block to scan when the node cannot answer `trace_filter`, read from `{CHAIN}_RPC_SCAN_FROM`.
+    /// There is no default: scanning from genesis would fetch every block of the chain.
+    pub fn rpc_scan_from_block(&self, chain: &Chain) -> Result<Option<u64>> {
+        let var = format!("{}_RPC_SCAN_FROM", chain.name.to_uppercase());
+        match env::var(&var) {
+            Ok(value) => value.trim().parse().map(Some)
+                .map_err(|_| Error::Config(format!("{} must be a block number, got '{}'", var, value))),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Base URL of the Esplora-compatible API used for Bitcoin, read from `ESPLORA_URL`.
+    pub fn esplora_url(&self) -> String {
+        env::var("ESPLORA_URL").unwrap_or_else(|_| "https://blockstream.info/api".to_string())
+    }
+
+    /// Directory of extra contract ABI JSON files, from `ABI_DIR` or `data/abis`.
+    pub fn abi_dir(&self) -> PathBuf {
+        PathBuf::from(env::var("ABI_DIR").unwrap_or_else(|_| "data/abis".to_string()))
+    }
+
+    /// Solana JSON-RPC endpoint, from `SOLANA_RPC_URL` or the public mainnet-beta endpoint.
+    pub fn solana_rpc_url(&self) -> String {
+        env::var("SOLANA_RPC_URL").unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string())
+    }
+
+    /// Name of the provider to use for `kind` queries on `chain`. ENS lookups use `rpc` unless
+    /// an `ens` provider is set for the chain.
+    pub fn provider_name(&self, chain: &str, kind: QueryKind) -> &str {
+        let fallback = if kind == QueryKind::Ens { "rpc" } else { &self.default_provider };
+        self.providers.get(chain)
+            .and_then(|selection| selection.for_kind(kind))
+            .unwrap_or(fallback)
+    }
+}
@@ -0,0 +1,94 @@
+use crate::address::AddressError;
+use crate::api::rpc::RpcError;
+use crate::api::template::TemplateError;
+use std::io;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Everything that can stop a command, grouped by what can be done about it. Each group exits
+/// with its own code, so scripts can tell a bad input from an unreachable provider.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// A setting is missing or invalid: an unset API key, an unknown chain or provider, a
+    /// malformed `config.json`.
+    #[error("{0}")]
+    Config(String),
+
+    /// Input was rejected before anything was fetched.
+    #[error("{0}")]
+    Validation(String),
+    #[error(transparent)]
+    Address(#[from] AddressError),
+    #[error(transparent)]
+    Template(#[from] TemplateError),
+
+    /// The provider could not be reached, or kept failing until the retries ran out.
+    #[error("{0}")]
+    Network(String),
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+
+    /// The provider kept answering 429, or asked to wait longer than is worth waiting.
+    #[error("{provider} is rate limiting requests: {message}")]
+    RateLimited { provider: &'static str, message: String },
+
+    /// The provider answered, but not with what was asked for.
+    #[error("{0}")]
+    ProviderResponse(String),
+    #[error(transparent)]
+    Rpc(#[from] RpcError),
+
+    /// Results could not be read or written locally.
+    #[error("{0}")]
+    Storage(String),
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error(transparent)]
+    Parquet(#[from] parquet::errors::ParquetError),
+
+    /// An interactive prompt failed, usually because there is no terminal to prompt on.
+    #[error(transparent)]
+    Prompt(#[from] dialoguer::Error),
+
+    /// Some addresses stopped partway; everything fetched for them has been saved.
+    #[error("{failed} of {total} addresses did not sync completely")]
+    Incomplete { failed: usize, total: usize },
+
+    /// Ctrl-C stopped a sync at a page boundary.
+    #[error("Interrupted")]
+    Interrupted,
+}
+
+impl Error {
+    /// The process exit code for this kind of failure. 2 is left to clap's usage errors.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Prompt(_) => 1,
+            Error::Config(_) => 3,
+            Error::Validation(_) | Error::Address(_) | Error::Template(_) => 4,
+            Error::Network(_) | Error::Http(_) => 5,
+            Error::ProviderResponse(_) | Error::Rpc(_) => 6,
+            Error::RateLimited { .. } => 7,
+            Error::Storage(_) | Error::Database(_) | Error::Io(_) | Error::Csv(_) | Error::Parquet(_) => 8,
+            Error::Incomplete { .. } => 9,
+            Error::Interrupted => 130,
+        }
+    }
+
+    /// What to try next, when there is more to say than the message itself.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Error::Config(_) => Some("Check config.json and .env, or run `sleuth config show` to see the current settings."),
+            Error::Network(_) | Error::Http(_) => Some("Check the connection and the provider's endpoint URL. Add --offline to replay cached responses instead."),
+            Error::RateLimited { .. } => Some("Wait a while before retrying, or lower the provider's rate under http.rate_limits in config.json."),
+            Error::ProviderResponse(_) | Error::Rpc(_) => Some("The provider may not support this query; try another with `sleuth config set default-provider`."),
+            Error::Storage(_) | Error::Database(_) | Error::Io(_) | Error::Csv(_) | Error::Parquet(_) => Some("Check that the data directory is writable, and run `sleuth db status` to check the database schema."),
+            Error::Incomplete { .. } | Error::Interrupted => Some("Run the same command again to resume where it stopped."),
+            Error::Validation(_) | Error::Address(_) | Error::Template(_) | Error::Prompt(_) => None,
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use crate::error::{Error, Result};
+use sqlx::postgres::PgPool;
+use sqlx::{Executor, Row, SqlitePool};
+use std::collections::BTreeMap;
+
+/// A numbered change to the schema. Its scripts are shared by both databases: `{amount}` stands
+/// for the type of exact integer amounts, and the chain script runs once for each listed chain
+/// with `{chain}` replaced by the chain's name. Each migration runs once per database, in its own
+/// transaction, and is recorded in `schema_version` so later runs skip it.
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    script: &'static str,
+    chain_script: &'static str,
+    chains: &'static [&'static str],
+}
+
+/// Every migration, oldest first. Released migrations must never be edited: a schema change,
+/// including the tables of a newly added chain, is a new migration at the end of the list.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Create the chain, Bitcoin, Solana, sync and address tables",
+        script: include_str!("migrations/0001_initial_schema.sql"),
+        chain_script: include_str!("migrations/0001_chain_tables.sql"),
+        chains: &["ethereum", "base", "arbitrum", "optimism", "polygon"],
+    },
+    Migration {
+        version: 2,
+        description: "Record which storage backends hold each synced history",
+        script: include_str!("migrations/0002_sync_state_sinks.sql"),
+        chain_script: "",
+        chains: &[],
+    },
+];
+
+impl Migration {
+    /// The migration's SQL for `database`, with every listed chain's tables and the database's
+    /// amount type filled in.
+    fn sql(&self, database: &Database) -> String {
+        let mut sql = self.script.to_string();
+        for chain in self.chains {
+            sql.push('\n');
+            sql.push_str(&self.chain_script.replace("{chain}", chain));
+        }
+        sql.replace("{amount}", database.amount_type())
+    }
+}
+
+/// Tables created by the schema setup that predates versioned migrations, with their columns in
+/// order. Migration 1 takes them over: they are renamed aside, created anew and their rows copied.
+const BASELINE_TABLES: &[(&str, &[&str])] = &[
+    ("ethereum_accounts", &["address", "created_timestamp", "creator_address", "last_active_timestamp", "type"]),
+    ("ethereum_transactions", &[
+        "transaction_hash", "base_fee_per_gas", "block_number", "contract_address", "fees_burned", "fees_rewarded", "fees_saved",
+        "from_address", "gas_limit", "gas_price", "gas_used", "input", "internal_failed_transaction_count",
+        "internal_transaction_count", "log_count", "max_fee_per_gas", "max_priority_fee_per_gas", "nonce", "output", "position",
+        "timestamp", "to_address", "transaction_fee", "type", "value",
+    ]),
+];
+
+/// Amount columns of the baseline `ethereum_transactions`. SQLite stored them as NUMERIC, which
+/// rounds integers past 64 bits to floating point.
+const BASELINE_AMOUNTS: &[&str] = &[
+    "base_fee_per_gas", "fees_burned", "fees_rewarded", "fees_saved", "gas_price", "max_fee_per_gas", "max_priority_fee_per_gas",
+    "transaction_fee", "value",
+];
+
+/// What taking over the baseline tables carried into migration 1.
+pub struct Takeover {
+    pub tables: Vec<&'static str>,
+    pub copied: u64,
+    /// Rows left behind because SQLite had already rounded one of their amounts.
+    pub rounded: u64,
+}
+
+/// A database the schema is kept in.
+#[derive(Clone, Copy)]
+pub enum Database<'a> {
+    Sqlite(&'a SqlitePool),
+    Postgres(&'a PgPool),
+}
+
+impl Database<'_> {
+    /// The enabled databases that connected.
+    pub fn enabled<'a>(sqlite_pool: Option<&'a SqlitePool>, pg_pool: Option<&'a PgPool>) -> Vec<Database<'a>> {
+        sqlite_pool.map(Database::Sqlite).into_iter().chain(pg_pool.map(Database::Postgres)).collect()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Database::Sqlite(_) => "SQLite",
+            Database::Postgres(_) => "PostgreSQL",
+        }
+    }
+
+    /// When each applied migration ran, by version.
+    pub async fn applied(&self) -> Result<BTreeMap<i64, String>> {
+        let create = "CREATE TABLE IF NOT EXISTS schema_version (
+            version BIGINT PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )";
+        let select = "SELECT version, applied_at FROM schema_version ORDER BY version";
+        let rows = match self {
+            Database::Sqlite(pool) => {
+                sqlx::query(create).execute(*pool).await?;
+                sqlx::query(select).fetch_all(*pool).await?.iter()
+                    .map(|row| Ok((row.try_get("version")?, row.try_get("applied_at")?)))
+                    .collect::<Result<_, sqlx::Error>>()?
+            }
+            Database::Postgres(pool) => {
+                sqlx::query(create).execute(*pool).await?;
+                sqlx::query(select).fetch_all(*pool).await?.iter()
+                    .map(|row| Ok((row.try_get("version")?, row.try_get("applied_at")?)))
+                    .collect::<Result<_, sqlx::Error>>()?
+            }
+        };
+        Ok(rows)
+    }
+
+    /// Migrations that have not run on this database yet. Fails if the database was migrated by
+    /// a newer version of sleuth, whose schema this one does not know.
+    pub async fn pending(&self) -> Result<Vec<&'static Migration>> {
+        let applied = self.applied().await?;
+        let latest = MIGRATIONS.last().map_or(0, |migration| migration.version);
+        if let Some(&version) = applied.keys().next_back().filter(|&&version| version > latest) {
+            return Err(Error::Storage(format!(
+                "{} schema is at version {}, but this version of sleuth only knows up to version {}. Upgrade sleuth to use this database.",
+                self.name(), version, latest
+            )));
+        }
+        Ok(MIGRATIONS.iter().filter(|migration| !applied.contains_key(&migration.version)).collect())
+    }
+
+    /// The database's type for exact integer amounts. SQLite's NUMERIC would round integers
+    /// past 64 bits, so amounts are kept there as decimal text.
+    fn amount_type(&self) -> &'static str {
+        match self {
+            Database::Sqlite(_) => "TEXT",
+            Database::Postgres(_) => "NUMERIC",
+        }
+    }
+
+    /// The columns of `table` in order, or none if it does not exist.
+    async fn columns(&self, table: &str) -> Result<Vec<String>, sqlx::Error> {
+        match self {
+            Database::Sqlite(pool) => {
+                sqlx::query("SELECT name FROM pragma_table_info(?) ORDER BY cid").bind(table).fetch_all(*pool).await?
+                    .iter().map(|row| row.try_get("name")).collect()
+            }
+            Database::Postgres(pool) => {
+                sqlx::query("SELECT column_name::TEXT AS name FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = $1 ORDER BY ordinal_position")
+                    .bind(table).fetch_all(*pool).await?
+                    .iter().map(|row| row.try_get("name")).collect()
+            }
+        }
+    }
+
+    /// The baseline tables present in this database. Fails if one does not have the layout the
+    /// baseline created, since migration 1 could not tell how to carry its rows over.
+    async fn baseline_tables(&self) -> Result<Vec<&'static str>> {
+        let mut tables = Vec::new();
+        for (table, expected) in BASELINE_TABLES {
+            let columns = self.columns(table).await?;
+            if columns.is_empty() {
+                continue;
+            }
+            if !columns.iter().map(String::as_str).eq(expected.iter().copied()) {
+                return Err(Error::Storage(format!(
+                    "{} table {} predates versioned migrations and does not have the layout sleuth created. Rename or drop it, then run `sleuth db migrate`.",
+                    self.name(), table
+                )));
+            }
+            tables.push(*table);
+        }
+        Ok(tables)
+    }
+
+    /// Copies the rows of a renamed baseline table into its migrated table. In SQLite, rows with
+    /// an amount that was already rounded to floating point are left out, so they are fetched
+    /// again exactly rather than kept wrong.
+    fn copy_baseline(&self, table: &str) -> String {
+        let mut copy = format!("INSERT INTO {0} SELECT * FROM baseline_{0}", table);
+        if matches!(self, Database::Sqlite(_)) && table == "ethereum_transactions" {
+            let rounded = BASELINE_AMOUNTS.iter().map(|column| format!("typeof({}) = 'real'", column)).collect::<Vec<_>>();
+            copy = format!("{} WHERE NOT ({})", copy, rounded.join(" OR "));
+        }
+        copy
+    }
+
+    /// Applies every pending migration in order, stopping at the first that fails. Returns how
+    /// many were applied.
+    pub async fn migrate(&self) -> Result<usize> {
+        let pending = self.pending().await?;
+        let baseline = match pending.first() {
+            Some(migration) if migration.version == 1 => self.baseline_tables().await?,
+            _ => Vec::new(),
+        };
+
+        for migration in &pending {
+            let takeover = if migration.version == 1 { baseline.clone() } else { Vec::new() };
+            let takeover = self.apply(migration, takeover).await
+                .map_err(|e| Error::Storage(format!("{} migration {} ({}) failed: {}", self.name(), migration.version, migration.description, e)))?;
+            println!("Applied {} migration {}: {}", self.name(), migration.version, migration.description);
+
+            if !takeover.tables.is_empty() {
+                println!("Carried {} rows over from the existing {} tables", takeover.copied, takeover.tables.join(" and "));
+            }
+            if takeover.rounded > 0 {
+                println!(
+                    "Left out {} transactions whose amounts the old schema had rounded; querying their addresses again fetches them exactly",
+                    takeover.rounded
+                );
+            }
+        }
+        Ok(pending.len())
+    }
+
+    /// Runs `migration` in one transaction. `baseline` tables are renamed aside first and their
+    /// rows copied into the migrated tables after.
+    async fn apply(&self, migration: &Migration, baseline: Vec<&'static str>) -> Result<Takeover, sqlx::Error> {
+        let sql = migration.sql(self);
+        let applied_at = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let mut takeover = Takeover { tables: baseline, copied: 0, rounded: 0 };
+        match self {
+            Database::Sqlite(pool) => {
+                let mut tx = pool.begin().await?;
+                for table in &takeover.tables {
+                    let rename = format!("ALTER TABLE {0} RENAME TO baseline_{0}", table);
+                    sqlx::query(&rename).execute(&mut *tx).await?;
+                }
+                tx.execute(sql.as_str()).await?;
+                for table in &takeover.tables {
+                    let (count, copy, drop) = (format!("SELECT COUNT(*) AS count FROM baseline_{}", table), self.copy_baseline(table), format!("DROP TABLE baseline_{}", table));
+                    let rows: i64 = sqlx::query(&count).fetch_one(&mut *tx).await?.try_get("count")?;
+                    let copied = sqlx::query(&copy).execute(&mut *tx).await?.rows_affected();
+                    sqlx::query(&drop).execute(&mut *tx).await?;
+                    takeover.copied += copied;
+                    takeover.rounded += rows as u64 - copied;
+                }
+                sqlx::query("INSERT INTO schema_version (version, description, applied_at) VALUES (?, ?, ?)")
+                    .bind(migration.version)
+                    .bind(migration.description)
+                    .bind(&applied_at)
+                    .execute(&mut *tx).await?;
+                tx.commit().await?;
+            }
+            Database::Postgres(pool) => {
+                let mut tx = pool.begin().await?;
+                for table in &takeover.tables {
+                    let rename = format!("ALTER TABLE {0} RENAME TO baseline_{0}", table);
+                    sqlx::query(&rename).execute(&mut *tx).await?;
+                }
+                tx.execute(sql.as_str()).await?;
+                for table in &takeover.tables {
+                    let (copy, drop) = (self.copy_baseline(table), format!("DROP TABLE baseline_{}", table));
+                    takeover.copied += sqlx::query(&copy).execute(&mut *tx).await?.rows_affected();
+                    sqlx::query(&drop).execute(&mut *tx).await?;
+                }
+                sqlx::query("INSERT INTO schema_version (version, description, applied_at) VALUES ($1, $2, $3)")
+                    .bind(migration.version)
+                    .bind(migration.description)
+                    .bind(&applied_at)
+                    .execute(&mut *tx).await?;
+                tx.commit().await?;
+            }
+        }
+        Ok(takeover)
+    }
+}
+
+/// Brings every enabled database up to the latest schema. Run at startup, so tables are created
+/// on first use and upgrades take effect without a separate step.
+pub async fn migrate_on_startup(sqlite_pool: Option<&SqlitePool>, pg_pool: Option<&PgPool>) -> Result<()> {
+    for database in Database::enabled(sqlite_pool, pg_pool) {
+        database.migrate().await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,126 @@
+-- The tables of one EVM chain, run once for each chain the migration lists.
+
+CREATE TABLE {chain}_accounts (
+    address TEXT PRIMARY KEY,
+    created_timestamp TEXT,
+    creator_address TEXT,
+    last_active_timestamp TEXT,
+    type TEXT
+);
+
+CREATE TABLE {chain}_transactions (
+    transaction_hash TEXT PRIMARY KEY,
+    base_fee_per_gas {amount},
+    block_number INTEGER,
+    contract_address TEXT,
+    fees_burned {amount},
+    fees_rewarded {amount},
+    fees_saved {amount},
+    from_address TEXT,
+    gas_limit NUMERIC,
+    gas_price {amount},
+    gas_used NUMERIC,
+    input TEXT,
+    internal_failed_transaction_count INTEGER,
+    internal_transaction_count INTEGER,
+    log_count INTEGER,
+    max_fee_per_gas {amount},
+    max_priority_fee_per_gas {amount},
+    nonce INTEGER,
+    output TEXT,
+    position INTEGER,
+    timestamp TIMESTAMP,
+    to_address TEXT,
+    transaction_fee {amount},
+    type INTEGER,
+    value {amount}
+);
+
+CREATE TABLE {chain}_logs (
+    transaction_hash TEXT,
+    log_index INTEGER,
+    block_number INTEGER,
+    transaction_position INTEGER,
+    timestamp TIMESTAMP,
+    address TEXT,
+    data TEXT,
+    topic_0 TEXT,
+    topic_1 TEXT,
+    topic_2 TEXT,
+    topic_3 TEXT,
+    PRIMARY KEY (transaction_hash, log_index)
+);
+
+CREATE TABLE {chain}_blocks (
+    block_number INTEGER PRIMARY KEY,
+    block_hash TEXT,
+    parent_hash TEXT,
+    timestamp TIMESTAMP,
+    miner TEXT,
+    gas_limit NUMERIC,
+    gas_used NUMERIC,
+    base_fee_per_gas {amount},
+    transaction_count INTEGER
+);
+
+CREATE TABLE {chain}_token_transfers (
+    transaction_hash TEXT,
+    log_index INTEGER,
+    batch_index INTEGER,
+    block_number INTEGER,
+    timestamp TIMESTAMP,
+    token_address TEXT,
+    token_standard TEXT,
+    from_address TEXT,
+    to_address TEXT,
+    amount {amount},
+    token_id {amount},
+    PRIMARY KEY (transaction_hash, log_index, batch_index)
+);
+
+CREATE TABLE {chain}_traces (
+    transaction_hash TEXT,
+    trace_address TEXT,
+    block_number INTEGER,
+    timestamp TIMESTAMP,
+    transaction_position INTEGER,
+    trace_type TEXT,
+    call_type TEXT,
+    from_address TEXT,
+    to_address TEXT,
+    value {amount},
+    input TEXT,
+    output TEXT,
+    gas NUMERIC,
+    gas_used NUMERIC,
+    subtraces INTEGER,
+    error TEXT,
+    PRIMARY KEY (transaction_hash, trace_address)
+);
+
+CREATE TABLE {chain}_decoded_calls (
+    transaction_hash TEXT,
+    trace_address TEXT,
+    block_number INTEGER,
+    timestamp TIMESTAMP,
+    from_address TEXT,
+    contract_address TEXT,
+    selector TEXT,
+    function_name TEXT,
+    signature TEXT,
+    arguments TEXT,
+    PRIMARY KEY (transaction_hash, trace_address)
+);
+
+CREATE TABLE {chain}_decoded_events (
+    transaction_hash TEXT,
+    log_index INTEGER,
+    block_number INTEGER,
+    timestamp TIMESTAMP,
+    contract_address TEXT,
+    topic_0 TEXT,
+    event_name TEXT,
+    signature TEXT,
+    arguments TEXT,
+    PRIMARY KEY (transaction_hash, log_index)
+);
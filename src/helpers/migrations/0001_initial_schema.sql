@@ -0,0 +1,140 @@
+-- Tables shared by every chain. Exact integer amounts are typed by the migration runner as
+-- NUMERIC in PostgreSQL and TEXT in SQLite, which would round NUMERIC integers past 64 bits.
+
+CREATE TABLE bitcoin_addresses (
+    address TEXT PRIMARY KEY,
+    tx_count INTEGER,
+    funded_txo_count INTEGER,
+    funded_txo_sum NUMERIC,
+    spent_txo_count INTEGER,
+    spent_txo_sum NUMERIC,
+    balance NUMERIC,
+    mempool_tx_count INTEGER
+);
+
+CREATE TABLE bitcoin_transactions (
+    txid TEXT PRIMARY KEY,
+    block_height INTEGER,
+    block_hash TEXT,
+    block_time TIMESTAMP,
+    version INTEGER,
+    locktime NUMERIC,
+    size INTEGER,
+    weight INTEGER,
+    fee NUMERIC,
+    input_count INTEGER,
+    output_count INTEGER,
+    input_value NUMERIC,
+    output_value NUMERIC,
+    is_coinbase BOOLEAN
+);
+
+CREATE TABLE bitcoin_inputs (
+    txid TEXT,
+    input_index INTEGER,
+    prev_txid TEXT,
+    prev_vout INTEGER,
+    address TEXT,
+    value NUMERIC,
+    script_sig TEXT,
+    witness TEXT,
+    sequence NUMERIC,
+    PRIMARY KEY (txid, input_index)
+);
+
+CREATE TABLE bitcoin_outputs (
+    txid TEXT,
+    output_index INTEGER,
+    address TEXT,
+    value NUMERIC,
+    script_pubkey TEXT,
+    script_type TEXT,
+    PRIMARY KEY (txid, output_index)
+);
+
+CREATE TABLE bitcoin_ingest_progress (
+    source TEXT PRIMARY KEY,
+    height BIGINT,
+    block_hash TEXT,
+    updated_at TEXT
+);
+
+CREATE TABLE sync_state (
+    chain TEXT,
+    address TEXT,
+    kind TEXT,
+    provider TEXT,
+    cursor_block BIGINT NOT NULL,
+    cursor_position BIGINT NOT NULL,
+    cursor_sub_position BIGINT NOT NULL,
+    last_block BIGINT,
+    last_timestamp TEXT,
+    rows_synced BIGINT NOT NULL,
+    complete BOOLEAN NOT NULL,
+    updated_at TEXT,
+    PRIMARY KEY (chain, address, kind)
+);
+
+CREATE TABLE solana_accounts (
+    address TEXT PRIMARY KEY,
+    lamports NUMERIC,
+    owner TEXT,
+    executable BOOLEAN,
+    rent_epoch {amount},
+    data_size INTEGER
+);
+
+CREATE TABLE solana_transactions (
+    signature TEXT PRIMARY KEY,
+    slot BIGINT,
+    block_time TIMESTAMP,
+    fee NUMERIC,
+    success BOOLEAN,
+    error TEXT,
+    fee_payer TEXT,
+    account_count INTEGER,
+    instruction_count INTEGER,
+    compute_units_consumed NUMERIC,
+    version TEXT
+);
+
+CREATE TABLE solana_instructions (
+    signature TEXT,
+    instruction_index INTEGER,
+    inner_index INTEGER,
+    program_id TEXT,
+    accounts TEXT,
+    data TEXT,
+    stack_height INTEGER,
+    PRIMARY KEY (signature, instruction_index, inner_index)
+);
+
+CREATE TABLE ens_names (
+    address TEXT PRIMARY KEY,
+    name TEXT,
+    resolved_at TEXT
+);
+
+CREATE TABLE address_inputs (
+    address TEXT,
+    source_file TEXT,
+    line INTEGER,
+    input TEXT,
+    label TEXT,
+    notes TEXT,
+    imported_at TEXT,
+    PRIMARY KEY (address, source_file)
+);
+
+CREATE TABLE solana_token_balance_changes (
+    signature TEXT,
+    account_index INTEGER,
+    token_account TEXT,
+    owner TEXT,
+    mint TEXT,
+    decimals INTEGER,
+    pre_amount {amount},
+    post_amount {amount},
+    change {amount},
+    PRIMARY KEY (signature, account_index)
+);
@@ -0,0 +1,4 @@
+-- The storage backends that hold an address's synced history, comma separated, so a backend
+-- enabled later is backfilled rather than only given newer rows.
+
+ALTER TABLE sync_state ADD COLUMN sinks TEXT;
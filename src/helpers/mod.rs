@@ -1,3 +1,6 @@
 pub mod storage;
-pub mod setup_schema;
-pub mod postgres;
\ No newline at end of file
+pub mod parquet;
+pub mod postgres;
+pub mod migrations;
+pub mod sink;
+pub mod sync_state;
@@ -0,0 +1,231 @@
+use crate::error::{Error, Result};
+use crate::helpers::sink::Dataset;
+use crate::record::{Cell, Column, Kind, Timestamp};
+use parquet::basic::{Compression, LogicalType, Repetition, TimeUnit, Type as PhysicalType};
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DataType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::{SerializedColumnWriter, SerializedFileWriter};
+use parquet::format::MicroSeconds;
+use parquet::schema::types::{Type, TypePtr};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Root of the data lake. Each dataset is a directory of Hive-style partitions under it, such
+/// as `evm_transactions/chain=ethereum/date=2024-01-31/part-*.parquet`.
+pub const LAKE_DIR: &str = "data/parquet";
+const MANIFEST: &str = "data/parquet/manifest.json";
+
+/// Columns that date a row, in order of preference. Rows of datasets without one are dated by
+/// when they were written.
+const TIME_COLUMNS: &[&str] = &["timestamp", "block_time"];
+/// The partition value Hive, Spark and DuckDB read back as NULL.
+const NULL_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+/// Numbers the files written in this run, so two saves in the same microsecond get different names.
+static FILE_NUMBER: AtomicU64 = AtomicU64::new(0);
+
+/// Every file in the lake, so readers can find the data and its schema without listing
+/// directories.
+#[derive(Serialize, Deserialize, Default)]
+struct Manifest {
+    datasets: BTreeMap<String, ManifestDataset>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestDataset {
+    /// The columns of the most recent files, with their Parquet types.
+    columns: Vec<ManifestColumn>,
+    /// Hive partition keys, outermost first.
+    partitions: Vec<String>,
+    /// Columns (partition keys included) that identify a row. Files are only ever appended, so
+    /// a row fetched twice is in two files; keep the copy in the most recently written one.
+    #[serde(default)]
+    key: Vec<String>,
+    /// Rows across every file, counting each copy of a row fetched more than once.
+    rows: u64,
+    files: Vec<ManifestFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestColumn {
+    name: String,
+    #[serde(rename = "type")]
+    parquet_type: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestFile {
+    /// Relative to `data/parquet`.
+    path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    chain: Option<String>,
+    date: String,
+    rows: usize,
+    written_at: String,
+}
+
+/// Appends `data` to the dataset's partitions, one new file per date, and records the files in
+/// the manifest. Files are written beside their final name and renamed into place, so readers
+/// never see a partial file. Rows already in the lake are not looked for, so saving one twice
+/// leaves two copies; the manifest names the key to deduplicate on when reading.
+pub fn save_to_parquet(data: &[Value], dataset: &Dataset) -> Result<()> {
+    if data.is_empty() {
+        return Ok(());
+    }
+
+    let mut rows = Vec::with_capacity(data.len());
+    for record in data {
+        let cells = dataset.columns.iter()
+            .map(|column| column.cell(&dataset.table, record.get(column.name)))
+            .collect::<Result<Vec<_>>>()?;
+        rows.push(cells);
+    }
+
+    let now = chrono::Utc::now();
+    let time_column = dataset.columns.iter()
+        .position(|column| column.kind == Kind::Timestamp && TIME_COLUMNS.contains(&column.name));
+    let mut partitions: BTreeMap<String, Vec<&[Cell]>> = BTreeMap::new();
+    for row in &rows {
+        let date = match time_column.map(|i| &row[i]) {
+            None => now.format("%Y-%m-%d").to_string(),
+            Some(Cell::Text(time)) => Timestamp::parse(time).map_or_else(|| NULL_PARTITION.to_string(), |time| time.date()),
+            Some(_) => NULL_PARTITION.to_string(),
+        };
+        partitions.entry(date).or_default().push(row);
+    }
+
+    let mut files = Vec::new();
+    for (date, rows) in partitions {
+        let mut dir = dataset.lake_name.clone();
+        if let Some(chain) = &dataset.chain {
+            dir = format!("{}/chain={}", dir, chain);
+        }
+        let path = format!(
+            "{}/date={}/part-{}-{:04}.parquet",
+            dir, date, now.timestamp_micros(), FILE_NUMBER.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let full_path = Path::new(LAKE_DIR).join(&path);
+        if let Some(parent) = full_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let partial = full_path.with_extension("parquet.partial");
+        write_file(&partial, dataset.columns, &rows)?;
+        fs::rename(&partial, &full_path)?;
+
+        files.push(ManifestFile { path, chain: dataset.chain.clone(), date, rows: rows.len(), written_at: now.to_rfc3339() });
+    }
+
+    add_to_manifest(dataset, files)
+}
+
+fn write_file(path: &Path, columns: &[Column], rows: &[&[Cell]]) -> Result<()> {
+    let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+    let mut writer = SerializedFileWriter::new(File::create(path)?, schema(columns)?, Arc::new(properties))?;
+    let mut row_group = writer.next_row_group()?;
+
+    let mut index = 0;
+    while let Some(mut column_writer) = row_group.next_column()? {
+        let cells = rows.iter().map(|row| &row[index]);
+        match columns[index].kind {
+            Kind::Integer => write_column::<Int64Type>(&mut column_writer, cells.map(|cell| match cell {
+                Cell::Integer(value) => Some(*value),
+                _ => None,
+            }))?,
+            Kind::Boolean => write_column::<BoolType>(&mut column_writer, cells.map(|cell| match cell {
+                Cell::Boolean(value) => Some(*value),
+                _ => None,
+            }))?,
+            Kind::Timestamp => write_column::<Int64Type>(&mut column_writer, cells.map(|cell| match cell {
+                Cell::Text(time) => Timestamp::parse(time).map(|time| time.unix_micros()),
+                _ => None,
+            }))?,
+            Kind::Amount | Kind::Text => write_column::<ByteArrayType>(&mut column_writer, cells.map(|cell| match cell {
+                Cell::Text(text) => Some(ByteArray::from(text.as_str())),
+                _ => None,
+            }))?,
+        }
+        column_writer.close()?;
+        index += 1;
+    }
+
+    row_group.close()?;
+    writer.close()?;
+    Ok(())
+}
+
+/// Writes one column of optional values: definition level 1 marks a value, 0 a null.
+fn write_column<T: DataType>(writer: &mut SerializedColumnWriter, values: impl Iterator<Item = Option<T::T>>) -> Result<()> {
+    let (mut present, mut levels) = (Vec::new(), Vec::new());
+    for value in values {
+        levels.push(value.is_some() as i16);
+        present.extend(value);
+    }
+    writer.typed::<T>().write_batch(&present, Some(&levels), None)?;
+    Ok(())
+}
+
+/// Integers are `INT64` and timestamps `INT64` microseconds in UTC. Amounts are strings of
+/// decimal digits, since they can be wider than any Parquet integer or decimal readers support.
+fn schema(columns: &[Column]) -> Result<TypePtr> {
+    let mut fields = Vec::with_capacity(columns.len());
+    for column in columns {
+        let (physical_type, logical_type) = match column.kind {
+            Kind::Integer => (PhysicalType::INT64, None),
+            Kind::Boolean => (PhysicalType::BOOLEAN, None),
+            Kind::Timestamp => (PhysicalType::INT64, Some(LogicalType::Timestamp { is_adjusted_to_u_t_c: true, unit: TimeUnit::MICROS(MicroSeconds::new()) })),
+            Kind::Amount | Kind::Text => (PhysicalType::BYTE_ARRAY, Some(LogicalType::String)),
+        };
+        let field = Type::primitive_type_builder(column.name, physical_type)
+            .with_repetition(Repetition::OPTIONAL)
+            .with_logical_type(logical_type)
+            .build()?;
+        fields.push(Arc::new(field));
+    }
+    Ok(Arc::new(Type::group_type_builder("schema").with_fields(fields).build()?))
+}
+
+fn parquet_type(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Integer => "INT64",
+        Kind::Amount => "STRING (decimal integer)",
+        Kind::Boolean => "BOOLEAN",
+        Kind::Timestamp => "TIMESTAMP (microseconds, UTC)",
+        Kind::Text => "STRING",
+    }
+}
+
+/// Adds `files` to `manifest.json`, rewriting it beside itself and renaming it into place.
+fn add_to_manifest(dataset: &Dataset, files: Vec<ManifestFile>) -> Result<()> {
+    let mut manifest: Manifest = match fs::read_to_string(MANIFEST) {
+        Ok(json) => serde_json::from_str(&json).map_err(|e| Error::Storage(format!("{} is not valid: {}", MANIFEST, e)))?,
+        Err(_) => Manifest::default(),
+    };
+
+    let mut partitions = Vec::new();
+    if dataset.chain.is_some() {
+        partitions.push("chain".to_string());
+    }
+    partitions.push("date".to_string());
+
+    let entry = manifest.datasets.entry(dataset.lake_name.clone())
+        .or_insert_with(|| ManifestDataset { columns: Vec::new(), partitions: Vec::new(), key: Vec::new(), rows: 0, files: Vec::new() });
+    entry.columns = dataset.columns.iter()
+        .map(|column| ManifestColumn { name: column.name.to_string(), parquet_type: parquet_type(column.kind).to_string() })
+        .collect();
+    entry.key = dataset.chain.iter().map(|_| "chain").chain(dataset.key()).map(str::to_string).collect();
+    entry.partitions = partitions;
+    entry.rows += files.iter().map(|file| file.rows as u64).sum::<u64>();
+    entry.files.extend(files);
+
+    let json = serde_json::to_string_pretty(&manifest).map_err(|e| Error::Storage(format!("Cannot encode {}: {}", MANIFEST, e)))?;
+    let partial = format!("{}.partial", MANIFEST);
+    fs::write(&partial, json)?;
+    fs::rename(&partial, MANIFEST)?;
+    Ok(())
+}
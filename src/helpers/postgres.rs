@@ -1,84 +1,86 @@
 use sqlx::postgres::PgPool;
 use serde_json::Value;
+use crate::error::{Error, Result};
+use crate::helpers::storage::batches;
+use crate::record::{cell, kind_of, Cell, Column, Kind};
+use csv::Writer;
+use std::collections::HashSet;
 
-pub async fn setup_postgres_schema(pool: &PgPool) -> Result<(), sqlx::Error> {
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS ethereum_accounts (
-            address TEXT PRIMARY KEY,
-            created_timestamp TEXT,
-            creator_address TEXT,
-            last_active_timestamp TEXT,
-            type TEXT
-        )"
-    ).execute(pool).await?;
+/// Marks a NULL in `COPY` input, so an empty string is still stored as an empty string.
+const COPY_NULL: &str = "\\N";
 
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS ethereum_transactions (
-            transaction_hash TEXT PRIMARY KEY,
-            base_fee_per_gas NUMERIC,
-            block_number INTEGER,
-            contract_address TEXT,
-            fees_burned NUMERIC,
-            fees_rewarded NUMERIC,
-            fees_saved NUMERIC,
-            from_address TEXT,
-            gas_limit NUMERIC,
-            gas_price NUMERIC,
-            gas_used NUMERIC,
-            input TEXT,
-            internal_failed_transaction_count INTEGER,
-            internal_transaction_count INTEGER,
-            log_count INTEGER,
-            max_fee_per_gas NUMERIC,
-            max_priority_fee_per_gas NUMERIC,
-            nonce INTEGER,
-            output TEXT,
-            position INTEGER,
-            timestamp TIMESTAMP,
-            to_address TEXT,
-            transaction_fee NUMERIC,
-            type INTEGER,
-            value NUMERIC
-        )"
-    ).execute(pool).await?;
+/// Upserts `data` into `table_name` in one transaction. Each batch of up to `batch_size` rows is
+/// streamed with `COPY FROM STDIN` into a temporary staging table, then merged into the table
+/// with a single `INSERT ... ON CONFLICT DO UPDATE`.
+pub async fn save_to_postgres(pool: &PgPool, data: &[Value], table_name: &str, columns: &[Column], batch_size: usize) -> Result<()> {
+    let key = primary_key(table_name);
+    let key_columns: Vec<&str> = key.split(", ").collect();
+    let mut tx = pool.begin().await?;
+    sqlx::query(&format!("CREATE TEMP TABLE sleuth_staging (LIKE {} INCLUDING DEFAULTS) ON COMMIT DROP", table_name))
+        .execute(&mut *tx).await?;
 
-    Ok(())
-}
+    for batch in batches(data, table_name, batch_size)? {
+        let column_list = batch.columns.join(", ");
+        let kinds: Vec<Kind> = batch.columns.iter().map(|name| kind_of(columns, name)).collect();
 
-pub async fn save_to_postgres(pool: &PgPool, data: &[Value], table_name: &str) -> Result<(), sqlx::Error> {
-    println!("Attempting to save {} records to PostgreSQL table: {}", data.len(), table_name);
-    for (index, record) in data.iter().enumerate() {
-        let columns = record.as_object().unwrap().keys().map(|s| s.as_str()).collect::<Vec<_>>().join(", ");
-        let placeholders = (1..=record.as_object().unwrap().len()).map(|i| format!("${}", i)).collect::<Vec<_>>().join(", ");
-        
-        let primary_key = if table_name == "ethereum_accounts" { "address" } else { "transaction_hash" };
-        
-        let sql = format!(
-            "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT ({}) DO UPDATE SET {}",
-            table_name,
-            columns,
-            placeholders,
-            primary_key,
-            columns.split(", ")
-                .enumerate()
-                .map(|(i, col)| format!("{} = ${}", col, i + 1))
-                .collect::<Vec<_>>()
-                .join(", ")
-        );
-        
-        println!("Executing SQL for record {}: {}", index, sql);
-        
-        let mut query = sqlx::query(&sql);
-        for value in record.as_object().unwrap().values() {
-            query = query.bind(value.as_str().unwrap_or(""));
-        }
-        
-        match query.execute(pool).await {
-            Ok(_) => println!("Successfully inserted/updated record {}", index),
-            Err(e) => println!("Error inserting/updating record {}: {}", index, e),
+        // A statement cannot update the same row twice, so only the last row for each key is kept
+        let mut seen = HashSet::new();
+        let mut rows: Vec<_> = batch.rows.iter().rev()
+            .filter(|row| seen.insert(key_columns.iter().map(|column| row.get(*column).map(Value::to_string)).collect::<Vec<_>>()))
+            .collect();
+        rows.reverse();
+
+        let mut wtr = Writer::from_writer(Vec::new());
+        for row in rows {
+            let mut record = Vec::with_capacity(kinds.len());
+            for (name, kind) in batch.columns.iter().zip(&kinds) {
+                record.push(match cell(table_name, name, *kind, row.get(*name))? {
+                    Cell::Null => COPY_NULL.to_string(),
+                    value => value.to_string(),
+                });
+            }
+            wtr.write_record(&record)?;
         }
+        let csv = wtr.into_inner().map_err(|e| Error::Storage(format!("Cannot encode rows for {}: {}", table_name, e)))?;
+
+        let mut copy = tx.copy_in_raw(&format!("COPY sleuth_staging ({}) FROM STDIN WITH (FORMAT csv, NULL '{}')", column_list, COPY_NULL)).await?;
+        copy.send(csv).await?;
+        copy.finish().await?;
+
+        let updates = batch.columns.iter()
+            .map(|column| format!("{} = EXCLUDED.{}", column, column))
+            .collect::<Vec<_>>()
+            .join(", ");
+        sqlx::query(&format!(
+            "INSERT INTO {} ({}) SELECT {} FROM sleuth_staging ON CONFLICT ({}) DO UPDATE SET {}",
+            table_name, column_list, column_list, key, updates
+        )).execute(&mut *tx).await?;
+        sqlx::query("TRUNCATE sleuth_staging").execute(&mut *tx).await?;
     }
-    
-    println!("Finished saving data to PostgreSQL");
+    tx.commit().await?;
+
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Conflict target used when upserting into `table_name`.
+pub fn primary_key(table_name: &str) -> &'static str {
+    match table_name {
+        "bitcoin_addresses" => "address",
+        "bitcoin_transactions" => "txid",
+        "bitcoin_inputs" => "txid, input_index",
+        "bitcoin_outputs" => "txid, output_index",
+        "solana_transactions" => "signature",
+        "solana_instructions" => "signature, instruction_index, inner_index",
+        "solana_token_balance_changes" => "signature, account_index",
+        "ens_names" => "address",
+        "address_inputs" => "address, source_file",
+        _ if table_name.ends_with("_accounts") => "address",
+        _ if table_name.ends_with("_logs") => "transaction_hash, log_index",
+        _ if table_name.ends_with("_blocks") => "block_number",
+        _ if table_name.ends_with("_token_transfers") => "transaction_hash, log_index, batch_index",
+        _ if table_name.ends_with("_traces") => "transaction_hash, trace_address",
+        _ if table_name.ends_with("_decoded_calls") => "transaction_hash, trace_address",
+        _ if table_name.ends_with("_decoded_events") => "transaction_hash, log_index",
+        _ => "transaction_hash",
+    }
+}
@@ -0,0 +1,273 @@
+use crate::config::chains::Chain;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::helpers::migrations::Database;
+use crate::helpers::{parquet, postgres, storage};
+use crate::record::Column;
+use async_trait::async_trait;
+use colored::*;
+use serde_json::Value;
+use sqlx::postgres::PgPool;
+use sqlx::SqlitePool;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// One kind of result row: the table it goes into, the CSV file it is exported to and the
+/// typed columns it has.
+pub struct Dataset {
+    pub table: String,
+    pub csv_path: String,
+    pub columns: &'static [Column],
+    /// Directory under `data/parquet` its Parquet files go in, shared by every chain with the
+    /// same columns.
+    pub lake_name: String,
+    /// The chain its rows come from, which partitions its Parquet files.
+    pub chain: Option<String>,
+}
+
+impl Dataset {
+    /// A dataset not tied to a chain, exported to `data/csv/{table}.csv`.
+    pub fn new(table: &str, columns: &'static [Column]) -> Self {
+        Dataset {
+            table: table.to_string(),
+            csv_path: format!("data/csv/{}.csv", table.replace('_', "-")),
+            columns,
+            lake_name: table.to_string(),
+            chain: None,
+        }
+    }
+
+    /// Marks the rows as coming from `chain`, such as `bitcoin`.
+    pub fn on_chain(mut self, chain: &str) -> Self {
+        self.chain = Some(chain.to_string());
+        self
+    }
+
+    /// `chain`'s table of `kind` rows, such as `ethereum_token_transfers`. Every EVM chain's rows
+    /// share one Parquet dataset, such as `evm_token_transfers`.
+    pub fn evm(chain: &Chain, kind: &str, columns: &'static [Column]) -> Self {
+        Dataset {
+            table: chain.table(kind),
+            csv_path: chain.csv_path(&kind.replace('_', "-")),
+            columns,
+            lake_name: format!("evm_{}", kind),
+            chain: Some(chain.name.to_string()),
+        }
+    }
+
+    /// The columns that identify a row.
+    pub fn key(&self) -> Vec<&'static str> {
+        postgres::primary_key(&self.table).split(", ").collect()
+    }
+}
+
+/// A storage backend results are written to.
+#[async_trait]
+pub trait Sink: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Creates whatever the backend needs before rows can be written to it.
+    async fn setup(&self) -> Result<()>;
+
+    /// Adds a batch of rows, such as a page of history. Databases replace rows with the same key.
+    async fn write(&self, dataset: &Dataset, rows: &[Value]) -> Result<()>;
+
+    /// Adds a batch of rows that describe current state, such as account balances, replacing
+    /// any stored row with the same key.
+    async fn upsert(&self, dataset: &Dataset, rows: &[Value]) -> Result<()> {
+        self.write(dataset, rows).await
+    }
+}
+
+pub struct CsvSink;
+
+#[async_trait]
+impl Sink for CsvSink {
+    fn name(&self) -> &'static str {
+        "CSV"
+    }
+
+    async fn setup(&self) -> Result<()> {
+        fs::create_dir_all("data/csv")?;
+        Ok(())
+    }
+
+    async fn write(&self, dataset: &Dataset, rows: &[Value]) -> Result<()> {
+        storage::save_to_csv(rows, &dataset.csv_path, dataset.columns).await
+    }
+
+    /// Rewrites the file with `rows` in place of the rows they update, so it keeps one row per key
+    /// across runs.
+    async fn upsert(&self, dataset: &Dataset, rows: &[Value]) -> Result<()> {
+        storage::upsert_csv(rows, &dataset.csv_path, dataset.columns, &dataset.key()).await
+    }
+}
+
+/// Appends Parquet files to the data lake in `data/parquet`. Parquet files are never rewritten,
+/// so rows that describe current state are added as a new snapshot on every run.
+#[derive(Default)]
+pub struct ParquetSink {
+    /// Held while files are added, so concurrent saves do not lose each other's manifest entries.
+    manifest: Mutex<()>,
+}
+
+#[async_trait]
+impl Sink for ParquetSink {
+    fn name(&self) -> &'static str {
+        "Parquet"
+    }
+
+    async fn setup(&self) -> Result<()> {
+        fs::create_dir_all(parquet::LAKE_DIR)?;
+        Ok(())
+    }
+
+    async fn write(&self, dataset: &Dataset, rows: &[Value]) -> Result<()> {
+        let _manifest = self.manifest.lock().unwrap();
+        parquet::save_to_parquet(rows, dataset)
+    }
+}
+
+pub struct SqliteSink {
+    pool: SqlitePool,
+    batch_size: usize,
+}
+
+#[async_trait]
+impl Sink for SqliteSink {
+    fn name(&self) -> &'static str {
+        "SQLite"
+    }
+
+    async fn setup(&self) -> Result<()> {
+        Database::Sqlite(&self.pool).migrate().await?;
+        Ok(())
+    }
+
+    async fn write(&self, dataset: &Dataset, rows: &[Value]) -> Result<()> {
+        storage::save_to_sqlite(&self.pool, rows, &dataset.table, dataset.columns, self.batch_size).await
+    }
+}
+
+pub struct PostgresSink {
+    pool: PgPool,
+    batch_size: usize,
+}
+
+#[async_trait]
+impl Sink for PostgresSink {
+    fn name(&self) -> &'static str {
+        "PostgreSQL"
+    }
+
+    async fn setup(&self) -> Result<()> {
+        Database::Postgres(&self.pool).migrate().await?;
+        Ok(())
+    }
+
+    async fn write(&self, dataset: &Dataset, rows: &[Value]) -> Result<()> {
+        postgres::save_to_postgres(&self.pool, rows, &dataset.table, dataset.columns, self.batch_size).await
+    }
+}
+
+/// Sends every batch of results to each enabled sink. A sink that fails is reported and the
+/// others are still written to, so one unreachable database does not lose the rows the rest
+/// could store.
+pub struct Sinks {
+    sinks: Vec<Arc<dyn Sink>>,
+}
+
+impl Sinks {
+    /// The sinks enabled in `config`. A database whose pool could not be opened is skipped.
+    pub fn enabled(config: &Config, sqlite_pool: Option<&SqlitePool>, pg_pool: Option<&PgPool>) -> Self {
+        let mut sinks: Vec<Arc<dyn Sink>> = Vec::new();
+        if config.save_as_csv {
+            sinks.push(Arc::new(CsvSink));
+        }
+        if config.save_as_parquet {
+            sinks.push(Arc::new(ParquetSink::default()));
+        }
+        if config.save_as_sqlite {
+            match sqlite_pool {
+                Some(pool) => sinks.push(Arc::new(SqliteSink { pool: pool.clone(), batch_size: config.batch_size })),
+                None => println!("SQLite pool is not available. Skipping SQLite save."),
+            }
+        }
+        if config.save_as_postgres {
+            match pg_pool {
+                Some(pool) => sinks.push(Arc::new(PostgresSink { pool: pool.clone(), batch_size: config.batch_size })),
+                None => println!("PostgreSQL pool is not available. Skipping PostgreSQL save."),
+            }
+        }
+        Sinks { sinks }
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.sinks.iter().map(|sink| sink.name()).collect()
+    }
+
+    /// The sinks among these named in `names`, such as backends enabled since an earlier sync.
+    pub fn only(&self, names: &[&str]) -> Sinks {
+        Sinks { sinks: self.sinks.iter().filter(|sink| names.contains(&sink.name())).cloned().collect() }
+    }
+
+    pub async fn setup(&self) -> Result<()> {
+        let mut failures = Vec::new();
+        for sink in &self.sinks {
+            if let Err(e) = sink.setup().await {
+                failures.push(failure(sink.as_ref(), "schema setup", e));
+            }
+        }
+        self.finish(failures)
+    }
+
+    pub async fn write(&self, dataset: &Dataset, rows: &[Value]) -> Result<()> {
+        self.save(dataset, rows, false).await
+    }
+
+    pub async fn upsert(&self, dataset: &Dataset, rows: &[Value]) -> Result<()> {
+        self.save(dataset, rows, true).await
+    }
+
+    /// Saves `rows` to every sink, then prints one line saying where they went and how long
+    /// each sink took.
+    async fn save(&self, dataset: &Dataset, rows: &[Value], upsert: bool) -> Result<()> {
+        if rows.is_empty() || self.sinks.is_empty() {
+            return Ok(());
+        }
+
+        let mut failures = Vec::new();
+        let mut timings = Vec::new();
+        for sink in &self.sinks {
+            let started = Instant::now();
+            let saved = match upsert {
+                true => sink.upsert(dataset, rows).await,
+                false => sink.write(dataset, rows).await,
+            };
+            match saved {
+                Ok(()) => timings.push(format!("{} {:.2}s", sink.name(), started.elapsed().as_secs_f64())),
+                Err(e) => failures.push(failure(sink.as_ref(), &dataset.table, e)),
+            }
+        }
+
+        if !timings.is_empty() {
+            println!("Saved {} rows to {} ({})", rows.len(), dataset.table, timings.join(", "));
+        }
+        self.finish(failures)
+    }
+
+    /// Fails with every sink's error once all of them have been tried.
+    fn finish(&self, failures: Vec<String>) -> Result<()> {
+        match failures.len() {
+            0 => Ok(()),
+            failed => Err(Error::Storage(format!("{} of {} storage backends failed: {}", failed, self.sinks.len(), failures.join("; ")))),
+        }
+    }
+}
+
+fn failure(sink: &dyn Sink, target: &str, e: Error) -> String {
+    let message = format!("{} failed on {}: {}", sink.name(), target, e);
+    eprintln!("{}", message.red());
+    message
+}
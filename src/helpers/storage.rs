@@ -1,57 +1,158 @@
-use serde_json::Value;
+use crate::error::{Error, Result};
+use crate::record::{cell, kind_of, Cell, Column, Kind};
+use serde_json::{Map, Value};
 use std::fs::OpenOptions;
-use csv::Writer;
+use csv::{Reader, Writer};
 use sqlx::{sqlite::SqlitePool};
 use std::fs;
 use std::path::Path;
+use std::collections::BTreeSet;
+use std::sync::Mutex;
 
-pub async fn save_to_csv(data: &[Value], filepath: &str, fieldnames: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Attempting to save data to CSV at: {}", filepath);
-    
+/// CSV files written so far in this run.
+static STARTED_FILES: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Makes this run's writes to `filepath` add to the file instead of starting it over, for
+/// results that continue an earlier run's.
+pub fn continue_csv(filepath: &str) {
+    STARTED_FILES.lock().unwrap().insert(filepath.to_string());
+}
+
+pub async fn save_to_csv(data: &[Value], filepath: &str, columns: &[Column]) -> Result<()> {
     // Create the directory if it doesn't exist
     if let Some(parent) = Path::new(filepath).parent() {
         fs::create_dir_all(parent)?;
-        println!("Directory created or already exists: {:?}", parent);
     }
 
+    // Results arrive a page at a time, so only the first write of a run starts the file over
+    let first_write = STARTED_FILES.lock().unwrap().insert(filepath.to_string());
     let file = OpenOptions::new()
         .write(true)
         .create(true)
-        .truncate(true)
+        .truncate(first_write)
+        .append(!first_write)
         .open(filepath)?;
-    println!("File opened successfully: {}", filepath);
 
+    let empty = file.metadata()?.len() == 0;
     let mut wtr = Writer::from_writer(file);
     
-    wtr.write_record(fieldnames)?;
+    if empty {
+        wtr.write_record(columns.iter().map(|column| column.name))?;
+    }
     
     for record in data {
-        let mut row = Vec::new();
-        for field in fieldnames {
-            row.push(record[field].as_str().unwrap_or("").to_string());
-        }
-        wtr.write_record(&row)?;
+        wtr.write_record(csv_row(record, filepath, columns)?)?;
     }
     
     wtr.flush()?;
-    println!("Data successfully written to CSV");
     Ok(())
 }
 
-pub async fn save_to_sqlite(pool: &SqlitePool, data: &[Value], table_name: &str) -> Result<(), sqlx::Error> {
+/// `record`'s values in `columns` order. Nulls and missing values are left empty.
+fn csv_row(record: &Value, filepath: &str, columns: &[Column]) -> Result<Vec<String>> {
+    columns.iter().map(|column| Ok(column.cell(filepath, record.get(column.name))?.to_string())).collect()
+}
+
+/// Merges `data` into the CSV file at `filepath`, replacing the rows whose `key` columns match a
+/// new row and keeping the rest. Columns of an older file that `columns` no longer lists are
+/// dropped, and new ones are left empty in its rows.
+pub async fn upsert_csv(data: &[Value], filepath: &str, columns: &[Column], key: &[&str]) -> Result<()> {
+    if let Some(parent) = Path::new(filepath).parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let rows: Vec<Vec<String>> = data.iter().map(|record| csv_row(record, filepath, columns)).collect::<Result<_>>()?;
+    let key_indexes: Vec<usize> = key.iter().filter_map(|name| columns.iter().position(|column| column.name == *name)).collect();
+    let row_key = |row: &[String]| key_indexes.iter().map(|&i| row[i].clone()).collect::<Vec<_>>();
+    let replaced: BTreeSet<Vec<String>> = rows.iter().map(|row| row_key(row)).collect();
+
+    let mut kept = Vec::new();
+    if Path::new(filepath).exists() {
+        let mut reader = Reader::from_path(filepath)?;
+        let headers = reader.headers()?.clone();
+        let positions: Vec<Option<usize>> = columns.iter().map(|column| headers.iter().position(|header| header == column.name)).collect();
+        for record in reader.records() {
+            let record = record?;
+            let row: Vec<String> = positions.iter()
+                .map(|column| column.and_then(|i| record.get(i)).unwrap_or("").to_string())
+                .collect();
+            if !replaced.contains(&row_key(&row)) {
+                kept.push(row);
+            }
+        }
+    }
+
+    // Written beside the file and renamed over it, so an interrupted write leaves the old file
+    let partial = format!("{}.partial", filepath);
+    let mut wtr = Writer::from_path(&partial)?;
+    wtr.write_record(columns.iter().map(|column| column.name))?;
+    for row in kept.iter().chain(&rows) {
+        wtr.write_record(row)?;
+    }
+    wtr.flush()?;
+    drop(wtr);
+    fs::rename(&partial, filepath)?;
+
+    STARTED_FILES.lock().unwrap().insert(filepath.to_string());
+    Ok(())
+}
+
+/// SQLite's default cap on bound parameters in one statement.
+const SQLITE_MAX_VARIABLES: usize = 32766;
+
+/// A run of consecutive rows with the same columns, written with one statement.
+pub struct Batch<'a> {
+    pub columns: Vec<&'a str>,
+    pub rows: Vec<&'a Map<String, Value>>,
+}
+
+/// Splits `data` into batches of at most `batch_size` rows. A new batch also starts wherever
+/// the columns change, so every row in a batch fills the same column list.
+pub fn batches<'a>(data: &'a [Value], table_name: &str, batch_size: usize) -> Result<Vec<Batch<'a>>> {
+    let mut batches: Vec<Batch> = Vec::new();
     for record in data {
-        let columns = record.as_object().unwrap().keys().map(|s| s.as_str()).collect::<Vec<_>>().join(", ");
-        let placeholders = (0..record.as_object().unwrap().len()).map(|i| format!("${}", i + 1)).collect::<Vec<_>>().join(", ");
-        
-        let sql = format!("INSERT OR REPLACE INTO {} ({}) VALUES ({})", table_name, columns, placeholders);
-        
-        let mut query = sqlx::query(&sql);
-        for value in record.as_object().unwrap().values() {
-            query = query.bind(value.as_str().unwrap_or(""));
+        let record = record.as_object()
+            .ok_or_else(|| Error::Storage(format!("Cannot save a row that is not an object to {}", table_name)))?;
+        let columns: Vec<&str> = record.keys().map(String::as_str).collect();
+        match batches.last_mut() {
+            Some(batch) if batch.rows.len() < batch_size.max(1) && batch.columns == columns => batch.rows.push(record),
+            _ => batches.push(Batch { columns, rows: vec![record] }),
         }
-        
-        query.execute(pool).await?;
     }
-    
+    Ok(batches)
+}
+
+/// Upserts `data` into `table_name` in one transaction, with up to `batch_size` rows per
+/// `INSERT`. Either every row is saved or, if any statement fails, none are. Integers and
+/// booleans are bound as such; amounts keep their exact digits as text.
+pub async fn save_to_sqlite(pool: &SqlitePool, data: &[Value], table_name: &str, columns: &[Column], batch_size: usize) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    for batch in batches(data, table_name, batch_size)? {
+        let row_placeholders = format!("({})", vec!["?"; batch.columns.len()].join(", "));
+        let kinds: Vec<Kind> = batch.columns.iter().map(|name| kind_of(columns, name)).collect();
+        for rows in batch.rows.chunks(SQLITE_MAX_VARIABLES / batch.columns.len().max(1)) {
+            let sql = format!(
+                "INSERT OR REPLACE INTO {} ({}) VALUES {}",
+                table_name,
+                batch.columns.join(", "),
+                vec![row_placeholders.as_str(); rows.len()].join(", ")
+            );
+
+            let mut query = sqlx::query(&sql);
+            for row in rows {
+                for (name, kind) in batch.columns.iter().zip(&kinds) {
+                    query = match cell(table_name, name, *kind, row.get(*name))? {
+                        Cell::Null => query.bind(None::<String>),
+                        Cell::Integer(value) => query.bind(value),
+                        Cell::Boolean(value) => query.bind(value),
+                        Cell::Text(value) => query.bind(value),
+                    };
+                }
+            }
+            query.execute(&mut *tx).await?;
+        }
+    }
+    tx.commit().await?;
+
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,150 @@
+use crate::address::EvmAddress;
+use crate::api::provider::{Cursor, QueryKind};
+use crate::config::chains::Chain;
+use sqlx::postgres::PgPool;
+use sqlx::{Row, SqlitePool};
+
+/// How far one address's history of one kind has been synced on one chain.
+pub struct SyncState {
+    /// Provider that fetched the last page. Providers number their cursors differently, so a
+    /// walk by another provider starts over.
+    pub provider: String,
+    /// Where the next fetch starts: the next page of an unfinished walk, or just past the newest
+    /// row once the walk reached the end.
+    pub cursor: Cursor,
+    pub last_block: Option<u64>,
+    pub last_timestamp: Option<String>,
+    /// Rows saved across every sync of this history.
+    pub rows_synced: u64,
+    /// Whether the last walk reached the end of the history.
+    pub complete: bool,
+    /// Storage backends holding every row up to `cursor`, or `None` for state saved before
+    /// they were recorded.
+    pub sinks: Option<Vec<String>>,
+    updated_at: String,
+}
+
+impl SyncState {
+    /// A history nothing has been fetched of yet.
+    pub fn new(provider: &str) -> Self {
+        SyncState {
+            provider: provider.to_string(),
+            cursor: Cursor::default(),
+            last_block: None,
+            last_timestamp: None,
+            rows_synced: 0,
+            complete: false,
+            sinks: None,
+            updated_at: String::new(),
+        }
+    }
+}
+
+const COLUMNS: &str = "provider, cursor_block, cursor_position, cursor_sub_position, last_block, last_timestamp, rows_synced, complete, sinks, updated_at";
+
+fn split_sinks(sinks: Option<String>) -> Option<Vec<String>> {
+    sinks.map(|sinks| sinks.split(',').filter(|sink| !sink.is_empty()).map(str::to_string).collect())
+}
+
+/// Loads the saved state. When both databases hold one, the most recently saved wins, since a
+/// database that was disabled for a while has an older one.
+pub async fn load(chain: &Chain, address: &EvmAddress, kind: QueryKind, sqlite_pool: Option<&SqlitePool>, pg_pool: Option<&PgPool>) -> Result<Option<SyncState>, sqlx::Error> {
+    let mut states = Vec::new();
+
+    if let Some(pool) = sqlite_pool {
+        let state = sqlx::query(&format!("SELECT {} FROM sync_state WHERE chain = ? AND address = ? AND kind = ?", COLUMNS))
+            .bind(chain.name)
+            .bind(address.to_string())
+            .bind(kind.as_str())
+            .fetch_optional(pool).await?
+            .map(|row| SyncState {
+                provider: row.get("provider"),
+                cursor: Cursor {
+                    block_number: row.get::<i64, _>("cursor_block") as u64,
+                    position: row.get::<i64, _>("cursor_position") as u64,
+                    sub_position: row.get::<i64, _>("cursor_sub_position") as u64,
+                },
+                last_block: row.get::<Option<i64>, _>("last_block").map(|block| block as u64),
+                last_timestamp: row.get("last_timestamp"),
+                rows_synced: row.get::<i64, _>("rows_synced") as u64,
+                complete: row.get("complete"),
+                sinks: split_sinks(row.get("sinks")),
+                updated_at: row.get::<Option<String>, _>("updated_at").unwrap_or_default(),
+            });
+        states.extend(state);
+    }
+
+    if let Some(pool) = pg_pool {
+        let state = sqlx::query(&format!("SELECT {} FROM sync_state WHERE chain = $1 AND address = $2 AND kind = $3", COLUMNS))
+            .bind(chain.name)
+            .bind(address.to_string())
+            .bind(kind.as_str())
+            .fetch_optional(pool).await?
+            .map(|row| SyncState {
+                provider: row.get("provider"),
+                cursor: Cursor {
+                    block_number: row.get::<i64, _>("cursor_block") as u64,
+                    position: row.get::<i64, _>("cursor_position") as u64,
+                    sub_position: row.get::<i64, _>("cursor_sub_position") as u64,
+                },
+                last_block: row.get::<Option<i64>, _>("last_block").map(|block| block as u64),
+                last_timestamp: row.get("last_timestamp"),
+                rows_synced: row.get::<i64, _>("rows_synced") as u64,
+                complete: row.get("complete"),
+                sinks: split_sinks(row.get("sinks")),
+                updated_at: row.get::<Option<String>, _>("updated_at").unwrap_or_default(),
+            });
+        states.extend(state);
+    }
+
+    Ok(states.into_iter().max_by(|a, b| a.updated_at.cmp(&b.updated_at)))
+}
+
+/// Records `state` in every enabled database.
+pub async fn save(chain: &Chain, address: &EvmAddress, kind: QueryKind, state: &SyncState, sqlite_pool: Option<&SqlitePool>, pg_pool: Option<&PgPool>) -> Result<(), sqlx::Error> {
+    let updated_at = chrono::Utc::now().to_rfc3339();
+    let sinks = state.sinks.as_ref().map(|sinks| sinks.join(","));
+
+    if let Some(pool) = sqlite_pool {
+        sqlx::query(&format!("INSERT OR REPLACE INTO sync_state (chain, address, kind, {}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)", COLUMNS))
+            .bind(chain.name)
+            .bind(address.to_string())
+            .bind(kind.as_str())
+            .bind(&state.provider)
+            .bind(state.cursor.block_number as i64)
+            .bind(state.cursor.position as i64)
+            .bind(state.cursor.sub_position as i64)
+            .bind(state.last_block.map(|block| block as i64))
+            .bind(&state.last_timestamp)
+            .bind(state.rows_synced as i64)
+            .bind(state.complete)
+            .bind(&sinks)
+            .bind(&updated_at)
+            .execute(pool).await?;
+    }
+
+    if let Some(pool) = pg_pool {
+        sqlx::query(&format!(
+            "INSERT INTO sync_state (chain, address, kind, {}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+             ON CONFLICT (chain, address, kind) DO UPDATE SET provider = $4, cursor_block = $5, cursor_position = $6, cursor_sub_position = $7,
+                last_block = $8, last_timestamp = $9, rows_synced = $10, complete = $11, sinks = $12, updated_at = $13",
+            COLUMNS
+        ))
+            .bind(chain.name)
+            .bind(address.to_string())
+            .bind(kind.as_str())
+            .bind(&state.provider)
+            .bind(state.cursor.block_number as i64)
+            .bind(state.cursor.position as i64)
+            .bind(state.cursor.sub_position as i64)
+            .bind(state.last_block.map(|block| block as i64))
+            .bind(&state.last_timestamp)
+            .bind(state.rows_synced as i64)
+            .bind(state.complete)
+            .bind(&sinks)
+            .bind(&updated_at)
+            .execute(pool).await?;
+    }
+
+    Ok(())
+}
@@ -1,19 +1,28 @@
+mod abi;
+mod address;
 mod api;
+mod bitcoin;
 mod cli;
 mod config;
+mod error;
 mod helpers;
-mod db;
-mod models;
-mod utils;
-mod ui;
+mod record;
 
+use clap::Parser;
+use cli::Cli;
 use config::Config;
 use sqlx::sqlite::SqlitePool;
 use sqlx::postgres::PgPool;
+use colored::*;
+use std::process::ExitCode;
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut config = Config::new();
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let mut config = match Config::new() {
+        Ok(config) => config,
+        Err(e) => return report(e),
+    };
 
     let sqlite_pool = if config.save_as_sqlite {
         match SqlitePool::connect("sqlite:data/sqlite/sleuth.db").await {
@@ -50,7 +59,27 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         None
     };
 
-    cli::run_cli(&mut config, sqlite_pool.as_ref(), pg_pool.as_ref()).await?;
+    // `sleuth db` reports and applies migrations itself; everything else needs the latest schema
+    let migrated = match cli.manages_schema() {
+        true => Ok(()),
+        false => helpers::migrations::migrate_on_startup(sqlite_pool.as_ref(), pg_pool.as_ref()).await,
+    };
+    let result = match migrated {
+        Ok(()) => cli::run(cli, &mut config, sqlite_pool.as_ref(), pg_pool.as_ref()).await,
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => report(e),
+    }
+}
 
-    Ok(())
+/// Prints `e` with its hint and returns its exit code.
+fn report(e: error::Error) -> ExitCode {
+    eprintln!("{}", format!("Error: {}", e).red());
+    if let Some(hint) = e.hint() {
+        eprintln!("{}", hint);
+    }
+    ExitCode::from(e.exit_code())
 }
\ No newline at end of file
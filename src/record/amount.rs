@@ -0,0 +1,86 @@
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use std::fmt;
+
+/// An exact integer quantity such as a wei value or token amount. It is kept as decimal digits,
+/// so values past 64 (or 128) bits are never rounded, and serialized as a string because JSON
+/// numbers that large do not survive most parsers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Amount(String);
+
+impl Amount {
+    /// Parses a decimal integer, with an optional leading `-`.
+    pub fn parse(s: &str) -> Option<Self> {
+        let (sign, digits) = match s.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", s),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let digits = match digits.trim_start_matches('0') {
+            "" => return Some(Amount("0".to_string())),
+            trimmed => trimmed,
+        };
+        Some(Amount(format!("{}{}", sign, digits)))
+    }
+
+    /// Parses a `0x` hex quantity of any width, as JSON-RPC returns them.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        hex_to_decimal(hex).map(Amount)
+    }
+
+    /// Reads a JSON number, decimal string or hex string. Fractions and exponents are rejected
+    /// rather than rounded.
+    pub fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Number(n) => Self::parse(&n.to_string()),
+            Value::String(s) if s.starts_with("0x") => Self::from_hex(s),
+            Value::String(s) => Self::parse(s),
+            _ => None,
+        }
+    }
+}
+
+/// Converts a big-endian hex quantity of any width (such as a `uint256` word) to a decimal string.
+pub fn hex_to_decimal(hex: &str) -> Option<String> {
+    // Little-endian decimal digits, so carries can be pushed onto the end
+    let mut digits: Vec<u8> = vec![0];
+    for c in hex.trim_start_matches("0x").chars() {
+        let mut carry = c.to_digit(16)?;
+        for digit in digits.iter_mut() {
+            let value = *digit as u32 * 16 + carry;
+            *digit = (value % 10) as u8;
+            carry = value / 10;
+        }
+        while carry > 0 {
+            digits.push((carry % 10) as u8);
+            carry /= 10;
+        }
+    }
+    Some(digits.iter().rev().map(|digit| char::from(b'0' + digit)).collect())
+}
+
+impl From<u128> for Amount {
+    fn from(value: u128) -> Self {
+        Amount(value.to_string())
+    }
+}
+
+impl From<u64> for Amount {
+    fn from(value: u64) -> Self {
+        Amount(value.to_string())
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
@@ -0,0 +1,92 @@
+//! The columns of every dataset, in the order they are exported to CSV.
+
+use crate::record::{Column, Kind};
+
+const fn integer(name: &'static str) -> Column {
+    Column::new(name, Kind::Integer)
+}
+
+const fn amount(name: &'static str) -> Column {
+    Column::new(name, Kind::Amount)
+}
+
+const fn boolean(name: &'static str) -> Column {
+    Column::new(name, Kind::Boolean)
+}
+
+const fn timestamp(name: &'static str) -> Column {
+    Column::new(name, Kind::Timestamp)
+}
+
+const fn text(name: &'static str) -> Column {
+    Column::new(name, Kind::Text)
+}
+
+pub const ACCOUNTS: &[Column] = &[text("address"), timestamp("created_timestamp"), text("creator_address"), timestamp("last_active_timestamp"), text("type")];
+pub const TRANSACTIONS: &[Column] = &[
+    text("transaction_hash"), amount("base_fee_per_gas"), integer("block_number"), text("contract_address"), amount("fees_burned"),
+    amount("fees_rewarded"), amount("fees_saved"), text("from_address"), integer("gas_limit"), amount("gas_price"), integer("gas_used"),
+    text("input"), integer("internal_failed_transaction_count"), integer("internal_transaction_count"), integer("log_count"),
+    amount("max_fee_per_gas"), amount("max_priority_fee_per_gas"), integer("nonce"), text("output"), integer("position"),
+    timestamp("timestamp"), text("to_address"), amount("transaction_fee"), integer("type"), amount("value"),
+];
+pub const TOKEN_TRANSFERS: &[Column] = &[
+    text("transaction_hash"), integer("log_index"), integer("batch_index"), integer("block_number"), timestamp("timestamp"),
+    text("token_address"), text("token_standard"), text("from_address"), text("to_address"), amount("amount"), amount("token_id"),
+];
+pub const TRACES: &[Column] = &[
+    text("transaction_hash"), text("trace_address"), integer("block_number"), timestamp("timestamp"), integer("transaction_position"),
+    text("trace_type"), text("call_type"), text("from_address"), text("to_address"), amount("value"), text("input"), text("output"),
+    integer("gas"), integer("gas_used"), integer("subtraces"), text("error"),
+];
+pub const LOGS: &[Column] = &[
+    text("transaction_hash"), integer("log_index"), integer("block_number"), integer("transaction_position"), timestamp("timestamp"),
+    text("address"), text("data"), text("topic_0"), text("topic_1"), text("topic_2"), text("topic_3"),
+];
+pub const DECODED_CALLS: &[Column] = &[
+    text("transaction_hash"), text("trace_address"), integer("block_number"), timestamp("timestamp"), text("from_address"),
+    text("contract_address"), text("selector"), text("function_name"), text("signature"), text("arguments"),
+];
+pub const DECODED_EVENTS: &[Column] = &[
+    text("transaction_hash"), integer("log_index"), integer("block_number"), timestamp("timestamp"), text("contract_address"),
+    text("topic_0"), text("event_name"), text("signature"), text("arguments"),
+];
+pub const BLOCKS: &[Column] = &[
+    integer("block_number"), text("block_hash"), text("parent_hash"), timestamp("timestamp"), text("miner"), integer("gas_limit"),
+    integer("gas_used"), amount("base_fee_per_gas"), integer("transaction_count"),
+];
+pub const BITCOIN_ADDRESSES: &[Column] = &[
+    text("address"), integer("tx_count"), integer("funded_txo_count"), integer("funded_txo_sum"), integer("spent_txo_count"),
+    integer("spent_txo_sum"), integer("balance"), integer("mempool_tx_count"),
+];
+pub const BITCOIN_TRANSACTIONS: &[Column] = &[
+    text("txid"), integer("block_height"), text("block_hash"), timestamp("block_time"), integer("version"), integer("locktime"),
+    integer("size"), integer("weight"), integer("fee"), integer("input_count"), integer("output_count"), integer("input_value"),
+    integer("output_value"), boolean("is_coinbase"),
+];
+pub const BITCOIN_INPUTS: &[Column] = &[
+    text("txid"), integer("input_index"), text("prev_txid"), integer("prev_vout"), text("address"), integer("value"),
+    text("script_sig"), text("witness"), integer("sequence"),
+];
+pub const BITCOIN_OUTPUTS: &[Column] = &[
+    text("txid"), integer("output_index"), text("address"), integer("value"), text("script_pubkey"), text("script_type"),
+];
+pub const ENS_NAMES: &[Column] = &[text("address"), text("name"), text("resolved_at")];
+pub const ADDRESS_INPUTS: &[Column] = &[
+    text("address"), text("source_file"), integer("line"), text("input"), text("label"), text("notes"), text("imported_at"),
+];
+pub const SOLANA_ACCOUNTS: &[Column] = &[
+    text("address"), integer("lamports"), text("owner"), boolean("executable"), amount("rent_epoch"), integer("data_size"),
+];
+pub const SOLANA_TRANSACTIONS: &[Column] = &[
+    text("signature"), integer("slot"), timestamp("block_time"), integer("fee"), boolean("success"), text("error"), text("fee_payer"),
+    integer("account_count"), integer("instruction_count"), integer("compute_units_consumed"), text("version"),
+];
+pub const SOLANA_INSTRUCTIONS: &[Column] = &[
+    text("signature"), integer("instruction_index"), integer("inner_index"), text("program_id"), text("accounts"), text("data"),
+    integer("stack_height"),
+];
+pub const SOLANA_TOKEN_BALANCE_CHANGES: &[Column] = &[
+    text("signature"), integer("account_index"), text("token_account"), text("owner"), text("mint"), integer("decimals"),
+    amount("pre_amount"), amount("post_amount"), amount("change"),
+];
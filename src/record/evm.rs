@@ -0,0 +1,109 @@
+//! Typed rows for the EVM datasets built from JSON-RPC, matching `columns::ACCOUNTS`,
+//! `columns::TRANSACTIONS`, `columns::BLOCKS`, `columns::LOGS`, `columns::TOKEN_TRANSFERS` and
+//! `columns::TRACES`.
+
+use crate::record::{Amount, Timestamp};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct Account {
+    pub address: String,
+    pub created_timestamp: Option<Timestamp>,
+    pub creator_address: Option<String>,
+    pub last_active_timestamp: Option<Timestamp>,
+    #[serde(rename = "type")]
+    pub account_type: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct Transaction {
+    pub transaction_hash: String,
+    pub base_fee_per_gas: Option<Amount>,
+    pub block_number: u64,
+    pub contract_address: Option<String>,
+    pub fees_burned: Option<Amount>,
+    pub fees_rewarded: Option<Amount>,
+    pub fees_saved: Option<Amount>,
+    pub from_address: Option<String>,
+    pub gas_limit: Option<u64>,
+    pub gas_price: Amount,
+    pub gas_used: u64,
+    pub input: Option<String>,
+    pub internal_failed_transaction_count: Option<usize>,
+    pub internal_transaction_count: Option<usize>,
+    pub log_count: Option<usize>,
+    pub max_fee_per_gas: Option<Amount>,
+    pub max_priority_fee_per_gas: Option<Amount>,
+    pub nonce: Option<u64>,
+    pub output: Option<String>,
+    pub position: Option<u64>,
+    pub timestamp: Option<Timestamp>,
+    pub to_address: Option<String>,
+    pub transaction_fee: Amount,
+    #[serde(rename = "type")]
+    pub transaction_type: u64,
+    pub value: Option<Amount>,
+}
+
+#[derive(Serialize)]
+pub struct Block {
+    pub block_number: u64,
+    pub block_hash: Option<String>,
+    pub parent_hash: Option<String>,
+    pub timestamp: Option<Timestamp>,
+    pub miner: Option<String>,
+    pub gas_limit: Option<u64>,
+    pub gas_used: Option<u64>,
+    pub base_fee_per_gas: Option<Amount>,
+    pub transaction_count: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct Log {
+    pub transaction_hash: Option<String>,
+    pub log_index: Option<u64>,
+    pub block_number: u64,
+    pub transaction_position: Option<u64>,
+    pub timestamp: Option<Timestamp>,
+    pub address: Option<String>,
+    pub data: Option<String>,
+    pub topic_0: Option<String>,
+    pub topic_1: Option<String>,
+    pub topic_2: Option<String>,
+    pub topic_3: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TokenTransfer {
+    pub transaction_hash: Option<String>,
+    pub log_index: Option<u64>,
+    pub batch_index: usize,
+    pub block_number: Option<u64>,
+    pub timestamp: Option<Timestamp>,
+    pub token_address: Option<String>,
+    pub token_standard: &'static str,
+    pub from_address: Option<String>,
+    pub to_address: Option<String>,
+    pub amount: Option<Amount>,
+    pub token_id: Option<Amount>,
+}
+
+#[derive(Serialize)]
+pub struct Trace {
+    pub transaction_hash: Option<String>,
+    pub trace_address: String,
+    pub block_number: Option<u64>,
+    pub timestamp: Option<Timestamp>,
+    pub transaction_position: Option<u64>,
+    pub trace_type: &'static str,
+    pub call_type: Option<String>,
+    pub from_address: Option<String>,
+    pub to_address: Option<String>,
+    pub value: Option<Amount>,
+    pub input: Option<String>,
+    pub output: Option<String>,
+    pub gas: Option<u64>,
+    pub gas_used: Option<u64>,
+    pub subtraces: Option<u64>,
+    pub error: Option<String>,
+}
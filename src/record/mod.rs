@@ -0,0 +1,143 @@
+pub mod amount;
+pub mod columns;
+pub mod evm;
+pub mod timestamp;
+
+pub use amount::Amount;
+pub use timestamp::Timestamp;
+
+use crate::error::{Error, Result};
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// What a column holds, which decides how its values are checked and written to each sink.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// A count, index or quantity that fits in a signed 64-bit integer.
+    Integer,
+    /// An exact integer that can exceed 64 bits, such as a wei value.
+    Amount,
+    Boolean,
+    /// A point in time, written as RFC 3339 in UTC.
+    Timestamp,
+    Text,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Kind::Integer => "an integer",
+            Kind::Amount => "an integer amount",
+            Kind::Boolean => "a boolean",
+            Kind::Timestamp => "a timestamp",
+            Kind::Text => "text",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Column {
+    pub name: &'static str,
+    pub kind: Kind,
+}
+
+impl Column {
+    pub const fn new(name: &'static str, kind: Kind) -> Self {
+        Column { name, kind }
+    }
+
+    /// Converts a row's value for this column into the cell stored for it.
+    pub fn cell(&self, table: &str, value: Option<&Value>) -> Result<Cell> {
+        cell(table, self.name, self.kind, value)
+    }
+}
+
+/// Converts a row's value for `table`.`name` into the cell stored for it. Numbers may arrive as
+/// JSON numbers, decimal strings or `0x` hex quantities; anything that cannot be stored as `kind`
+/// without changing its value is rejected.
+pub fn cell(table: &str, name: &str, kind: Kind, value: Option<&Value>) -> Result<Cell> {
+    let value = match value {
+        None | Some(Value::Null) => return Ok(Cell::Null),
+        Some(value) => value,
+    };
+
+    let cell = match kind {
+        Kind::Integer => integer(value).map(Cell::Integer),
+        Kind::Amount => Amount::from_value(value).map(|amount| Cell::Text(amount.to_string())),
+        Kind::Boolean => boolean(value).map(Cell::Boolean),
+        Kind::Timestamp => Timestamp::from_value(value).map(|timestamp| Cell::Text(timestamp.to_string())),
+        Kind::Text => Some(Cell::Text(match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        })),
+    };
+    cell.ok_or_else(|| Error::Storage(format!("Cannot store {} in {}.{}, which holds {}", value, table, name, kind)))
+}
+
+/// Reshapes `row` into exactly `columns`, in order, with each value converted as it is stored:
+/// integers and booleans as JSON numbers and booleans, amounts and timestamps as their exact text.
+/// Columns the row lacks are null and fields the dataset does not declare are dropped.
+pub fn typed_row(table: &str, columns: &[Column], row: &Value) -> Result<Value> {
+    let mut typed = Map::new();
+    for column in columns {
+        let value = match column.cell(table, row.get(column.name))? {
+            Cell::Null => Value::Null,
+            Cell::Integer(value) => Value::from(value),
+            Cell::Boolean(value) => Value::from(value),
+            Cell::Text(value) => Value::from(value),
+        };
+        typed.insert(column.name.to_string(), value);
+    }
+    Ok(Value::Object(typed))
+}
+
+/// The kind of `name` in `columns`. Columns a dataset does not declare are stored as text.
+pub fn kind_of(columns: &[Column], name: &str) -> Kind {
+    columns.iter().find(|column| column.name == name).map_or(Kind::Text, |column| column.kind)
+}
+
+/// One value ready to be written: integers and booleans are bound natively, everything else as
+/// its exact text.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Cell {
+    Null,
+    Integer(i64),
+    Boolean(bool),
+    Text(String),
+}
+
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cell::Null => Ok(()),
+            Cell::Integer(value) => write!(f, "{}", value),
+            Cell::Boolean(value) => write!(f, "{}", value),
+            Cell::Text(value) => f.write_str(value),
+        }
+    }
+}
+
+fn integer(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(n) => n.as_i64(),
+        Value::String(s) => match s.strip_prefix("0x") {
+            Some(hex) => i64::from_str_radix(hex, 16).ok(),
+            None => s.parse().ok(),
+        },
+        _ => None,
+    }
+}
+
+fn boolean(value: &Value) -> Option<bool> {
+    match value {
+        Value::Bool(b) => Some(*b),
+        Value::Number(n) => match n.as_u64()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        },
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
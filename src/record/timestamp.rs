@@ -0,0 +1,55 @@
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use std::fmt;
+
+/// A point in time in UTC, written as RFC 3339 the way Transpose returns timestamps
+/// (`2024-01-31T12:00:00Z`), with fractional seconds only when there are any.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timestamp(DateTime<Utc>);
+
+impl Timestamp {
+    pub fn from_unix(seconds: u64) -> Option<Self> {
+        DateTime::from_timestamp(i64::try_from(seconds).ok()?, 0).map(Timestamp)
+    }
+
+    /// Parses RFC 3339, or a timestamp without an offset (`2024-01-31 12:00:00`) taken as UTC.
+    pub fn parse(s: &str) -> Option<Self> {
+        if let Ok(parsed) = DateTime::parse_from_rfc3339(s) {
+            return Some(Timestamp(parsed.with_timezone(&Utc)));
+        }
+        ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"].iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+            .map(|naive| Timestamp(naive.and_utc()))
+    }
+
+    pub fn unix_micros(&self) -> i64 {
+        self.0.timestamp_micros()
+    }
+
+    /// The UTC date, as `2024-01-31`.
+    pub fn date(&self) -> String {
+        self.0.format("%Y-%m-%d").to_string()
+    }
+
+    /// Reads a timestamp string or a number of unix seconds.
+    pub fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Number(n) => Self::from_unix(n.as_u64()?),
+            Value::String(s) => Self::parse(s),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
@@ -1,3 +0,0 @@
-SELECT *
-FROM ethereum.accounts
-WHERE address = '{{address}}'
\ No newline at end of file
@@ -0,0 +1,8 @@
+SELECT
+    a.address,
+    a.created_timestamp,
+    a.creator_address,
+    a.last_active_timestamp,
+    a.type
+FROM {{schema:enum}}.accounts a
+WHERE a.address = {{address:address}}
\ No newline at end of file
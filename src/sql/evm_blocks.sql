@@ -0,0 +1,15 @@
+SELECT
+    b.block_number,
+    b.block_hash,
+    b.parent_hash,
+    b.timestamp,
+    b.miner,
+    b.gas_limit,
+    b.gas_used,
+    b.base_fee_per_gas,
+    b.transaction_count
+FROM {{schema:enum}}.blocks b
+WHERE b.block_number >= {{from_block:integer}}
+  AND b.block_number <= {{to_block:integer}}
+ORDER BY b.block_number ASC
+LIMIT {{limit:integer}}
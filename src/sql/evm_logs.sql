@@ -0,0 +1,17 @@
+SELECT
+    l.transaction_hash,
+    l.log_index,
+    l.block_number,
+    l.transaction_position,
+    l.timestamp,
+    l.address,
+    l.data,
+    l.topic_0,
+    l.topic_1,
+    l.topic_2,
+    l.topic_3
+FROM {{schema:enum}}.logs l
+WHERE l.address = {{address:address}}
+  AND (l.block_number, l.log_index) >= ({{from_block:integer}}, {{from_position:integer}})
+ORDER BY l.block_number, l.log_index
+LIMIT {{limit:integer}}
@@ -0,0 +1,37 @@
+SELECT * FROM (
+    SELECT
+        t.transaction_hash,
+        t.log_index,
+        0 AS batch_index,
+        t.block_number,
+        t.timestamp,
+        t.token_address,
+        'erc20' AS token_standard,
+        t.from_address,
+        t.to_address,
+        t.quantity AS amount,
+        NULL AS token_id
+    FROM {{schema:enum}}.token_transfers t
+    WHERE t.from_address = {{wallet_address:address}} OR t.to_address = {{wallet_address:address}}
+
+    UNION ALL
+
+    SELECT
+        n.transaction_hash,
+        n.log_index,
+        ROW_NUMBER() OVER (PARTITION BY n.transaction_hash, n.log_index ORDER BY n.token_id) - 1 AS batch_index,
+        n.block_number,
+        n.timestamp,
+        n.contract_address AS token_address,
+        c.standard AS token_standard,
+        n.from_address,
+        n.to_address,
+        n.quantity AS amount,
+        n.token_id
+    FROM {{schema:enum}}.nft_transfers n
+    LEFT JOIN {{schema:enum}}.collections c ON c.contract_address = n.contract_address
+    WHERE n.from_address = {{wallet_address:address}} OR n.to_address = {{wallet_address:address}}
+) transfers
+WHERE (block_number, log_index, batch_index) >= ({{from_block:integer}}, {{from_position:integer}}, {{from_sub_position:integer}})
+ORDER BY block_number, log_index, batch_index
+LIMIT {{limit:integer}}
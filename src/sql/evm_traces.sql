@@ -0,0 +1,30 @@
+SELECT
+    t.transaction_hash,
+    array_to_string(t.trace_address, ',') AS trace_address,
+    t.block_number,
+    t.timestamp,
+    t.transaction_position,
+    t.trace_type,
+    t.call_type,
+    t.from_address,
+    t.to_address,
+    t.value,
+    t.input,
+    t.output,
+    t.gas,
+    t.gas_used,
+    t.subtraces,
+    t.error
+FROM {{schema:enum}}.traces t
+WHERE t.transaction_hash IN (
+    SELECT page.transaction_hash
+    FROM (
+        SELECT DISTINCT a.block_number, a.transaction_position, a.transaction_hash
+        FROM {{schema:enum}}.traces a
+        WHERE (a.from_address = {{wallet_address:address}} OR a.to_address = {{wallet_address:address}})
+          AND (a.block_number, a.transaction_position) >= ({{from_block:integer}}, {{from_position:integer}})
+        ORDER BY a.block_number, a.transaction_position
+        LIMIT {{limit:integer}}
+    ) page
+)
+ORDER BY t.block_number, t.transaction_position, t.trace_address
@@ -24,9 +24,8 @@ SELECT
     t.transaction_fee,
     t.type,
     t.value
-FROM ethereum.transactions t
-WHERE t.from_address = '{{wallet_address}}'
-   OR t.to_address = '{{wallet_address}}'
-ORDER BY t.timestamp DESC
-LIMIT {{limit}}
-OFFSET {{offset}}
\ No newline at end of file
+FROM {{schema:enum}}.transactions t
+WHERE (t.from_address = {{wallet_address:address}} OR t.to_address = {{wallet_address:address}})
+  AND (t.block_number, t.position) >= ({{from_block:integer}}, {{from_position:integer}})
+ORDER BY t.block_number, t.position
+LIMIT {{limit:integer}}
\ No newline at end of file